@@ -0,0 +1,38 @@
+#!/usr/bin/env python3
+"""Fails when any criterion benchmark regressed by more than the given threshold.
+
+Usage: check_bench_regressions.py <criterion-dir> <threshold>
+
+Reads the change estimates written by `cargo bench -- --baseline <name>`. A benchmark counts
+as regressed only when the whole confidence interval of the mean change lies above the
+threshold, so that the runner noise alone does not fail the check.
+"""
+
+import json
+import pathlib
+import sys
+
+
+def main():
+    root = pathlib.Path(sys.argv[1])
+    threshold = float(sys.argv[2])
+
+    regressions = []
+    for path in sorted(root.glob("**/change/estimates.json")):
+        mean = json.loads(path.read_text())["mean"]
+        change = mean["point_estimate"]
+        lower_bound = mean["confidence_interval"]["lower_bound"]
+        name = path.parent.parent.relative_to(root)
+        print(f"{name}: {change:+.2%} (lower bound {lower_bound:+.2%})")
+        if lower_bound > threshold:
+            regressions.append(name)
+
+    if regressions:
+        print(f"Regressions above {threshold:.0%}:")
+        for name in regressions:
+            print(f"  {name}")
+        sys.exit(1)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,88 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use poster::fuzzing::{decode_connack, decode_publish, decode_var_size_int};
+
+// Note: the decode functions copy the input into the owned buffer first,
+// the copy is included in the measurements.
+
+fn encode_var_size_int(mut val: usize, buf: &mut Vec<u8>) {
+    loop {
+        let byte = (val % 128) as u8;
+        val /= 128;
+        if val == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+fn publish_packet(payload_len: usize) -> Vec<u8> {
+    const VARIABLE_HEADER: [u8; 6] = [
+        0, 3, b'a', b'/', b'b', // Topic name
+        0,    // Property length
+    ];
+
+    let mut packet = vec![0x30];
+    encode_var_size_int(VARIABLE_HEADER.len() + payload_len, &mut packet);
+    packet.extend_from_slice(&VARIABLE_HEADER);
+    packet.resize(packet.len() + payload_len, 0xab);
+    packet
+}
+
+fn connack(c: &mut Criterion) {
+    const MINIMAL: [u8; 5] = [0x20, 3, 0, 0, 0];
+    const WITH_PROPERTIES: [u8; 16] = [
+        0x20, 14, // Fixed header
+        0, 0,  // Flags, reason
+        11, // Property length
+        0x21, 0, 10, // Receive maximum
+        0x27, 0, 0, 0x10, 0, // Maximum packet size
+        0x22, 0, 5, // Topic alias maximum
+    ];
+
+    let mut group = c.benchmark_group("connack");
+    for (name, packet) in [
+        ("minimal", &MINIMAL[..]),
+        ("with_properties", &WITH_PROPERTIES[..]),
+    ] {
+        decode_connack(packet).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(name), packet, |b, packet| {
+            b.iter(|| decode_connack(black_box(packet)))
+        });
+    }
+    group.finish();
+}
+
+fn publish(c: &mut Criterion) {
+    let mut group = c.benchmark_group("publish");
+    for payload_len in [64, 1024, 64 * 1024] {
+        let packet = publish_packet(payload_len);
+        decode_publish(&packet).unwrap();
+
+        group.throughput(Throughput::Bytes(packet.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(payload_len),
+            &packet,
+            |b, packet| b.iter(|| decode_publish(black_box(packet))),
+        );
+    }
+    group.finish();
+}
+
+fn var_size_int(c: &mut Criterion) {
+    let mut group = c.benchmark_group("var_size_int");
+    for (width, val) in [(1, 0x7f), (2, 0x3fff), (3, 0x1f_ffff), (4, 0xfff_ffff)] {
+        let mut buf = Vec::new();
+        encode_var_size_int(val, &mut buf);
+        assert_eq!(buf.len(), width);
+        decode_var_size_int(&buf).unwrap();
+
+        group.bench_with_input(BenchmarkId::from_parameter(width), &buf, |b, buf| {
+            b.iter(|| decode_var_size_int(black_box(buf)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, connack, publish, var_size_int);
+criterion_main!(benches);
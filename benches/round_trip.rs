@@ -0,0 +1,62 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures::StreamExt;
+use poster::{
+    testing::TestBroker, ConnectOpts, Context, PublishOpts, QoS, SubscribeOpts, SubscriptionOpts,
+};
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+fn publish_subscribe(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let broker = TestBroker::new();
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let (srx, stx) = tokio::io::split(server);
+    let (crx, ctx) = tokio::io::split(client);
+
+    let (mut context, mut handle) = Context::new();
+    context.set_up((crx.compat(), ctx.compat_write()));
+
+    rt.spawn(async move { broker.serve((srx.compat(), stx.compat_write())).await });
+    rt.spawn(async move {
+        context
+            .connect(ConnectOpts::new().client_identifier("bench"))
+            .await
+            .unwrap();
+        context.run().await
+    });
+
+    let mut stream = rt.block_on(async {
+        handle
+            .subscribe(SubscribeOpts::new().subscription("a/b", SubscriptionOpts::new()))
+            .await
+            .unwrap()
+            .stream()
+    });
+
+    let payload = [0xab; 64];
+    let mut group = c.benchmark_group("round_trip");
+    for qos in [QoS::AtMostOnce, QoS::AtLeastOnce] {
+        group.bench_function(format!("{:?}", qos), |b| {
+            b.iter(|| {
+                rt.block_on(async {
+                    handle
+                        .publish(
+                            PublishOpts::new()
+                                .topic_name("a/b")
+                                .qos(qos)
+                                .payload(&payload),
+                        )
+                        .await
+                        .unwrap();
+                    stream.next().await.unwrap()
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, publish_subscribe);
+criterion_main!(benches);
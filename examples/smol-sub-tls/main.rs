@@ -0,0 +1,89 @@
+use async_native_tls::{Certificate, TlsConnector};
+use clap::Parser;
+use poster::{error::MqttError, prelude::*, ConnectOpts, Context, SubscribeOpts, SubscriptionOpts};
+use smol::{io, net};
+use std::{error::Error, fs, path::PathBuf, str};
+
+/// poster-rs subscription example using smol over TLS
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Broker host name, verified against the broker certificate
+    #[arg(long)]
+    host: String,
+
+    /// Broker port
+    #[arg(long, default_value_t = 8883)]
+    port: u16,
+
+    /// CA certificate (PEM) used to verify the broker, in addition to the system ones
+    #[arg(long)]
+    ca_cert: Option<PathBuf>,
+
+    /// Topic
+    #[arg(long)]
+    topic: String,
+
+    /// Username
+    #[arg(long)]
+    username: Option<String>,
+
+    /// Password
+    #[arg(long)]
+    password: Option<String>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    smol::block_on(async {
+        let (mut context, mut client) = Context::new();
+
+        let subscription_task = smol::spawn(async move {
+            // Set subscription parameters
+            let opts = SubscribeOpts::new().subscription(&args.topic, SubscriptionOpts::default());
+
+            // Send subscription request to the broker
+            let mut subscription = client.subscribe(opts).await?.stream();
+
+            // Asynchronously iterate over messages published to the subscribed topic
+            while let Some(msg) = subscription.next().await {
+                println!(
+                    "[{}] {}",
+                    msg.topic_name(),
+                    str::from_utf8(msg.payload()).unwrap_or("<invalid UTF8 string>")
+                );
+            }
+
+            Ok::<(), MqttError>(())
+        });
+
+        let mut connector = TlsConnector::new();
+
+        if let Some(path) = args.ca_cert.as_ref() {
+            connector = connector.add_root_certificate(Certificate::from_pem(&fs::read(path)?)?);
+        }
+
+        // Perform the TLS handshake, the resulting stream is AsyncRead + AsyncWrite
+        // like the plain TCP one, so it is split and passed to the context the same way.
+        let stream = net::TcpStream::connect(format!("{}:{}", args.host, args.port)).await?;
+        let stream = connector.connect(&args.host, stream).await?;
+        let (rx, tx) = io::split(stream);
+
+        let mut opts = ConnectOpts::new();
+
+        if let Some(username) = args.username.as_ref() {
+            opts = opts.username(username);
+        }
+
+        if let Some(password) = args.password.as_ref() {
+            opts = opts.password(password.as_bytes());
+        }
+
+        context.set_up((rx, tx)).connect(opts).await?;
+        context.run().await?;
+
+        subscription_task.await?;
+        Ok(())
+    })
+}
@@ -1,4 +1,4 @@
-use clap::{arg, command, Parser};
+use clap::Parser;
 use poster::{error::MqttError, prelude::*, ConnectOpts, Context, SubscribeOpts, SubscriptionOpts};
 use smol::{io, net};
 use std::{error::Error, str};
@@ -58,12 +58,12 @@ fn main() -> Result<(), Box<dyn Error>> {
 
         let mut opts = ConnectOpts::new();
 
-        if args.username.is_some() {
-            opts = opts.username(args.username.as_ref().unwrap());
+        if let Some(username) = args.username.as_ref() {
+            opts = opts.username(username);
         }
 
-        if args.password.is_some() {
-            opts = opts.password(args.password.as_ref().unwrap().as_bytes());
+        if let Some(password) = args.password.as_ref() {
+            opts = opts.password(password.as_bytes());
         }
 
         context.set_up((rx, tx)).connect(opts).await?;
@@ -0,0 +1,124 @@
+use clap::Parser;
+use poster::{
+    error::MqttError, ConnectOpts, Context, DisconnectOpts, OwnedPublishOpts, PublishInterceptor,
+    PublishOpts,
+};
+use std::{error::Error, mem};
+use tokio::net;
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+/// Compresses the payloads larger than the threshold with LZ4, marking the compressed
+/// messages with the `content-encoding: lz4` user property. The uncompressed size is
+/// prepended to the compressed data, see `lz4_flex::compress_prepend_size`.
+struct Lz4Compression {
+    threshold: usize,
+}
+
+impl PublishInterceptor for Lz4Compression {
+    fn intercept(&self, opts: &mut OwnedPublishOpts) -> Result<(), MqttError> {
+        let payload = opts.get_payload().unwrap_or_default();
+        if payload.len() <= self.threshold {
+            return Ok(());
+        }
+
+        let compressed = lz4_flex::compress_prepend_size(payload);
+        println!(
+            "Payload compressed from {} to {} bytes.",
+            payload.len(),
+            compressed.len()
+        );
+
+        *opts = mem::take(opts)
+            .payload(compressed)
+            .payload_format_indicator(false)
+            .user_property(("content-encoding", "lz4"));
+        Ok(())
+    }
+}
+
+/// poster-rs publish example using tokio, compressing large payloads
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Broker IP
+    #[arg(long)]
+    host: String,
+
+    /// Broker port
+    #[arg(long, default_value_t = 1883)]
+    port: u16,
+
+    /// Topic
+    #[arg(long)]
+    topic: String,
+
+    /// Username
+    #[arg(long)]
+    username: Option<String>,
+
+    /// Password
+    #[arg(long)]
+    password: Option<String>,
+
+    /// Message
+    #[arg(long)]
+    message: String,
+
+    /// Number of times the message is repeated in the payload
+    #[arg(long, default_value_t = 1)]
+    repeat: usize,
+
+    /// Payloads larger than the threshold are compressed
+    #[arg(long, default_value_t = 1024)]
+    threshold: usize,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let args = Args::parse();
+
+    let (mut context, mut client) = Context::new();
+    context.add_publish_interceptor(Lz4Compression {
+        threshold: args.threshold,
+    });
+
+    let stream = net::TcpStream::connect(format!("{}:{}", args.host, args.port)).await?;
+    let (rx, tx) = stream.into_split();
+
+    let mut opts = ConnectOpts::new();
+
+    if let Some(username) = args.username.as_ref() {
+        opts = opts.username(username);
+    }
+
+    if let Some(password) = args.password.as_ref() {
+        opts = opts.password(password.as_bytes());
+    }
+
+    context
+        .set_up((rx.compat(), tx.compat_write()))
+        .connect(opts)
+        .await?;
+
+    let ctx_task = tokio::spawn(async move {
+        match context.run().await {
+            Err(MqttError::SocketClosed(_)) => {}
+            Err(err) => eprintln!("Error: \"{}\".", err),
+            _ => {}
+        }
+    });
+
+    // Interceptors are applied by the handle, the message is published as usual.
+    let payload = args.message.repeat(args.repeat);
+    client
+        .publish(
+            PublishOpts::new()
+                .topic_name(&args.topic)
+                .payload(payload.as_bytes()),
+        )
+        .await?;
+    client.disconnect(DisconnectOpts::default()).await?;
+
+    ctx_task.await?;
+    Ok(())
+}
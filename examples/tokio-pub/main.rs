@@ -1,4 +1,4 @@
-use clap::{arg, command, Parser};
+use clap::Parser;
 use poster::{error::MqttError, ConnectOpts, Context, DisconnectOpts, PublishOpts, QoS};
 use std::{
     error::Error,
@@ -61,12 +61,12 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
 
         let mut opts = ConnectOpts::new();
 
-        if args.username.is_some() {
-            opts = opts.username(args.username.as_ref().unwrap());
+        if let Some(username) = args.username.as_ref() {
+            opts = opts.username(username);
         }
 
-        if args.password.is_some() {
-            opts = opts.password(args.password.as_ref().unwrap().as_bytes());
+        if let Some(password) = args.password.as_ref() {
+            opts = opts.password(password.as_bytes());
         }
 
         context
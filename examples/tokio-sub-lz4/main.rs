@@ -0,0 +1,106 @@
+use clap::Parser;
+use poster::{
+    error::{CodecError, ConversionError, InvalidEncoding, MqttError},
+    prelude::*,
+    ConnectOpts, Context, PublishData, ReceiveInterceptor, SubscribeOpts, SubscriptionOpts,
+};
+use std::{error::Error, str};
+use tokio::net;
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+/// Decompresses the payloads of the messages marked with the `content-encoding: lz4`
+/// user property, as published by the `tokio-pub-lz4` example.
+struct Lz4Decompression;
+
+impl ReceiveInterceptor for Lz4Decompression {
+    fn intercept(&self, msg: &mut PublishData) -> Result<(), MqttError> {
+        if !msg
+            .user_properties()
+            .get("content-encoding")
+            .any(|encoding| encoding == "lz4")
+        {
+            return Ok(());
+        }
+
+        // Malformed payload drops the message.
+        let decompressed = lz4_flex::decompress_size_prepended(msg.payload()).map_err(|_| {
+            MqttError::CodecError(CodecError::ConversionError(
+                ConversionError::InvalidEncoding(InvalidEncoding),
+            ))
+        })?;
+
+        msg.set_payload(decompressed);
+        Ok(())
+    }
+}
+
+/// poster-rs subscription example using tokio, decompressing LZ4 payloads
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Broker IP
+    #[arg(long)]
+    host: String,
+
+    /// Broker port
+    #[arg(long, default_value_t = 1883)]
+    port: u16,
+
+    /// Topic
+    #[arg(long)]
+    topic: String,
+
+    /// Username
+    #[arg(long)]
+    username: Option<String>,
+
+    /// Password
+    #[arg(long)]
+    password: Option<String>,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let args = Args::parse();
+
+    let (mut context, mut client) = Context::new();
+    context.add_receive_interceptor(Lz4Decompression);
+
+    let subscription_task = tokio::spawn(async move {
+        let opts = SubscribeOpts::new().subscription(&args.topic, SubscriptionOpts::default());
+        let mut subscription = client.subscribe(opts).await?.stream();
+
+        // Messages arrive already decompressed.
+        while let Some(msg) = subscription.next().await {
+            println!(
+                "[{}] {}",
+                msg.topic_name(),
+                str::from_utf8(msg.payload()).unwrap_or("<invalid UTF8 string>")
+            );
+        }
+
+        Ok::<(), MqttError>(())
+    });
+
+    let stream = net::TcpStream::connect(format!("{}:{}", args.host, args.port)).await?;
+    let (rx, tx) = stream.into_split();
+
+    let mut opts = ConnectOpts::new();
+
+    if let Some(username) = args.username.as_ref() {
+        opts = opts.username(username);
+    }
+
+    if let Some(password) = args.password.as_ref() {
+        opts = opts.password(password.as_bytes());
+    }
+
+    context
+        .set_up((rx.compat(), tx.compat_write()))
+        .connect(opts)
+        .await?;
+    context.run().await?;
+
+    subscription_task.await??;
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use clap::Parser;
+use poster::{error::MqttError, prelude::*, ConnectOpts, Context, SubscribeOpts, SubscriptionOpts};
+use std::{error::Error, path::PathBuf, str, sync::Arc};
+use tokio::net;
+use tokio_rustls::{
+    rustls::{
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        crypto::{self, CryptoProvider},
+        pki_types::{pem::PemObject, CertificateDer, ServerName, UnixTime},
+        ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    },
+    TlsConnector,
+};
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+/// poster-rs subscription example using tokio over TLS
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Broker host name, verified against the broker certificate
+    #[arg(long)]
+    host: String,
+
+    /// Broker port
+    #[arg(long, default_value_t = 8883)]
+    port: u16,
+
+    /// CA certificate (PEM) used to verify the broker
+    #[arg(long, required_unless_present = "no_verify")]
+    ca_cert: Option<PathBuf>,
+
+    /// INSECURE: skip the broker certificate verification, for development only
+    #[arg(long)]
+    no_verify: bool,
+
+    /// Topic
+    #[arg(long)]
+    topic: String,
+
+    /// Username
+    #[arg(long)]
+    username: Option<String>,
+
+    /// Password
+    #[arg(long)]
+    password: Option<String>,
+}
+
+/// Accepts any broker certificate. Handshake signatures are still checked,
+/// but the identity of the broker is not, so the connection may be intercepted.
+#[derive(Debug)]
+struct NoVerifier(CryptoProvider);
+
+impl ServerCertVerifier for NoVerifier {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+fn make_tls_config(args: &Args) -> Result<ClientConfig, Box<dyn Error + Send + Sync>> {
+    let builder = ClientConfig::builder_with_provider(Arc::new(crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()?;
+
+    if args.no_verify {
+        eprintln!("Warning: broker certificate verification is disabled.");
+        return Ok(builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(
+                NoVerifier(crypto::ring::default_provider()),
+            ))
+            .with_no_client_auth());
+    }
+
+    let mut roots = RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(args.ca_cert.as_ref().unwrap())? {
+        roots.add(cert?)?;
+    }
+
+    Ok(builder.with_root_certificates(roots).with_no_client_auth())
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let args = Args::parse();
+
+    let (mut context, mut client) = Context::new();
+
+    let topic = args.topic.clone();
+    let subscription_task = tokio::spawn(async move {
+        // Set subscription parameters
+        let opts = SubscribeOpts::new().subscription(&topic, SubscriptionOpts::default());
+
+        // Send subscription request to the broker
+        let mut subscription = client.subscribe(opts).await?.stream();
+
+        // Asynchronously iterate over messages published to the subscribed topic
+        while let Some(msg) = subscription.next().await {
+            println!(
+                "[{}] {}",
+                msg.topic_name(),
+                str::from_utf8(msg.payload()).unwrap_or("<invalid UTF8 string>")
+            );
+        }
+
+        Ok::<(), MqttError>(())
+    });
+
+    let connector = TlsConnector::from(Arc::new(make_tls_config(&args)?));
+    let server_name = ServerName::try_from(args.host.clone())?;
+
+    // Perform the TLS handshake, the resulting stream is then split and converted
+    // to the futures traits the same way as the plain TCP one.
+    let stream = net::TcpStream::connect(format!("{}:{}", args.host, args.port)).await?;
+    let stream = connector.connect(server_name, stream).await?;
+    let (rx, tx) = tokio::io::split(stream);
+
+    let mut opts = ConnectOpts::new();
+
+    if let Some(username) = args.username.as_ref() {
+        opts = opts.username(username);
+    }
+
+    if let Some(password) = args.password.as_ref() {
+        opts = opts.password(password.as_bytes());
+    }
+
+    context
+        .set_up((rx.compat(), tx.compat_write()))
+        .connect(opts)
+        .await?;
+    context.run().await?;
+
+    subscription_task.await??;
+    Ok(())
+}
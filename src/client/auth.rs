@@ -0,0 +1,96 @@
+use crate::client::{error::MqttError, owned_opts::OwnedAuthOpts, rsp::AuthRsp};
+use core::future::Future;
+
+/// Client side of the extended authentication exchange, driven by
+/// [connect_with_auth](crate::Context::connect_with_auth). Implementations may
+/// define the method as `async fn`.
+///
+pub trait AuthHandler {
+    /// Responds to the AUTH packet received from the broker. The returned [options](OwnedAuthOpts)
+    /// are sent back in the AUTH packet, typically with the
+    /// [ContinueAuthentication](crate::reason::AuthReason::ContinueAuthentication) reason.
+    /// Returning an error aborts the connection attempt with that error.
+    ///
+    fn handle_challenge(
+        &mut self,
+        rsp: AuthRsp,
+    ) -> impl Future<Output = Result<OwnedAuthOpts, MqttError>> + Send;
+}
+
+impl<H> AuthHandler for &mut H
+where
+    H: AuthHandler,
+{
+    fn handle_challenge(
+        &mut self,
+        rsp: AuthRsp,
+    ) -> impl Future<Output = Result<OwnedAuthOpts, MqttError>> + Send {
+        (**self).handle_challenge(rsp)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{reason::AuthReason, ConnectOpts, Context};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+    struct Challenges {
+        received: Vec<Vec<u8>>,
+    }
+
+    impl AuthHandler for Challenges {
+        async fn handle_challenge(&mut self, rsp: AuthRsp) -> Result<OwnedAuthOpts, MqttError> {
+            self.received
+                .push(rsp.authentication_data().unwrap_or_default().to_vec());
+
+            Ok(OwnedAuthOpts::new()
+                .reason(AuthReason::ContinueAuthentication)
+                .authentication_method("m")
+                .authentication_data(format!("r{}", self.received.len())))
+        }
+    }
+
+    #[tokio::test]
+    async fn connect_with_auth() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, _handle) = Context::new();
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        let mut handler = Challenges {
+            received: Vec::new(),
+        };
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+
+            for challenge in [b'1', b'2'] {
+                server
+                    .write_all(&[
+                        0xf0, 11, 0x18, 9, 0x15, 0, 1, b'm', 0x16, 0, 2, b'c', challenge,
+                    ]) // AUTH, continue authentication
+                    .await
+                    .unwrap();
+
+                let len = server.read(&mut buf).await.unwrap();
+                assert_eq!(buf[0], 0xf0); // AUTH
+                assert_eq!(buf[len - 1], challenge);
+            }
+
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+        };
+
+        let opts = ConnectOpts::new()
+            .client_identifier("test")
+            .authentication_method("m")
+            .authentication_data(b"init");
+
+        let (rsp, _) = futures::join!(context.connect_with_auth(opts, &mut handler), server);
+        assert!(rsp.is_ok());
+        assert_eq!(handler.received, [b"c1".to_vec(), b"c2".to_vec()]);
+    }
+}
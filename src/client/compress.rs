@@ -0,0 +1,247 @@
+use crate::client::{
+    error::CompressionError, opts::PublishOpts, owned_opts::OwnedPublishOpts, rsp::PublishData,
+};
+use flate2::{
+    read::{DeflateDecoder, GzDecoder},
+    write::{DeflateEncoder, GzEncoder},
+    Compression,
+};
+use std::{
+    borrow::Cow,
+    io::{self, Read, Write},
+};
+
+const CONTENT_ENCODING: &str = "content-encoding";
+const UNCOMPRESSED_SIZE: &str = "uncompressed-size";
+
+/// Payload compression algorithm.
+///
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CompressAlgorithm {
+    /// Gzip format, see [RFC 1952](https://www.rfc-editor.org/rfc/rfc1952).
+    ///
+    Gzip,
+
+    /// Raw deflate stream, see [RFC 1951](https://www.rfc-editor.org/rfc/rfc1951).
+    ///
+    Deflate,
+
+    /// LZ4 block with the uncompressed size prepended, see `lz4_flex::compress_prepend_size`.
+    ///
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+impl CompressAlgorithm {
+    /// Value of the `content-encoding` user property identifying the algorithm.
+    ///
+    pub fn content_encoding(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => "lz4",
+        }
+    }
+
+    /// Content type set on the compressed messages.
+    ///
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Gzip => "application/gzip",
+            Self::Deflate => "application/deflate",
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => "application/x-lz4",
+        }
+    }
+
+    fn from_content_encoding(val: &str) -> Option<Self> {
+        match val {
+            "gzip" => Some(Self::Gzip),
+            "deflate" => Some(Self::Deflate),
+            #[cfg(feature = "lz4")]
+            "lz4" => Some(Self::Lz4),
+            _ => None,
+        }
+    }
+
+    fn compress(&self, payload: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(payload)?;
+                encoder.finish()
+            }
+            Self::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(payload)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => Ok(lz4_flex::compress_prepend_size(payload)),
+        }
+    }
+
+    fn decompress(&self, payload: &[u8], size_hint: usize) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(size_hint);
+        match self {
+            Self::Gzip => GzDecoder::new(payload).read_to_end(&mut buf).map(|_| buf),
+            Self::Deflate => DeflateDecoder::new(payload)
+                .read_to_end(&mut buf)
+                .map(|_| buf),
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => lz4_flex::decompress_size_prepended(payload)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+        }
+    }
+}
+
+/// [PublishOpts] wrapper compressing the payload with the chosen [algorithm](CompressAlgorithm).
+/// The compressed message carries the algorithm in the `content-encoding` user property, the size
+/// of the original payload in the `uncompressed-size` user property and the content type of the
+/// compressed data. Received messages are restored with [decompress].
+///
+/// ```
+/// # use poster::{compress::*, PublishOpts};
+/// let opts = CompressedPublishOpts::new(
+///     CompressAlgorithm::Gzip,
+///     PublishOpts::new().topic_name("logs").payload(b"..."),
+/// )
+/// .build()
+/// .unwrap();
+///
+/// // handle.publish(PublishOpts::from(&opts)).await?;
+/// ```
+///
+pub struct CompressedPublishOpts {
+    algorithm: CompressAlgorithm,
+    opts: OwnedPublishOpts,
+}
+
+impl CompressedPublishOpts {
+    /// Creates a new [CompressedPublishOpts] instance from the options of the message to compress.
+    ///
+    pub fn new(algorithm: CompressAlgorithm, opts: PublishOpts<'_>) -> Self {
+        Self {
+            algorithm,
+            opts: OwnedPublishOpts::from(&opts),
+        }
+    }
+
+    /// Compresses the payload, returning the options of the compressed message.
+    ///
+    /// # Errors
+    /// [CompressionError] when the compression fails.
+    ///
+    pub fn build(self) -> Result<OwnedPublishOpts, CompressionError> {
+        let payload = self.opts.get_payload().unwrap_or_default();
+        let uncompressed_size = payload.len().to_string();
+        let compressed = self.algorithm.compress(payload)?;
+
+        Ok(self
+            .opts
+            .payload(compressed)
+            .payload_format_indicator(false)
+            .content_type(self.algorithm.content_type())
+            .user_property((CONTENT_ENCODING, self.algorithm.content_encoding()))
+            .user_property((UNCOMPRESSED_SIZE, uncompressed_size)))
+    }
+}
+
+/// Restores the payload of the message compressed with [CompressedPublishOpts]. Payload
+/// of the message without the `content-encoding` user property is returned as is.
+///
+/// # Errors
+/// [CompressionError] when the encoding is not supported or the payload is malformed.
+///
+pub fn decompress(msg: &PublishData) -> Result<Cow<'_, [u8]>, CompressionError> {
+    let encoding = match msg.user_properties().get(CONTENT_ENCODING).next() {
+        Some(encoding) => encoding,
+        None => return Ok(Cow::Borrowed(msg.payload())),
+    };
+
+    let algorithm = CompressAlgorithm::from_content_encoding(encoding).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::Unsupported, "unsupported content encoding")
+    })?;
+
+    let size_hint = msg
+        .user_properties()
+        .get(UNCOMPRESSED_SIZE)
+        .next()
+        .and_then(|val| val.parse().ok())
+        .unwrap_or_default();
+
+    Ok(Cow::Owned(algorithm.decompress(msg.payload(), size_hint)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        codec::PublishRx,
+        core::utils::{Encode, TryDecode},
+    };
+    use bytes::BytesMut;
+
+    fn receive(opts: &OwnedPublishOpts) -> PublishData {
+        let mut buf = BytesMut::new();
+        PublishOpts::from(opts).build().unwrap().encode(&mut buf);
+        PublishData::from(PublishRx::try_decode(buf.freeze()).unwrap())
+    }
+
+    #[test]
+    fn round_trip() {
+        let payload = b"lorem ipsum ".repeat(64);
+
+        let algorithms = [
+            CompressAlgorithm::Gzip,
+            CompressAlgorithm::Deflate,
+            #[cfg(feature = "lz4")]
+            CompressAlgorithm::Lz4,
+        ];
+
+        for algorithm in algorithms {
+            let opts = CompressedPublishOpts::new(
+                algorithm,
+                PublishOpts::new()
+                    .topic_name("a")
+                    .user_property(("key", "val"))
+                    .payload(&payload),
+            )
+            .build()
+            .unwrap();
+
+            let msg = receive(&opts);
+            assert!(msg.payload().len() < payload.len());
+            assert_eq!(msg.content_type(), Some(algorithm.content_type()));
+
+            let props = msg.user_properties();
+            assert_eq!(props.get("key").next(), Some("val"));
+            assert_eq!(
+                props.get(CONTENT_ENCODING).next(),
+                Some(algorithm.content_encoding())
+            );
+            assert_eq!(props.get(UNCOMPRESSED_SIZE).next(), Some("768"));
+
+            assert_eq!(decompress(&msg).unwrap().as_ref(), payload);
+        }
+    }
+
+    #[test]
+    fn decompress_invalid() {
+        let plain = OwnedPublishOpts::new()
+            .topic_name("a")
+            .payload(b"ok".to_vec());
+        let msg = receive(&plain);
+        assert!(matches!(decompress(&msg).unwrap(), Cow::Borrowed(b"ok")));
+
+        let msg = receive(&plain.clone().user_property((CONTENT_ENCODING, "gzip")));
+        assert!(decompress(&msg).is_err());
+
+        let msg = receive(&plain.user_property((CONTENT_ENCODING, "br")));
+        assert_eq!(
+            decompress(&msg).err().unwrap().kind(),
+            io::ErrorKind::Unsupported
+        );
+    }
+}
@@ -1,46 +1,195 @@
+#[cfg(feature = "pool")]
+use crate::client::pool::BufferPool;
 use crate::{
     client::{
+        auth::AuthHandler,
         error::{HandleClosed, MaximumPacketSizeExceeded, MqttError, SocketClosed},
         handle::ContextHandle,
+        interceptor::{PublishInterceptor, ReceiveInterceptor, SharedInterceptors},
         message::*,
-        opts::{AuthOpts, ConnectOpts},
-        rsp::{AuthRsp, ConnectRsp},
+        opts::{AuthOpts, ConnectOpts, ContextIoOptions, DisconnectOpts},
+        owned_opts::OwnedAuthOpts,
+        rsp::{AuthRsp, ConnectRsp, PublishData},
+        stream::SubscriptionStats,
+        topic::{TopicFilter, TopicTree},
         utils,
     },
     codec::*,
     core::{
         base_types::NonZero,
+        error::CodecError,
         properties::ReceiveMaximum,
         utils::{ByteLen, Encode, PacketID, SizedPacket},
     },
-    io::{RxPacketStream, TxPacketStream},
+    io::{
+        PacketObserver, RxPacketStream, SharedFramingCounters, SharedPacketObserver, TxPacketStream,
+    },
     QoS,
 };
 use bytes::{Bytes, BytesMut};
-use core::sync::atomic::{AtomicU16, AtomicU32};
+use core::sync::atomic::{AtomicU16, AtomicU32, AtomicU8, AtomicUsize, Ordering};
+use core::task::Waker;
 use either::{Either, Left, Right};
 use futures::{
     channel::{mpsc, oneshot},
-    AsyncRead, AsyncWrite, FutureExt, StreamExt,
+    future, stream, AsyncRead, AsyncWrite, FutureExt, StreamExt,
+};
+use futures_timer::Delay;
+use std::{
+    collections::VecDeque,
+    io, mem, str,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant, SystemTime},
 };
-use std::{collections::VecDeque, sync::Arc, time::SystemTime};
 
-use super::error::{InternalError, QuotaExceeded};
+use super::error::{
+    InactivityTimeout, InternalError, PacketIdentifierExhausted, PubackError, PubcompError,
+    PubrecError, QuotaExceeded, SessionExpired,
+};
+
+const ERRMSG_REAUTH_UNHANDLED: &str = "Re-authentication handler not set.";
+
+struct Subscription {
+    stream: mpsc::UnboundedSender<RxPacket>,
+    paused: Option<VecDeque<RxPacket>>, // Messages held back while paused
+    stats: Arc<Mutex<SubscriptionStats>>,
+    topic_filters: TopicFilters,
+    filter: Option<MessageFilter>, // Messages not matching are not delivered
+}
+
+impl Subscription {
+    fn stats(&self) -> MutexGuard<'_, SubscriptionStats> {
+        self.stats.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn record_delivered(&self, payload_len: u64) {
+        let mut stats = self.stats();
+        stats.messages_received += 1;
+        stats.bytes_received += payload_len;
+        stats.last_message_at = Some(SystemTime::now());
+    }
+}
+
+/// State of the outgoing QoS>0 PUBLISH flow, identified by the packet identifier.
+///
+#[allow(clippy::enum_variant_names)]
+enum PublishState {
+    /// QoS 1 PUBLISH sent, the packet with DUP flag set is kept for retransmission.
+    WaitingForPuback(NonZero<u16>, Bytes),
+    /// QoS 2 PUBLISH sent, the packet with DUP flag set is kept for retransmission.
+    WaitingForPubrec(NonZero<u16>, Bytes),
+    /// PUBREC received, PUBREL is retransmitted until PUBCOMP arrives.
+    WaitingForPubcomp(NonZero<u16>),
+}
+
+impl PublishState {
+    fn packet_identifier(&self) -> NonZero<u16> {
+        match self {
+            Self::WaitingForPuback(packet_id, _)
+            | Self::WaitingForPubrec(packet_id, _)
+            | Self::WaitingForPubcomp(packet_id) => *packet_id,
+        }
+    }
+}
 
-const ERRMSG_HANDLE_DROPPED: &str = "Unable to complete async operation.";
+/// Acknowledgement advancing the [PublishState].
+///
+#[derive(Clone, Copy)]
+enum PublishAck {
+    Puback,
+    Pubrec { accepted: bool },
+    Pubcomp,
+}
 
 struct Session {
     awaiting_ack: VecDeque<(usize, oneshot::Sender<Result<RxPacket, MqttError>>)>,
-    subscriptions: VecDeque<(usize, mpsc::UnboundedSender<RxPacket>)>,
-    retrasmit_queue: VecDeque<(usize, Bytes)>,
+    subscriptions: VecDeque<(usize, Subscription)>,
+    topic_tree: TopicTree<Vec<usize>>, // Subscription identifiers by topic filter, for MQTT v3.1.1 routing
+    pending_unsubscribes: VecDeque<(usize, Vec<String>)>,
+    pending_pings: VecDeque<(Instant, oneshot::Sender<Result<Duration, MqttError>>)>, // PINGREQ write timestamps
+    publish_flows: VecDeque<PublishState>, // Kept in the order of sending for retransmission
+    publish_callbacks: VecDeque<(NonZero<u16>, PublishCallback)>,
+    drain_waiters: Vec<oneshot::Sender<Result<(), MqttError>>>,
+    pause_buffer_size: usize,
+    #[cfg(feature = "pool")]
+    pool: BufferPool,
 }
 
 struct Connection {
+    connected: bool,
     disconnection_timestamp: Option<SystemTime>,
+    clean_start: bool,
     session_expiry_interval: u32,
     remote_receive_maximum: u16,
     remote_max_packet_size: Option<u32>,
-    send_quota: u16,
+    send_quota: Arc<AtomicU16>,
+    quota_wakers: SharedQuotaWakers,
+    remote_maximum_qos: Arc<AtomicU8>,
+    reauth_handler: Option<Box<dyn FnMut(AuthRsp) -> OwnedAuthOpts + Send>>,
+    inactivity_timeout: Option<Duration>,
+    protocol_version: MqttVersion,
+    observer: Option<SharedPacketObserver>,
+    framing_counters: SharedFramingCounters,
+    receive_interceptors: Vec<Box<dyn ReceiveInterceptor + Send>>,
+    connect_state: SharedConnectState,
+}
+
+/// Snapshot of the statistics of the [Context], see [stats](ContextHandle::stats).
+/// Counted over all the connections made with the context.
+///
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ContextStats {
+    /// Number of received packets that could not be framed or decoded, e.g. malformed
+    /// or exceeding the [maximum packet size](ConnectOpts::maximum_packet_size).
+    ///
+    pub framing_errors: u64,
+
+    /// Number of received bytes discarded because of the framing errors and
+    /// [read timeouts](crate::ContextIoOptions::read_timeout).
+    ///
+    pub bytes_discarded: u64,
+
+    /// Number of received PUBLISH packets without the subscription identifier, see
+    /// [UnexpectedPublish](crate::error::UnexpectedPublish). Such messages are acknowledged,
+    /// but not delivered to any of the subscriptions.
+    ///
+    pub unexpected_publishes: u64,
+}
+
+/// Outcome of the connection attempt, awaited with [wait_until_connected](ContextHandle::wait_until_connected).
+///
+#[derive(Default)]
+pub(crate) struct ConnectState {
+    result: Option<Result<(), MqttError>>, // Cleared on reset
+    waiters: Vec<oneshot::Sender<Result<(), MqttError>>>,
+}
+
+pub(crate) type SharedConnectState = Arc<Mutex<ConnectState>>;
+
+/// Wakers of the [ContextHandle] sinks waiting for the send quota to become available.
+///
+pub(crate) type SharedQuotaWakers = Arc<Mutex<Vec<Waker>>>;
+
+impl ConnectState {
+    pub(crate) fn wait(&mut self) -> oneshot::Receiver<Result<(), MqttError>> {
+        let (sender, receiver) = oneshot::channel();
+        match self.result.as_ref() {
+            Some(result) => {
+                let _ = sender.send(result.clone());
+            }
+            None => self.waiters.push(sender),
+        }
+
+        receiver
+    }
+
+    fn complete(&mut self, result: Result<(), MqttError>) {
+        for waiter in self.waiters.drain(..) {
+            let _ = waiter.send(result.clone());
+        }
+
+        self.result = Some(result);
+    }
 }
 
 /// Client context. Responsible for socket management and direct communication with the broker.
@@ -49,12 +198,21 @@ pub struct Context<RxStreamT, TxStreamT> {
     rx: Option<RxPacketStream<RxStreamT>>,
     tx: Option<TxPacketStream<TxStreamT>>,
 
-    message_queue: mpsc::UnboundedReceiver<ContextMessage>,
+    priority_queue: mpsc::UnboundedReceiver<ContextMessage>,
+    normal_queue: mpsc::UnboundedReceiver<ContextMessage>,
+    queued: Arc<AtomicUsize>, // Messages in the normal queue, see MessageSender
+    control_queue: mpsc::UnboundedReceiver<ContextMessage>, // Not tied to the handles' lifetime
+    interceptors: SharedInterceptors, // Applied by the handles
 
     session: Session,
     connection: Connection,
 }
 
+/// [Context] with type-erased streams, see [boxed](Context::boxed).
+///
+pub type BoxedContext =
+    Context<Box<dyn AsyncRead + Unpin + Send>, Box<dyn AsyncWrite + Unpin + Send>>;
+
 impl<RxStreamT, TxStreamT> Context<RxStreamT, TxStreamT>
 where
     RxStreamT: AsyncRead + Unpin,
@@ -91,10 +249,158 @@ where
         connection.session_expiry_interval >= elapsed
     }
 
+    // The connection is not complete yet while the extended authentication is in progress.
+    fn notify_connect_state(
+        connection: &Connection,
+        result: &Result<Either<ConnectRsp, AuthRsp>, MqttError>,
+    ) {
+        let result = match result {
+            Ok(Left(_)) => Ok(()),
+            Ok(Right(_)) => return,
+            Err(err) => Err(err.clone()),
+        };
+
+        connection
+            .connect_state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .complete(result);
+    }
+
+    fn has_stored_session(connection: &Connection, session: &Session) -> bool {
+        connection.session_expiry_interval != 0
+            && (!session.subscriptions.is_empty() || !session.publish_flows.is_empty())
+    }
+
     fn reset_session(session: &mut Session) {
         session.awaiting_ack.clear();
         session.subscriptions.clear();
-        session.retrasmit_queue.clear();
+        session.topic_tree = TopicTree::new();
+        session.pending_unsubscribes.clear();
+        session.pending_pings.clear();
+        session.publish_flows.clear();
+        session.publish_callbacks.clear();
+    }
+
+    fn packet_identifier_in_use(session: &Session, action_id: usize) -> bool {
+        let packet_identifier = (action_id >> 8) & 0xffff;
+        utils::packet_identifier_in_use(&session.awaiting_ack, action_id)
+            || session
+                .publish_callbacks
+                .iter()
+                .any(|(packet_id, _)| packet_id.get() as usize == packet_identifier)
+    }
+
+    fn take_publish_callback(
+        session: &mut Session,
+        packet_id: NonZero<u16>,
+    ) -> Option<PublishCallback> {
+        session
+            .publish_callbacks
+            .iter()
+            .position(|(id, _)| *id == packet_id)
+            .and_then(|pos| session.publish_callbacks.remove(pos))
+            .map(|(_, callback)| callback)
+    }
+
+    /// Advances the outgoing PUBLISH flow with the given packet identifier.
+    /// Returns `true` if the flow has completed and its send quota may be released.
+    ///
+    fn advance_publish_flow(
+        session: &mut Session,
+        packet_id: NonZero<u16>,
+        ack: PublishAck,
+    ) -> bool {
+        let pos = match session
+            .publish_flows
+            .iter()
+            .position(|state| state.packet_identifier() == packet_id)
+        {
+            Some(pos) => pos,
+            None => return false, // Acknowledgement for the flow not in progress.
+        };
+
+        let next = match (&session.publish_flows[pos], ack) {
+            (PublishState::WaitingForPuback(..), PublishAck::Puback) => None,
+            (PublishState::WaitingForPubrec(..), PublishAck::Pubrec { accepted: true }) => {
+                Some(PublishState::WaitingForPubcomp(packet_id))
+            }
+            (PublishState::WaitingForPubrec(..), PublishAck::Pubrec { accepted: false }) => None,
+            (PublishState::WaitingForPubcomp(_), PublishAck::Pubcomp) => None,
+            (
+                PublishState::WaitingForPuback(..),
+                PublishAck::Pubrec { .. } | PublishAck::Pubcomp,
+            )
+            | (PublishState::WaitingForPubrec(..), PublishAck::Puback | PublishAck::Pubcomp)
+            | (
+                PublishState::WaitingForPubcomp(_),
+                PublishAck::Puback | PublishAck::Pubrec { .. },
+            ) => {
+                return false; // Unexpected acknowledgement type, the flow is left intact.
+            }
+        };
+
+        let completed = next.is_none();
+        let previous = match next {
+            Some(state) => mem::replace(&mut session.publish_flows[pos], state),
+            None => session.publish_flows.remove(pos).unwrap(),
+        };
+
+        #[cfg(feature = "pool")]
+        if let PublishState::WaitingForPuback(_, packet)
+        | PublishState::WaitingForPubrec(_, packet) = previous
+        {
+            session.pool.give_back_frozen(packet);
+        }
+
+        #[cfg(not(feature = "pool"))]
+        drop(previous);
+
+        completed
+    }
+
+    fn release_send_quota(connection: &Connection) {
+        let send_quota = connection.send_quota.load(Ordering::Relaxed);
+        if send_quota != connection.remote_receive_maximum {
+            connection
+                .send_quota
+                .store(send_quota + 1, Ordering::Relaxed);
+            Self::wake_quota_waiters(connection);
+        }
+    }
+
+    fn wake_quota_waiters(connection: &Connection) {
+        let wakers = mem::take(
+            &mut *connection
+                .quota_wakers
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    fn notify_drained(session: &mut Session) {
+        // QoS 2 flows stay in the session until PUBCOMP, also while PUBREL is not yet sent.
+        if !session.awaiting_ack.is_empty()
+            || !session.publish_callbacks.is_empty()
+            || !session.publish_flows.is_empty()
+        {
+            return;
+        }
+
+        for sender in session.drain_waiters.drain(..) {
+            let _ = sender.send(Ok(())); // Drain future may have been dropped.
+        }
+    }
+
+    fn inactivity_timer(connection: &Connection) -> future::Either<Delay, future::Pending<()>> {
+        match connection.inactivity_timeout {
+            Some(timeout) => future::Either::Left(Delay::new(timeout)),
+            None => future::Either::Right(future::pending()),
+        }
     }
 
     fn validate_packet_size(connection: &Connection, packet: &[u8]) -> Result<(), MqttError> {
@@ -113,61 +419,130 @@ where
         session: &mut Session,
         msg: ContextMessage,
     ) -> Result<(), MqttError> {
+        #[cfg(feature = "tracing")]
+        {
+            let fixed_hdr = match &msg {
+                ContextMessage::FireAndForget(msg) => Some(msg.packet[0]),
+                ContextMessage::AwaitAck(msg) => Some(msg.packet[0]),
+                ContextMessage::Ping(msg) => Some(msg.packet[0]),
+                ContextMessage::PublishWithCallback(msg) => Some(msg.packet[0]),
+                ContextMessage::Subscribe(msg) => Some(msg.packet[0]),
+                ContextMessage::Unsubscribe(msg) => Some(msg.packet[0]),
+                ContextMessage::Raw(msg) => msg.packet.first().copied(),
+                ContextMessage::Detached(packet) => Some(packet[0]),
+                ContextMessage::Drain(_)
+                | ContextMessage::Subscriptions(_)
+                | ContextMessage::PauseSubscription(_)
+                | ContextMessage::ResumeSubscription(_) => None,
+            };
+
+            if let Some(fixed_hdr) = fixed_hdr {
+                let packet_type = crate::codec::packet_type_name(fixed_hdr >> 4);
+                tracing::Span::current().record("packet_type", packet_type);
+                tracing::debug!(packet_type, "sending packet");
+            }
+        }
+
         match msg {
             ContextMessage::FireAndForget(msg) => {
                 if let Err(err) = Self::validate_packet_size(connection, msg.packet.as_ref()) {
-                    msg.response_channel
-                        .send(Err(err))
-                        .map_err(|_| InternalError::from(ERRMSG_HANDLE_DROPPED))?;
+                    let _ = msg.response_channel.send(Err(err));
                     return Ok(());
                 }
 
-                tx.write(msg.packet.freeze().as_ref()).await?;
-                msg.response_channel
-                    .send(Ok(()))
-                    .map_err(|_| InternalError::from(ERRMSG_HANDLE_DROPPED))?;
+                if msg.packet[0] >> 4 == DisconnectTx::PACKET_ID {
+                    connection.connected = false;
+                }
+
+                tx.write(msg.packet.as_ref()).await?;
+
+                #[cfg(feature = "pool")]
+                session.pool.give_back(msg.packet);
+
+                let _ = msg.response_channel.send(Ok(()));
+            }
+            ContextMessage::Detached(packet) => {
+                // Sent by the last dropped handle, nobody awaits the result.
+                if connection.connected {
+                    connection.connected = false;
+                    tx.write(packet.as_ref()).await?;
+                }
+            }
+            ContextMessage::Drain(msg) => {
+                session.drain_waiters.push(msg.response_channel);
+                Self::notify_drained(session);
+            }
+            ContextMessage::Subscriptions(msg) => {
+                Self::remove_closed_subscriptions(session);
+
+                let subscriptions = session
+                    .subscriptions
+                    .iter()
+                    .map(|(id, subscription)| (*id, subscription.topic_filters.clone()))
+                    .collect();
+
+                let _ = msg.response_channel.send(subscriptions);
             }
-            ContextMessage::AwaitAck(mut msg) => {
+            ContextMessage::PauseSubscription(subscription_identifier) => {
+                if let Some((_, subscription)) =
+                    utils::linear_search_by_key(&session.subscriptions, subscription_identifier)
+                        .map(|pos| &mut session.subscriptions[pos])
+                {
+                    subscription.paused.get_or_insert_with(VecDeque::new);
+                }
+            }
+            ContextMessage::ResumeSubscription(subscription_identifier) => {
+                if let Some((_, subscription)) =
+                    utils::linear_search_by_key(&session.subscriptions, subscription_identifier)
+                        .map(|pos| &mut session.subscriptions[pos])
+                {
+                    for packet in subscription.paused.take().into_iter().flatten() {
+                        let payload_len = match &packet {
+                            RxPacket::Publish(publish) => publish.payload.0.len() as u64,
+                            _ => 0,
+                        };
+
+                        // Stream may have been dropped.
+                        if subscription.stream.unbounded_send(packet).is_ok() {
+                            subscription.record_delivered(payload_len);
+                        }
+                    }
+                }
+            }
+            ContextMessage::Raw(msg) => {
+                // No validation, the packet is written as is.
+                tx.write(msg.packet.as_ref()).await?;
+                let _ = msg.response_channel.send(Ok(()));
+            }
+            ContextMessage::AwaitAck(msg) => {
                 if let Err(err) = Self::validate_packet_size(connection, msg.packet.as_ref()) {
-                    msg.response_channel
-                        .send(Err(err))
-                        .map_err(|_| InternalError::from(ERRMSG_HANDLE_DROPPED))?;
+                    let _ = msg.response_channel.send(Err(err));
                     return Ok(());
                 }
 
                 let packet_id = msg.packet.first().unwrap() >> 4; // Extract packet id, being the four MSB bits
 
+                if packet_id != PubrelTx::PACKET_ID
+                    && Self::packet_identifier_in_use(session, msg.action_id)
+                {
+                    let _ = msg
+                        .response_channel
+                        .send(Err(PacketIdentifierExhausted.into()));
+                    return Ok(());
+                }
+
                 if packet_id == PublishTx::PACKET_ID {
-                    if connection.send_quota == 0 {
-                        msg.response_channel
-                            .send(Err(QuotaExceeded.into()))
-                            .map_err(|_| InternalError::from(ERRMSG_HANDLE_DROPPED))?;
+                    if let Err(err) =
+                        Self::send_publish(tx, connection, session, msg.action_id, msg.packet)
+                            .await?
+                    {
+                        let _ = msg.response_channel.send(Err(err));
                         return Ok(());
                     }
 
-                    connection.send_quota -= 1;
-
-                    tx.write(msg.packet.as_ref()).await?;
-
-                    let fixed_hdr = msg.packet.get_mut(0).unwrap();
-                    *fixed_hdr |= (1 << 3) as u8; // Set DUP flag in the PUBLISH fixed header
-
-                    session
-                        .awaiting_ack
-                        .push_back((msg.action_id, msg.response_channel));
-
-                    session
-                        .retrasmit_queue
-                        .push_back((msg.action_id, msg.packet.freeze()));
-                } else if packet_id == PubrelTx::PACKET_ID {
-                    tx.write(msg.packet.as_ref()).await?;
                     session
                         .awaiting_ack
                         .push_back((msg.action_id, msg.response_channel));
-
-                    session
-                        .retrasmit_queue
-                        .push_back((msg.action_id, msg.packet.freeze()));
                 } else {
                     tx.write(msg.packet.as_ref()).await?;
                     session
@@ -175,20 +550,101 @@ where
                         .push_back((msg.action_id, msg.response_channel));
                 }
             }
-            ContextMessage::Subscribe(msg) => {
+            ContextMessage::Ping(msg) => {
+                let timestamp = Instant::now();
+                tx.write(msg.packet.as_ref()).await?;
+                session
+                    .pending_pings
+                    .push_back((timestamp, msg.response_channel));
+            }
+            ContextMessage::Unsubscribe(msg) => {
                 if let Err(err) = Self::validate_packet_size(connection, msg.packet.as_ref()) {
-                    msg.response_channel
-                        .send(Err(err))
-                        .map_err(|_| InternalError::from(ERRMSG_HANDLE_DROPPED))?;
+                    let _ = msg.response_channel.send(Err(err));
                     return Ok(());
                 }
 
+                if Self::packet_identifier_in_use(session, msg.action_id) {
+                    let _ = msg
+                        .response_channel
+                        .send(Err(PacketIdentifierExhausted.into()));
+                    return Ok(());
+                }
+
+                tx.write(msg.packet.as_ref()).await?;
                 session
                     .awaiting_ack
                     .push_back((msg.action_id, msg.response_channel));
                 session
-                    .subscriptions
-                    .push_back((msg.subscription_identifier, msg.stream));
+                    .pending_unsubscribes
+                    .push_back((msg.action_id, msg.topic_filters));
+            }
+            ContextMessage::PublishWithCallback(msg) => {
+                if let Err(err) = Self::validate_packet_size(connection, msg.packet.as_ref()) {
+                    (msg.callback)(Err(err));
+                    return Ok(());
+                }
+
+                let action_id = match msg.action_id {
+                    Some(action_id) => action_id,
+                    None => {
+                        tx.write(msg.packet.as_ref()).await?;
+
+                        #[cfg(feature = "pool")]
+                        session.pool.give_back(msg.packet);
+
+                        (msg.callback)(Ok(()));
+                        return Ok(());
+                    }
+                };
+
+                if Self::packet_identifier_in_use(session, action_id) {
+                    (msg.callback)(Err(PacketIdentifierExhausted.into()));
+                    return Ok(());
+                }
+
+                if let Err(err) =
+                    Self::send_publish(tx, connection, session, action_id, msg.packet).await?
+                {
+                    (msg.callback)(Err(err));
+                    return Ok(());
+                }
+
+                let packet_id = NonZero::try_from(((action_id >> 8) & 0xffff) as u16).unwrap();
+                session
+                    .publish_callbacks
+                    .push_back((packet_id, msg.callback));
+            }
+            ContextMessage::Subscribe(msg) => {
+                if let Err(err) = Self::validate_packet_size(connection, msg.packet.as_ref()) {
+                    let _ = msg.response_channel.send(Err(err));
+                    return Ok(());
+                }
+
+                if Self::packet_identifier_in_use(session, msg.action_id) {
+                    let _ = msg
+                        .response_channel
+                        .send(Err(PacketIdentifierExhausted.into()));
+                    return Ok(());
+                }
+
+                session
+                    .awaiting_ack
+                    .push_back((msg.action_id, msg.response_channel));
+                Self::index_topic_filters(
+                    &mut session.topic_tree,
+                    msg.subscription_identifier,
+                    &msg.topic_filters,
+                );
+                session.subscriptions.push_back((
+                    msg.subscription_identifier,
+                    Subscription {
+                        stream: msg.stream,
+                        paused: None,
+                        stats: msg.stats,
+                        topic_filters: msg.topic_filters,
+                        filter: msg.filter,
+                    },
+                ));
 
                 tx.write(msg.packet.freeze().as_ref()).await?;
             }
@@ -197,6 +653,72 @@ where
         Ok(())
     }
 
+    async fn ack_publish(
+        tx: &mut TxPacketStream<TxStreamT>,
+        qos: QoS,
+        packet_id: NonZero<u16>,
+    ) -> Result<(), MqttError> {
+        match qos {
+            QoS::AtLeastOnce => Self::ack::<PubackReason>(tx, packet_id).await,
+            QoS::ExactlyOnce => Self::ack::<PubrecReason>(tx, packet_id).await,
+            _ => unreachable!("No acknowledgement for QoS==0."),
+        }
+    }
+
+    /// Applies the [receive interceptors](Context::add_receive_interceptor) to the incoming message.
+    ///
+    fn intercept(connection: &Connection, publish: PublishRx) -> Result<PublishRx, MqttError> {
+        if connection.receive_interceptors.is_empty() {
+            return Ok(publish);
+        }
+
+        let mut msg = PublishData::from(publish);
+        for interceptor in connection.receive_interceptors.iter() {
+            interceptor.intercept(&mut msg)?;
+        }
+
+        Ok(msg.into_inner())
+    }
+
+    /// Sends the QoS>0 PUBLISH packet and starts its flow. The outer error is fatal for the context,
+    /// while the inner one concerns the publish operation only.
+    ///
+    async fn send_publish(
+        tx: &mut TxPacketStream<TxStreamT>,
+        connection: &mut Connection,
+        session: &mut Session,
+        action_id: usize,
+        mut packet: BytesMut,
+    ) -> Result<Result<(), MqttError>, MqttError> {
+        let send_quota = connection.send_quota.load(Ordering::Relaxed);
+        if send_quota == 0 {
+            return Ok(Err(QuotaExceeded.into()));
+        }
+
+        connection
+            .send_quota
+            .store(send_quota - 1, Ordering::Relaxed);
+
+        tx.write(packet.as_ref()).await?;
+
+        let fixed_hdr = packet.get_mut(0).unwrap();
+        *fixed_hdr |= (1 << 3) as u8; // Set DUP flag in the PUBLISH fixed header
+        let qos = (*fixed_hdr >> 1) & 0x03;
+
+        let packet_id = NonZero::try_from(((action_id >> 8) & 0xffff) as u16).unwrap();
+        let packet = packet.freeze();
+
+        session
+            .publish_flows
+            .push_back(if qos == QoS::AtLeastOnce as u8 {
+                PublishState::WaitingForPuback(packet_id, packet)
+            } else {
+                PublishState::WaitingForPubrec(packet_id, packet)
+            });
+
+        Ok(Ok(()))
+    }
+
     async fn ack<'a, ReasonT>(
         tx: &mut TxPacketStream<TxStreamT>,
         packet_id: NonZero<u16>,
@@ -217,49 +739,212 @@ where
         Ok(())
     }
 
+    fn index_topic_filters(
+        tree: &mut TopicTree<Vec<usize>>,
+        subscription_identifier: usize,
+        topic_filters: &TopicFilters,
+    ) {
+        for (filter, _) in topic_filters.iter() {
+            if let Ok(filter) = TopicFilter::try_from(filter.as_str()) {
+                let mut identifiers = tree.remove(&filter).unwrap_or_default();
+                identifiers.push(subscription_identifier);
+                tree.insert(&filter, identifiers);
+            }
+        }
+    }
+
+    fn unindex_topic_filters(
+        tree: &mut TopicTree<Vec<usize>>,
+        subscription_identifier: usize,
+        topic_filters: &TopicFilters,
+    ) {
+        for (filter, _) in topic_filters.iter() {
+            if let Ok(filter) = TopicFilter::try_from(filter.as_str()) {
+                let mut identifiers = tree.remove(&filter).unwrap_or_default();
+                identifiers.retain(|&id| id != subscription_identifier);
+                if !identifiers.is_empty() {
+                    tree.insert(&filter, identifiers);
+                }
+            }
+        }
+    }
+
+    /// Subscriptions whose streams were dropped are no longer of interest.
+    ///
+    fn remove_closed_subscriptions(session: &mut Session) {
+        let (closed, open) = mem::take(&mut session.subscriptions)
+            .into_iter()
+            .partition::<VecDeque<_>, _>(|(_, subscription)| subscription.stream.is_closed());
+        session.subscriptions = open;
+
+        for (subscription_identifier, removed) in closed {
+            Self::unindex_topic_filters(
+                &mut session.topic_tree,
+                subscription_identifier,
+                &removed.topic_filters,
+            );
+        }
+    }
+
+    // Unsubscribed topic filters no longer route messages to any of the subscriptions.
+    // MQTT v3.1.1 UNSUBACK carries no reason codes, hence missing ones mean success.
+    fn handle_unsuback(session: &mut Session, action_id: usize, unsuback: &UnsubackRx) {
+        let Some((_, topic_filters)) =
+            utils::linear_search_by_key(&session.pending_unsubscribes, action_id)
+                .and_then(|pos| session.pending_unsubscribes.remove(pos))
+        else {
+            return;
+        };
+
+        for (idx, filter) in topic_filters.iter().enumerate() {
+            let success = unsuback
+                .payload
+                .get(idx)
+                .is_none_or(|&reason| (reason as u8) < 0x80);
+
+            if let (true, Ok(filter)) = (success, TopicFilter::try_from(filter.as_str())) {
+                session.topic_tree.remove(&filter);
+            }
+        }
+    }
+
+    /// Finds the identifiers of the subscriptions with the topic filters matching the topic name.
+    ///
+    fn match_subscriptions(session: &Session, publish: &PublishRx) -> Vec<usize> {
+        let topic_name = str::from_utf8(publish.topic_name.0.as_ref()).unwrap_or_default();
+        let mut matching: Vec<usize> = session
+            .topic_tree
+            .matching(topic_name)
+            .flatten()
+            .copied()
+            .collect();
+        matching.sort_unstable();
+        matching.dedup();
+        matching
+    }
+
     async fn handle_packet(
         tx: &mut TxPacketStream<TxStreamT>,
         connection: &mut Connection,
         session: &mut Session,
         packet: RxPacket,
     ) -> Result<(), MqttError> {
+        #[cfg(feature = "tracing")]
+        {
+            let packet_type = packet.packet_type();
+            tracing::Span::current().record("packet_type", packet_type);
+            tracing::debug!(packet_type, "received packet");
+        }
+
         match packet {
             RxPacket::Publish(publish) => {
-                if let Some(subscription_identifier) =
-                    publish
-                        .subscription_identifier
-                        .map(|subscription_identifier| {
-                            NonZero::from(subscription_identifier).get().value() as usize
-                        })
-                {
-                    let qos = publish.qos;
-                    let maybe_packet_id = publish.packet_identifier;
+                // The message carries one subscription identifier for each subscription it matches.
+                // Without any, it cannot be routed. MQTT v3.1.1 brokers do not send the identifiers,
+                // so the subscriptions are matched by the topic name instead.
+                let subscription_identifiers = match connection.protocol_version {
+                    MqttVersion::V311 => Self::match_subscriptions(session, &publish),
+                    MqttVersion::V500 => {
+                        if publish.subscription_identifiers.is_empty() {
+                            // Reported without closing the connection, the message is still acknowledged below.
+                            connection.framing_counters.record_unexpected_publish();
+
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!(error = %super::error::UnexpectedPublish, "message not delivered");
+                        }
+
+                        publish
+                            .subscription_identifiers
+                            .iter()
+                            .map(|&subscription_identifier| {
+                                NonZero::from(subscription_identifier).get().value() as usize
+                            })
+                            .collect()
+                    }
+                };
+
+                let qos = publish.qos;
+                let maybe_packet_id = publish.packet_identifier;
 
+                let publish = match Self::intercept(connection, publish) {
+                    Ok(publish) => publish,
+                    Err(_err) => {
+                        // Message rejected by the receive interceptors is acknowledged, but not delivered.
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(error = %_err, "message rejected by the receive interceptor");
+
+                        if let Some(packet_id) = maybe_packet_id {
+                            Self::ack_publish(tx, qos, packet_id).await?;
+                        }
+
+                        return Ok(());
+                    }
+                };
+
+                let payload_len = publish.payload.0.len() as u64;
+
+                for subscription_identifier in subscription_identifiers {
                     if let Some((_, subscription)) =
                         utils::linear_search_by_key(&session.subscriptions, subscription_identifier)
                             .map(|pos| &mut session.subscriptions[pos])
                     {
+                        if subscription
+                            .filter
+                            .as_ref()
+                            .is_some_and(|filter| !filter(&PublishData::from(publish.clone())))
+                        {
+                            continue;
+                        }
+
+                        // Buffered messages are counted once delivered on resume.
+                        if let Some(paused) = subscription.paused.as_mut() {
+                            if session.pause_buffer_size == 0 {
+                                subscription.stats().messages_dropped += 1;
+                                continue;
+                            }
+
+                            if paused.len() >= session.pause_buffer_size {
+                                paused.pop_front();
+                                subscription
+                                    .stats
+                                    .lock()
+                                    .unwrap_or_else(PoisonError::into_inner)
+                                    .messages_dropped += 1;
+                            }
+
+                            paused.push_back(RxPacket::Publish(publish.clone()));
+                        }
                         // User may drop the receiving stream,
                         // in that case remove it from the active subscriptions map.
-                        if (subscription.unbounded_send(RxPacket::Publish(publish))).is_err() {
-                            utils::linear_search_by_key(
+                        else if (subscription
+                            .stream
+                            .unbounded_send(RxPacket::Publish(publish.clone())))
+                        .is_err()
+                        {
+                            if let Some((_, removed)) = utils::linear_search_by_key(
                                 &session.subscriptions,
                                 subscription_identifier,
                             )
-                            .and_then(|pos| session.subscriptions.remove(pos));
+                            .and_then(|pos| session.subscriptions.remove(pos))
+                            {
+                                Self::unindex_topic_filters(
+                                    &mut session.topic_tree,
+                                    subscription_identifier,
+                                    &removed.topic_filters,
+                                );
+                            }
+                        } else {
+                            subscription.record_delivered(payload_len);
                         }
                     }
+                }
 
-                    if let Some(packet_id) = maybe_packet_id {
-                        match qos {
-                            QoS::AtLeastOnce => Self::ack::<PubackReason>(tx, packet_id).await?,
-                            QoS::ExactlyOnce => Self::ack::<PubrecReason>(tx, packet_id).await?,
-                            _ => unreachable!("No acknowledgement for QoS==0."),
-                        }
-                    }
+                if let Some(packet_id) = maybe_packet_id {
+                    Self::ack_publish(tx, qos, packet_id).await?;
                 }
             }
             RxPacket::Disconnect(disconnect) => {
+                connection.connected = false;
+
                 if disconnect.reason == DisconnectReason::Success {
                     return Ok(()); // Graceful disconnection.
                 }
@@ -267,59 +952,140 @@ where
                 return Err(disconnect.into());
             }
             RxPacket::Puback(puback) => {
+                if Self::advance_publish_flow(session, puback.packet_identifier, PublishAck::Puback)
+                {
+                    Self::release_send_quota(connection);
+                }
+
+                if let Some(callback) =
+                    Self::take_publish_callback(session, puback.packet_identifier)
+                {
+                    callback(if (puback.reason as u8) < 0x80 {
+                        Ok(())
+                    } else {
+                        Err(PubackError::from(puback).into())
+                    });
+                    return Ok(());
+                }
+
                 let rx_packet = RxPacket::Puback(puback);
                 let action_id = utils::rx_action_id(&rx_packet);
 
-                if connection.send_quota != connection.remote_receive_maximum {
-                    connection.send_quota += 1;
+                if let Some((_, sender)) =
+                    utils::linear_search_by_key(&session.awaiting_ack, action_id)
+                        .and_then(|pos| session.awaiting_ack.remove(pos))
+                {
+                    let _ = sender.send(Ok(rx_packet)); // Operation may have been cancelled or timed out.
+                }
+            }
+            RxPacket::Pubrec(pubrec) => {
+                let accepted = (pubrec.reason as u8) < 0x80;
+                if Self::advance_publish_flow(
+                    session,
+                    pubrec.packet_identifier,
+                    PublishAck::Pubrec { accepted },
+                ) {
+                    Self::release_send_quota(connection);
+                }
+
+                // Flows started with a callback are driven by the context itself.
+                if accepted
+                    && session
+                        .publish_callbacks
+                        .iter()
+                        .any(|(packet_id, _)| *packet_id == pubrec.packet_identifier)
+                {
+                    return Self::ack::<PubrelReason>(tx, pubrec.packet_identifier).await;
                 }
 
-                utils::linear_search_by_key(&session.retrasmit_queue, action_id)
-                    .and_then(|pos| session.retrasmit_queue.remove(pos));
+                if let Some(callback) =
+                    Self::take_publish_callback(session, pubrec.packet_identifier)
+                {
+                    callback(Err(PubrecError::from(pubrec).into()));
+                    return Ok(());
+                }
+
+                let rx_packet = RxPacket::Pubrec(pubrec);
+                let action_id = utils::rx_action_id(&rx_packet);
 
                 if let Some((_, sender)) =
                     utils::linear_search_by_key(&session.awaiting_ack, action_id)
                         .and_then(|pos| session.awaiting_ack.remove(pos))
                 {
-                    sender
-                        .send(Ok(rx_packet))
-                        .map_err(|_| InternalError::from(ERRMSG_HANDLE_DROPPED))?;
+                    let _ = sender.send(Ok(rx_packet));
                 }
             }
             RxPacket::Pubcomp(pubcomp) => {
-                let rx_packet = RxPacket::Pubcomp(pubcomp);
-                let action_id = utils::rx_action_id(&rx_packet);
+                if Self::advance_publish_flow(
+                    session,
+                    pubcomp.packet_identifier,
+                    PublishAck::Pubcomp,
+                ) {
+                    Self::release_send_quota(connection);
+                }
 
-                if connection.send_quota != connection.remote_receive_maximum {
-                    connection.send_quota += 1;
+                if let Some(callback) =
+                    Self::take_publish_callback(session, pubcomp.packet_identifier)
+                {
+                    callback(if (pubcomp.reason as u8) < 0x80 {
+                        Ok(())
+                    } else {
+                        Err(PubcompError::from(pubcomp).into())
+                    });
+                    return Ok(());
                 }
 
-                utils::linear_search_by_key(&session.retrasmit_queue, action_id)
-                    .and_then(|pos| session.retrasmit_queue.remove(pos));
+                let rx_packet = RxPacket::Pubcomp(pubcomp);
+                let action_id = utils::rx_action_id(&rx_packet);
 
                 if let Some((_, sender)) =
                     utils::linear_search_by_key(&session.awaiting_ack, action_id)
                         .and_then(|pos| session.awaiting_ack.remove(pos))
                 {
-                    sender
-                        .send(Ok(rx_packet))
-                        .map_err(|_| InternalError::from(ERRMSG_HANDLE_DROPPED))?;
+                    let _ = sender.send(Ok(rx_packet));
+                }
+            }
+            RxPacket::Pingresp(_) => {
+                // PINGRESP packets are sent in the order of the PINGREQ packets.
+                if let Some((timestamp, sender)) = session.pending_pings.pop_front() {
+                    let _ = sender.send(Ok(timestamp.elapsed()));
                 }
             }
             RxPacket::Pubrel(pubrel) => {
                 let packet_id = pubrel.packet_identifier;
                 Self::ack::<PubcompReason>(tx, packet_id).await?
             }
+            RxPacket::Auth(auth) => {
+                let rsp = AuthRsp::try_from(auth)?;
+
+                let handler = match connection.reauth_handler.as_mut() {
+                    Some(handler) => handler,
+                    None => {
+                        Self::disconnect(tx, DisconnectReason::ProtocolError).await?;
+                        return Err(InternalError::from(ERRMSG_REAUTH_UNHANDLED).into());
+                    }
+                };
+
+                let opts = handler(rsp);
+                let packet = AuthOpts::from(&opts).build()?;
+
+                let mut buf = BytesMut::with_capacity(packet.packet_len());
+                packet.encode(&mut buf);
+
+                tx.write(buf.as_ref()).await?;
+            }
             other => {
                 let action_id = utils::rx_action_id(&other);
 
+                if let RxPacket::Unsuback(unsuback) = &other {
+                    Self::handle_unsuback(session, action_id, unsuback);
+                }
+
                 if let Some((_, sender)) =
                     utils::linear_search_by_key(&session.awaiting_ack, action_id)
                         .and_then(|pos| session.awaiting_ack.remove(pos))
                 {
-                    sender
-                        .send(Ok(other))
-                        .map_err(|_| InternalError::from(ERRMSG_HANDLE_DROPPED))?;
+                    let _ = sender.send(Ok(other));
                 }
             }
         }
@@ -327,6 +1093,19 @@ where
         Ok(())
     }
 
+    async fn disconnect(
+        tx: &mut TxPacketStream<TxStreamT>,
+        reason: DisconnectReason,
+    ) -> Result<(), MqttError> {
+        let packet = DisconnectOpts::new().reason(reason).build()?;
+
+        let mut buf = BytesMut::with_capacity(packet.packet_len());
+        packet.encode(&mut buf);
+
+        tx.write(buf.as_ref()).await?;
+        tx.flush().await
+    }
+
     fn handle_connack(connection: &mut Connection, connack: &ConnackRx) {
         if connack.session_expiry_interval.is_some() {
             connection.session_expiry_interval =
@@ -341,7 +1120,70 @@ where
         }
 
         connection.remote_receive_maximum = u16::from(NonZero::from(connack.receive_maximum));
-        connection.send_quota = connection.remote_receive_maximum;
+        connection
+            .send_quota
+            .store(connection.remote_receive_maximum, Ordering::Relaxed);
+        Self::wake_quota_waiters(connection);
+        connection
+            .remote_maximum_qos
+            .store(QoS::from(connack.maximum_qos) as u8, Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "tracing")]
+    fn log_capabilities(rsp: &ConnectRsp) {
+        tracing::info!(
+            max_qos = ?rsp.maximum_qos(),
+            retain_available = rsp.retain_available(),
+            wildcard_subscriptions = rsp.wildcard_subscription_available(),
+            shared_subscriptions = rsp.shared_subscription_available(),
+            subscription_identifiers = rsp.subscription_identifier_available(),
+            topic_alias_maximum = rsp.topic_alias_maximum(),
+            receive_maximum = rsp.receive_maximum(),
+            maximum_packet_size = rsp.maximum_packet_size(),
+            "connected"
+        );
+    }
+
+    fn validate_session(
+        connection: &Connection,
+        session: &mut Session,
+        connack: &ConnackRx,
+    ) -> Result<(), MqttError> {
+        if connection.clean_start || connack.session_present || session.publish_flows.is_empty() {
+            return Ok(());
+        }
+
+        // Broker has discarded the session, packets awaiting retransmission are lost.
+        // Other operations, e.g. SUBSCRIBE, are not affected.
+        let lost = |packet_identifier: usize| {
+            session
+                .publish_flows
+                .iter()
+                .any(|flow| flow.packet_identifier().get() as usize == packet_identifier)
+        };
+
+        let (expired, awaiting_ack) = session
+            .awaiting_ack
+            .drain(..)
+            .partition::<VecDeque<_>, _>(|(action_id, _)| lost((action_id >> 8) & 0xffff));
+        let (expired_callbacks, publish_callbacks) = session
+            .publish_callbacks
+            .drain(..)
+            .partition::<VecDeque<_>, _>(|(packet_id, _)| lost(packet_id.get() as usize));
+
+        session.awaiting_ack = awaiting_ack;
+        session.publish_callbacks = publish_callbacks;
+        session.publish_flows.clear();
+
+        for (_, sender) in expired {
+            let _ = sender.send(Err(SessionExpired.into()));
+        }
+
+        for (_, callback) in expired_callbacks {
+            callback(Err(SessionExpired.into()));
+        }
+
+        Err(SessionExpired.into())
     }
 
     async fn retransmit(
@@ -351,8 +1193,14 @@ where
     ) -> Result<(), MqttError> {
         connection.disconnection_timestamp = None;
 
-        for (_, packet) in session.retrasmit_queue.iter() {
-            tx.write(packet.as_ref()).await?;
+        for state in session.publish_flows.iter() {
+            match state {
+                PublishState::WaitingForPuback(_, packet)
+                | PublishState::WaitingForPubrec(_, packet) => tx.write(packet.as_ref()).await?,
+                PublishState::WaitingForPubcomp(packet_id) => {
+                    Self::ack::<PubrelReason>(tx, *packet_id).await?
+                }
+            }
         }
 
         Ok(())
@@ -361,31 +1209,92 @@ where
     /// Creates a new [Context] instance, paired with [ContextHandle].
     ///
     pub fn new() -> (Self, ContextHandle) {
+        Self::with_queue_capacity(usize::MAX)
+    }
+
+    /// Creates a new [Context] instance, same as [new](Context::new), with the number of messages
+    /// queued by the handles limited to `capacity`. When the context does not keep up with processing
+    /// them, the [ContextHandle] operations fail with [QueueFull](crate::error::QueueFull) error
+    /// instead of growing the queue.
+    ///
+    /// # Note
+    /// Pings, disconnections and continuations of the already enqueued publish flows are
+    /// not limited by the capacity.
+    ///
+    pub fn with_queue_capacity(capacity: usize) -> (Self, ContextHandle) {
         let (sender, receiver) = mpsc::unbounded();
+        let queued = Arc::new(AtomicUsize::from(0));
+        let interceptors = SharedInterceptors::default();
+        let (priority_sender, priority_receiver) = mpsc::unbounded();
+        let (control_sender, control_receiver) = mpsc::unbounded();
+        let send_quota = Arc::new(AtomicU16::from(u16::from(NonZero::from(
+            ReceiveMaximum::default(),
+        ))));
+        let quota_wakers = SharedQuotaWakers::default();
+        let remote_maximum_qos = Arc::new(AtomicU8::from(QoS::ExactlyOnce as u8));
+        let framing_counters = SharedFramingCounters::default();
+        let connect_state = SharedConnectState::default();
+        #[cfg(feature = "pool")]
+        let pool = BufferPool::new(ContextIoOptions::default().tx_pool_size);
 
         (
             Self {
                 rx: None,
                 tx: None,
-                message_queue: receiver,
+                priority_queue: priority_receiver,
+                normal_queue: receiver,
+                queued: queued.clone(),
+                control_queue: control_receiver,
+                interceptors: interceptors.clone(),
 
                 session: Session {
                     awaiting_ack: VecDeque::new(),
                     subscriptions: VecDeque::new(),
-                    retrasmit_queue: VecDeque::new(),
+                    topic_tree: TopicTree::new(),
+                    pending_unsubscribes: VecDeque::new(),
+                    pending_pings: VecDeque::new(),
+                    publish_flows: VecDeque::new(),
+                    publish_callbacks: VecDeque::new(),
+                    drain_waiters: Vec::new(),
+                    pause_buffer_size: ContextIoOptions::default().pause_buffer_size,
+                    #[cfg(feature = "pool")]
+                    pool: pool.clone(),
                 },
                 connection: Connection {
+                    connected: false,
                     disconnection_timestamp: None,
+                    clean_start: true,
                     session_expiry_interval: 0,
                     remote_receive_maximum: u16::from(NonZero::from(ReceiveMaximum::default())),
                     remote_max_packet_size: None,
-                    send_quota: u16::from(NonZero::from(ReceiveMaximum::default())),
+                    send_quota: send_quota.clone(),
+                    quota_wakers: quota_wakers.clone(),
+                    remote_maximum_qos: remote_maximum_qos.clone(),
+                    reauth_handler: None,
+                    inactivity_timeout: None,
+                    protocol_version: MqttVersion::default(),
+                    observer: None,
+                    framing_counters: framing_counters.clone(),
+                    receive_interceptors: Vec::new(),
+                    connect_state: connect_state.clone(),
                 },
             },
             ContextHandle {
-                sender,
+                sender: MessageSender::new(sender, queued, capacity),
+                priority_sender,
+                control_sender,
                 packet_id: Arc::new(AtomicU16::from(1)),
                 sub_id: Arc::new(AtomicU32::from(1)),
+                send_quota,
+                quota_wakers,
+                remote_maximum_qos,
+                framing_counters,
+                connect_state,
+                pending: Mutex::default(),
+                handles: Arc::new(AtomicUsize::from(1)),
+                interceptors,
+                #[cfg(feature = "pool")]
+                pool,
             },
         )
     }
@@ -400,9 +1309,103 @@ where
     /// # Note
     /// Calling any other member function before prior call to [set_up](Context::set_up) will panic.
     ///
-    pub fn set_up(&mut self, (rx, tx): (RxStreamT, TxStreamT)) -> &mut Self {
-        self.rx = Some(RxPacketStream::from(rx));
-        self.tx = Some(TxPacketStream::from(tx));
+    pub fn set_up(&mut self, (rx, tx): (RxStreamT, TxStreamT)) -> &mut Self
+    where
+        TxStreamT: AsyncWrite,
+    {
+        self.set_up_with_options((rx, tx), ContextIoOptions::default())
+    }
+
+    /// Sets up communication primitives for the context, same as [set_up](Context::set_up),
+    /// with the IO buffer sizes configured in [ContextIoOptions].
+    ///
+    /// # Arguments
+    /// * `rx` - Read half of the stream, must be [AsyncRead] + [Unpin].
+    /// * `tx` - Write half of the stream, must be [AsyncWrite] + [Unpin].
+    /// * `opts` - IO options.
+    ///
+    pub fn set_up_with_options(
+        &mut self,
+        (rx, tx): (RxStreamT, TxStreamT),
+        opts: ContextIoOptions,
+    ) -> &mut Self
+    where
+        TxStreamT: AsyncWrite,
+    {
+        let mut rx = RxPacketStream::with_capacity(rx, opts.rx_buf_size);
+        if let Some(timeout) = opts.read_timeout {
+            rx = rx.with_read_timeout(timeout);
+        }
+        rx.set_observer(self.connection.observer.clone());
+        rx.set_counters(self.connection.framing_counters.clone());
+
+        let mut tx = TxPacketStream::with_capacity(tx, opts.tx_buf_size);
+        tx.set_observer(self.connection.observer.clone());
+
+        #[cfg(feature = "pool")]
+        self.session.pool.set_max_size(opts.tx_pool_size);
+
+        self.session.pause_buffer_size = opts.pause_buffer_size;
+
+        self.rx = Some(rx);
+        self.tx = Some(tx);
+        self
+    }
+
+    /// Prepares the context for another connection after [run](Context::run) has returned, without
+    /// recreating it together with the handles. Releases the streams and resets the connection state
+    /// received from the broker. Registered handlers, interceptors and the observer are kept.
+    ///
+    /// # Arguments
+    /// * `clear_session` - Discards the session state, i.e. the subscriptions and the publish flows awaiting
+    ///   acknowledgement. Pending operations complete with the [SessionExpired](crate::error::SessionExpired) error.
+    ///   Otherwise, the session may be resumed on the next [connect](Context::connect).
+    ///
+    /// # Note
+    /// [set_up](Context::set_up) must be called again before the next connection.
+    ///
+    pub fn reset(&mut self, clear_session: bool) -> &mut Self {
+        self.rx = None;
+        self.tx = None;
+
+        let connection = &mut self.connection;
+        let receive_maximum = u16::from(NonZero::from(ReceiveMaximum::default()));
+        connection.connected = false;
+        connection
+            .connect_state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .result = None;
+        connection.remote_receive_maximum = receive_maximum;
+        connection.remote_max_packet_size = None;
+        connection
+            .send_quota
+            .store(receive_maximum, Ordering::Relaxed);
+        Self::wake_quota_waiters(connection);
+        connection
+            .remote_maximum_qos
+            .store(QoS::ExactlyOnce as u8, Ordering::Relaxed);
+
+        if clear_session {
+            connection.clean_start = true;
+            connection.session_expiry_interval = 0;
+
+            let session = &mut self.session;
+            for (_, sender) in session.awaiting_ack.drain(..) {
+                let _ = sender.send(Err(SessionExpired.into()));
+            }
+
+            for (_, callback) in session.publish_callbacks.drain(..) {
+                callback(Err(SessionExpired.into()));
+            }
+
+            Self::reset_session(session);
+
+            for sender in session.drain_waiters.drain(..) {
+                let _ = sender.send(Ok(()));
+            }
+        }
+
         self
     }
 
@@ -414,24 +1417,58 @@ where
     /// is then [AuthRsp]. Otherwise, the return type is [ConnectRsp].
     ///
     /// When the [reason](crate::reason::ConnectReason) in the CONNACK packet is greater or equal 0x80, the
-    /// [ConnectError](crate::error::ConnectError) is returned.
+    /// [ConnectError](crate::error::ConnectError) is returned. For the [UseAnotherServer](crate::reason::ConnectReason::UseAnotherServer)
+    /// and [ServerMoved](crate::reason::ConnectReason::ServerMoved) reasons, the [ServerMovedError](crate::error::ServerMovedError)
+    /// with the server reference is returned instead.
+    ///
+    /// When [clean_start](ConnectOpts::clean_start) is set to `false` and the broker does not resume the session
+    /// while there are packets awaiting retransmission, the [SessionExpired](crate::error::SessionExpired)
+    /// error is returned. Packets awaiting acknowledgement are discarded in that case.
+    ///
+    /// With [clean_start_auto](ConnectOpts::clean_start_auto), the session is resumed only when
+    /// the context holds the session state from the previous connection.
     ///
     /// When in extended authorization mode, the authorize method is used for subsequent
     /// authorization requests.
     ///
+    /// To connect again after [run](Context::run) has returned, call [reset](Context::reset)
+    /// and [set_up](Context::set_up) with the new streams first.
+    ///
     /// # Panics
     /// When invoked without prior call to [set_up](Context::set_up).
     ///
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub async fn connect<'a>(
         &mut self,
         opts: ConnectOpts<'a>,
+    ) -> Result<Either<ConnectRsp, AuthRsp>, MqttError> {
+        let result = self.connect_impl(opts).await;
+        Self::notify_connect_state(&self.connection, &result);
+        result
+    }
+
+    async fn connect_impl<'a>(
+        &mut self,
+        opts: ConnectOpts<'a>,
     ) -> Result<Either<ConnectRsp, AuthRsp>, MqttError> {
         assert!(
             self.rx.is_some() && self.tx.is_some(),
             "Context must be set up before connecting."
         );
 
+        self.connection.inactivity_timeout = opts.inactivity_timeout;
+        self.connection.protocol_version = opts.protocol_version;
+
+        let opts = if opts.clean_start_auto {
+            let resume = Self::has_stored_session(&self.connection, &self.session);
+            opts.clean_start(!resume)
+        } else {
+            opts
+        };
+
         let packet = opts.build()?;
+        self.connection.clean_start = packet.clean_start;
+        self.session.pending_pings.clear(); // Never answered on the previous connection
         self.connection.session_expiry_interval =
             packet.session_expiry_interval.map(u32::from).unwrap_or(0);
 
@@ -441,18 +1478,28 @@ where
         let tx = self.tx.as_mut().unwrap();
         let rx = self.rx.as_mut().unwrap();
 
+        rx.set_max_packet_size(packet.maximum_packet_size.map(NonZero::from).map(u32::from));
+        rx.set_protocol_version(self.connection.protocol_version);
+        tx.set_protocol_version(self.connection.protocol_version);
+
         tx.write(buf.as_ref()).await?;
+        tx.flush().await?;
 
-        match rx
-            .next()
-            .await
-            .transpose()
-            .map_err(MqttError::from)
-            .and_then(|maybe_next| maybe_next.ok_or(SocketClosed.into()))?
-        {
+        match rx.next().await.transpose().and_then(|maybe_next| {
+            maybe_next.ok_or(SocketClosed::from(io::ErrorKind::UnexpectedEof).into())
+        })? {
             RxPacket::Connack(connack) => {
+                let requested_session_expiry_interval = self.connection.session_expiry_interval;
                 Self::handle_connack(&mut self.connection, &connack);
-                Ok(Left(ConnectRsp::try_from(connack)?))
+                let mut rsp = ConnectRsp::from_connack(connack)?;
+                rsp.requested_session_expiry_interval = requested_session_expiry_interval;
+                Self::validate_session(&self.connection, &mut self.session, &rsp.packet)?;
+                self.connection.connected = true;
+
+                #[cfg(feature = "tracing")]
+                Self::log_capabilities(&rsp);
+
+                Ok(Left(rsp))
             }
             RxPacket::Auth(auth) => Ok(Right(AuthRsp::try_from(auth)?)),
             _ => {
@@ -475,6 +1522,15 @@ where
     pub async fn authorize<'a>(
         &mut self,
         opts: AuthOpts<'a>,
+    ) -> Result<Either<ConnectRsp, AuthRsp>, MqttError> {
+        let result = self.authorize_impl(opts).await;
+        Self::notify_connect_state(&self.connection, &result);
+        result
+    }
+
+    async fn authorize_impl<'a>(
+        &mut self,
+        opts: AuthOpts<'a>,
     ) -> Result<Either<ConnectRsp, AuthRsp>, MqttError> {
         assert!(
             self.rx.is_some() && self.tx.is_some(),
@@ -490,17 +1546,23 @@ where
         let rx = self.rx.as_mut().unwrap();
 
         tx.write(buf.as_ref()).await?;
+        tx.flush().await?;
 
-        match rx
-            .next()
-            .await
-            .transpose()
-            .map_err(MqttError::from)
-            .and_then(|maybe_next| maybe_next.ok_or(SocketClosed.into()))?
-        {
+        match rx.next().await.transpose().and_then(|maybe_next| {
+            maybe_next.ok_or(SocketClosed::from(io::ErrorKind::UnexpectedEof).into())
+        })? {
             RxPacket::Connack(connack) => {
+                let requested_session_expiry_interval = self.connection.session_expiry_interval;
                 Self::handle_connack(&mut self.connection, &connack);
-                Ok(Left(ConnectRsp::try_from(connack)?))
+                let mut rsp = ConnectRsp::from_connack(connack)?;
+                rsp.requested_session_expiry_interval = requested_session_expiry_interval;
+                Self::validate_session(&self.connection, &mut self.session, &rsp.packet)?;
+                self.connection.connected = true;
+
+                #[cfg(feature = "tracing")]
+                Self::log_capabilities(&rsp);
+
+                Ok(Left(rsp))
             }
             RxPacket::Auth(auth) => Ok(Right(AuthRsp::try_from(auth)?)),
             _ => {
@@ -509,14 +1571,113 @@ where
         }
     }
 
+    /// Performs connection with the extended authentication, same as [connect](Context::connect) followed by
+    /// the [authorize](Context::authorize) calls. Each AUTH packet received from the broker is passed to the
+    /// [handler](AuthHandler), whose response is sent back, until the CONNACK packet is received.
+    ///
+    /// # Panics
+    /// When invoked without prior call to [set_up](Context::set_up).
+    ///
+    pub async fn connect_with_auth<'a, H>(
+        &mut self,
+        opts: ConnectOpts<'a>,
+        mut handler: H,
+    ) -> Result<ConnectRsp, MqttError>
+    where
+        H: AuthHandler,
+    {
+        let mut rsp = self.connect(opts).await?;
+
+        loop {
+            match rsp {
+                Left(connect_rsp) => return Ok(connect_rsp),
+                Right(auth_rsp) => {
+                    let opts = handler.handle_challenge(auth_rsp).await?;
+                    rsp = self.authorize(AuthOpts::from(&opts)).await?;
+                }
+            }
+        }
+    }
+
+    /// Registers the handler for the re-authentication initiated by the broker. When the AUTH packet
+    /// arrives after the connection is established, the handler is invoked with the received
+    /// [AuthRsp] and the returned [options](OwnedAuthOpts) are sent back in the AUTH packet.
+    ///
+    /// Without the handler, the broker initiated re-authentication results in disconnection
+    /// with the [ProtocolError](crate::reason::DisconnectReason::ProtocolError) reason.
+    ///
+    pub fn on_reauth<F>(&mut self, handler: F) -> &mut Self
+    where
+        F: FnMut(AuthRsp) -> OwnedAuthOpts + Send + 'static,
+    {
+        self.connection.reauth_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Registers the [interceptor](PublishInterceptor) of the messages published with all the handles.
+    /// Interceptors are applied in the order of registration, before the message is encoded.
+    ///
+    pub fn add_publish_interceptor<I>(&mut self, interceptor: I) -> &mut Self
+    where
+        I: PublishInterceptor + Send + Sync + 'static,
+    {
+        self.interceptors
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Box::new(interceptor));
+        self
+    }
+
+    /// Registers the [interceptor](ReceiveInterceptor) of the incoming messages. Interceptors are applied
+    /// in the order of registration, before the message is delivered to the subscription streams.
+    ///
+    pub fn add_receive_interceptor<I>(&mut self, interceptor: I) -> &mut Self
+    where
+        I: ReceiveInterceptor + Send + 'static,
+    {
+        self.connection
+            .receive_interceptors
+            .push(Box::new(interceptor));
+        self
+    }
+
+    /// Registers the [observer](PacketObserver) of all the packets received from and sent to the broker.
+    /// Meant for protocol debugging, e.g. capturing the traffic at the application level.
+    ///
+    pub fn set_packet_observer<O>(&mut self, observer: O) -> &mut Self
+    where
+        O: PacketObserver + Send + Sync + 'static,
+    {
+        let observer: SharedPacketObserver = Arc::new(observer);
+
+        if let Some(rx) = self.rx.as_mut() {
+            rx.set_observer(Some(observer.clone()));
+        }
+
+        if let Some(tx) = self.tx.as_mut() {
+            tx.set_observer(Some(observer.clone()));
+        }
+
+        self.connection.observer = Some(observer);
+        self
+    }
+
     /// Starts processing MQTT traffic, blocking (on .await) the current task until
     /// graceful disconnection or error. Successful disconnection via [disconnect](ContextHandle::disconnect) method or
     /// receiving a [Disconnect](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901205)
     /// packet with reason a code equal to 0 (success) is considered a graceful disconnection.
     ///
+    /// When an incoming packet exceedes the [maximum packet size](ConnectOpts::maximum_packet_size)
+    /// declared by the client, the DISCONNECT packet with [PacketTooLarge](crate::reason::DisconnectReason::PacketTooLarge)
+    /// reason is sent and the [PacketTooLarge](crate::error::PacketTooLarge) error is returned.
+    ///
     /// # Panics
     /// When invoked without prior call to [set_up](Context::set_up).
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(packet_type))
+    )]
     pub async fn run(&mut self) -> Result<(), MqttError>
     where
         RxStreamT: AsyncRead + Unpin,
@@ -529,7 +1690,15 @@ where
 
         let rx = self.rx.as_mut().unwrap();
         let tx = self.tx.as_mut().unwrap();
-        let message_queue = &mut self.message_queue;
+        // Messages from the priority queue (DISCONNECT, PINGREQ) are always handled first.
+        let queued = &self.queued;
+        let mut message_queue = stream::select_with_strategy(
+            &mut self.priority_queue,
+            (&mut self.normal_queue).inspect(|_| {
+                queued.fetch_sub(1, Ordering::AcqRel);
+            }),
+            |_: &mut ()| stream::PollNext::Left,
+        );
         let session = &mut self.session;
         let connection = &mut self.connection;
 
@@ -539,23 +1708,1106 @@ where
             }
 
             Self::retransmit(tx, connection, session).await?;
+            tx.flush().await?;
         }
 
         let mut pck_fut = rx.next().fuse();
         let mut msg_fut = message_queue.next();
+        let mut ctl_fut = self.control_queue.next();
+        let mut inactivity_fut = Self::inactivity_timer(connection).fuse();
 
         loop {
             futures::select! {
                 maybe_rx_packet = pck_fut => {
-                    let rx_packet = maybe_rx_packet.ok_or(SocketClosed)?;
+                    let rx_packet = maybe_rx_packet.ok_or(SocketClosed::from(io::ErrorKind::UnexpectedEof))?;
+                    if let Err(MqttError::CodecError(CodecError::PacketTooLarge(err))) = rx_packet {
+                        Self::disconnect(tx, DisconnectReason::PacketTooLarge).await?;
+                        return Err(CodecError::from(err).into());
+                    }
+
                     Self::handle_packet(tx, connection, session, rx_packet?).await?;
+                    Self::notify_drained(session);
+                    tx.flush().await?;
                     pck_fut = rx.next().fuse();
+                    inactivity_fut = Self::inactivity_timer(connection).fuse();
                 },
                 maybe_msg = msg_fut => {
-                    Self::handle_message(tx, connection, session, maybe_msg.ok_or(HandleClosed)?).await?;
+                    // Handle all the messages queued so far before flushing, so that the packets
+                    // are written out together.
+                    let mut maybe_msg = maybe_msg;
+                    loop {
+                        Self::handle_message(tx, connection, session, maybe_msg.ok_or(HandleClosed)?).await?;
+                        match message_queue.next().now_or_never() {
+                            Some(next) => maybe_msg = next,
+                            None => break,
+                        }
+                    }
+
+                    tx.flush().await?;
                     msg_fut = message_queue.next();
+                },
+                maybe_msg = ctl_fut => {
+                    // Senders are held by the subscription streams as well, so the queue
+                    // may end before the handles are dropped.
+                    if let Some(msg) = maybe_msg {
+                        Self::handle_message(tx, connection, session, msg).await?;
+                        tx.flush().await?;
+                        ctl_fut = self.control_queue.next();
+                    }
+                },
+                _ = inactivity_fut => {
+                    return Err(InactivityTimeout.into());
                 }
             }
         }
     }
 }
+
+impl<RxStreamT, TxStreamT> Context<RxStreamT, TxStreamT>
+where
+    RxStreamT: AsyncRead + Unpin + Send + 'static,
+    TxStreamT: AsyncWrite + Unpin + Send + 'static,
+{
+    /// Converts the context into [BoxedContext], erasing the stream types. This allows storing
+    /// contexts using different transports in a single collection.
+    ///
+    pub fn boxed(self) -> BoxedContext {
+        Context {
+            rx: self.rx.map(|rx| {
+                rx.map_stream(|stream| Box::new(stream) as Box<dyn AsyncRead + Unpin + Send>)
+            }),
+            tx: self.tx.map(|tx| {
+                tx.map_stream(|stream| Box::new(stream) as Box<dyn AsyncWrite + Unpin + Send>)
+            }),
+            priority_queue: self.priority_queue,
+            normal_queue: self.normal_queue,
+            queued: self.queued,
+            control_queue: self.control_queue,
+            interceptors: self.interceptors,
+            session: self.session,
+            connection: self.connection,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{SubscribeOpts, SubscriptionOpts, UnsubscribeOpts};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+    #[tokio::test]
+    async fn boxed_context_connects() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, _handle) = Context::new();
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        let mut contexts: Vec<BoxedContext> = vec![context.boxed()];
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+        };
+
+        let (rsp, _) = futures::join!(
+            contexts[0].connect(ConnectOpts::new().client_identifier("test")),
+            server
+        );
+
+        assert!(rsp.unwrap().is_left());
+    }
+
+    #[tokio::test]
+    async fn queued_packets_flushed_together() {
+        use crate::PublishOpts;
+        use core::pin::Pin;
+        use core::task::{Context as TaskContext, Poll};
+
+        // Records the size of each write reaching the stream.
+        #[derive(Clone, Default)]
+        struct Recorder(Arc<Mutex<Vec<usize>>>);
+
+        impl AsyncWrite for Recorder {
+            fn poll_write(
+                self: Pin<&mut Self>,
+                _: &mut TaskContext<'_>,
+                buf: &[u8],
+            ) -> Poll<io::Result<usize>> {
+                self.0.lock().unwrap().push(buf.len());
+                Poll::Ready(Ok(buf.len()))
+            }
+
+            fn poll_flush(self: Pin<&mut Self>, _: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn poll_close(self: Pin<&mut Self>, _: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        let (mut context, handle) = Context::new();
+        let (client, mut server) = tokio::io::duplex(1024);
+        let recorder = Recorder::default();
+
+        let opts = ContextIoOptions {
+            tx_buf_size: 1024,
+            ..ContextIoOptions::default()
+        };
+        context.set_up_with_options((client.compat(), recorder.clone()), opts);
+
+        server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+        context.connect(ConnectOpts::new()).await.unwrap();
+        assert_eq!(recorder.0.lock().unwrap().len(), 1); // CONNECT
+
+        let (mut first, mut second, mut third) = (handle.clone(), handle.clone(), handle);
+        let opts = || PublishOpts::new().topic_name("a").payload(b"ok");
+        let test = future::join3(
+            first.publish(opts()),
+            second.publish(opts()),
+            third.publish(opts()),
+        );
+
+        match future::select(Box::pin(test), Box::pin(context.run())).await {
+            future::Either::Left(_) => {}
+            future::Either::Right(_) => unreachable!(),
+        }
+
+        // PUBLISH packets of 8 bytes each, written with a single call.
+        assert_eq!(recorder.0.lock().unwrap()[1..], [24]);
+    }
+
+    #[tokio::test]
+    async fn clean_start_auto() {
+        async fn connect_flags(
+            context: &mut Context<
+                tokio_util::compat::Compat<tokio::io::ReadHalf<tokio::io::DuplexStream>>,
+                tokio_util::compat::Compat<tokio::io::WriteHalf<tokio::io::DuplexStream>>,
+            >,
+        ) -> u8 {
+            let (client, mut server) = tokio::io::duplex(1024);
+            let (crx, ctx) = tokio::io::split(client);
+            context.set_up((crx.compat(), ctx.compat_write()));
+
+            let server = async move {
+                let mut buf = [0u8; 64];
+                let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+                server.write_all(&[0x20, 3, 1, 0, 0]).await.unwrap(); // CONNACK, session present
+                buf[9]
+            };
+
+            let (rsp, flags) = futures::join!(
+                context.connect(
+                    ConnectOpts::new()
+                        .client_identifier("test")
+                        .session_expiry_interval(Duration::from_secs(60))
+                        .clean_start_auto()
+                ),
+                server
+            );
+
+            assert!(rsp.unwrap().is_left());
+            flags
+        }
+
+        const CLEAN_START: u8 = 0x02;
+
+        let (mut context, _handle) = Context::new();
+
+        // No session state, the session is cleared.
+        assert_ne!(connect_flags(&mut context).await & CLEAN_START, 0);
+
+        context
+            .session
+            .publish_flows
+            .push_back(PublishState::WaitingForPubcomp(
+                NonZero::try_from(1).unwrap(),
+            ));
+
+        // Message in flight, the session is resumed.
+        assert_eq!(connect_flags(&mut context).await & CLEAN_START, 0);
+    }
+
+    #[tokio::test]
+    async fn reauth_handler() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, _handle) = Context::new();
+        context.set_up((crx.compat(), ctx.compat_write()));
+        context.on_reauth(|rsp| {
+            assert_eq!(rsp.reason(), AuthReason::ReAuthenticate);
+            OwnedAuthOpts::new()
+                .reason(AuthReason::ContinueAuthentication)
+                .authentication_method("m")
+                .authentication_data(b"data".to_vec())
+        });
+
+        let client = async {
+            context
+                .connect(ConnectOpts::new().client_identifier("test"))
+                .await
+                .unwrap();
+            context.run().await
+        };
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+
+            // AUTH with re-authenticate reason, authentication method and data
+            server
+                .write_all(&[0xf0, 10, 0x19, 8, 0x15, 0, 1, b'm', 0x16, 0, 1, b'x'])
+                .await
+                .unwrap();
+
+            let len = server.read(&mut buf).await.unwrap();
+            buf[..len].to_vec()
+        };
+
+        let (result, rsp) = futures::join!(client, server);
+        assert!(result.is_err()); // Socket closed by the server
+        assert_eq!(rsp[0], 0xf0);
+        assert_eq!(rsp[2], AuthReason::ContinueAuthentication as u8);
+    }
+
+    #[test]
+    fn closed_subscriptions_unindexed() {
+        type TestContext = Context<futures::io::Cursor<Vec<u8>>, futures::io::Cursor<Vec<u8>>>;
+
+        let mut session = Session {
+            awaiting_ack: VecDeque::new(),
+            subscriptions: VecDeque::new(),
+            topic_tree: TopicTree::new(),
+            pending_unsubscribes: VecDeque::new(),
+            pending_pings: VecDeque::new(),
+            #[cfg(feature = "pool")]
+            pool: BufferPool::new(0),
+            publish_flows: VecDeque::new(),
+            publish_callbacks: VecDeque::new(),
+            drain_waiters: Vec::new(),
+            pause_buffer_size: 0,
+        };
+
+        let mut receivers = Vec::new();
+        for subscription_identifier in [1, 2] {
+            let (stream, receiver) = mpsc::unbounded();
+            let topic_filters = vec![(String::from("a"), SubscriptionOptions::default())];
+            TestContext::index_topic_filters(
+                &mut session.topic_tree,
+                subscription_identifier,
+                &topic_filters,
+            );
+            session.subscriptions.push_back((
+                subscription_identifier,
+                Subscription {
+                    stream,
+                    paused: None,
+                    stats: Arc::default(),
+                    topic_filters,
+                    filter: None,
+                },
+            ));
+            receivers.push(receiver);
+        }
+
+        drop(receivers.remove(0)); // Stream of the first subscription dropped
+        TestContext::remove_closed_subscriptions(&mut session);
+
+        assert_eq!(session.subscriptions.len(), 1);
+        assert_eq!(session.subscriptions[0].0, 2);
+
+        let matching: Vec<usize> = session
+            .topic_tree
+            .matching("a")
+            .flatten()
+            .copied()
+            .collect();
+        assert_eq!(matching, [2]);
+    }
+
+    #[test]
+    fn publish_flow_transitions() {
+        type TestContext = Context<futures::io::Cursor<Vec<u8>>, futures::io::Cursor<Vec<u8>>>;
+
+        let packet_id = |val: u16| NonZero::try_from(val).unwrap();
+        let mut session = Session {
+            awaiting_ack: VecDeque::new(),
+            subscriptions: VecDeque::new(),
+            topic_tree: TopicTree::new(),
+            pending_unsubscribes: VecDeque::new(),
+            pending_pings: VecDeque::new(),
+            #[cfg(feature = "pool")]
+            pool: BufferPool::new(0),
+            publish_flows: VecDeque::from([
+                PublishState::WaitingForPuback(packet_id(1), Bytes::new()),
+                PublishState::WaitingForPubrec(packet_id(2), Bytes::new()),
+                PublishState::WaitingForPubrec(packet_id(3), Bytes::new()),
+            ]),
+            publish_callbacks: VecDeque::new(),
+            drain_waiters: Vec::new(),
+            pause_buffer_size: 0,
+        };
+
+        // Unexpected acknowledgement type leaves the flow intact.
+        assert!(!TestContext::advance_publish_flow(
+            &mut session,
+            packet_id(1),
+            PublishAck::Pubcomp
+        ));
+        assert_eq!(session.publish_flows.len(), 3);
+
+        assert!(TestContext::advance_publish_flow(
+            &mut session,
+            packet_id(1),
+            PublishAck::Puback
+        ));
+
+        assert!(!TestContext::advance_publish_flow(
+            &mut session,
+            packet_id(2),
+            PublishAck::Pubrec { accepted: true }
+        ));
+        assert!(matches!(
+            session.publish_flows.front(),
+            Some(PublishState::WaitingForPubcomp(id)) if *id == packet_id(2)
+        ));
+        assert!(TestContext::advance_publish_flow(
+            &mut session,
+            packet_id(2),
+            PublishAck::Pubcomp
+        ));
+
+        // Rejected PUBREC completes the flow.
+        assert!(TestContext::advance_publish_flow(
+            &mut session,
+            packet_id(3),
+            PublishAck::Pubrec { accepted: false }
+        ));
+
+        assert!(!TestContext::advance_publish_flow(
+            &mut session,
+            packet_id(4),
+            PublishAck::Puback
+        ));
+        assert!(session.publish_flows.is_empty());
+    }
+
+    #[test]
+    fn validate_expired_session() {
+        use crate::core::utils::TryDecode;
+
+        type TestContext = Context<futures::io::Cursor<Vec<u8>>, futures::io::Cursor<Vec<u8>>>;
+
+        let connack = |session_present: u8| {
+            ConnackRx::try_decode(Bytes::copy_from_slice(&[0x20, 3, session_present, 0, 0]))
+                .unwrap()
+        };
+
+        let (mut context, _handle) = TestContext::new();
+        context.connection.clean_start = false;
+
+        let packet_id = NonZero::try_from(1).unwrap();
+        let (sender, mut receiver) = oneshot::channel();
+        let (callback_sender, mut callback_receiver) = oneshot::channel();
+        context
+            .session
+            .publish_flows
+            .push_back(PublishState::WaitingForPuback(packet_id, Bytes::new()));
+        context
+            .session
+            .awaiting_ack
+            .push_back(((4 << 24) | (1 << 8), sender)); // PUBACK
+
+        // SUBSCRIBE awaiting SUBACK, unrelated to the lost messages.
+        let (subscribe_sender, mut subscribe_receiver) = oneshot::channel();
+        context
+            .session
+            .awaiting_ack
+            .push_back(((9 << 24) | (2 << 8), subscribe_sender)); // SUBACK
+        context.session.publish_callbacks.push_back((
+            packet_id,
+            Box::new(move |result| {
+                let _ = callback_sender.send(result);
+            }),
+        ));
+
+        // Session kept by the broker, nothing is lost.
+        assert!(TestContext::validate_session(
+            &context.connection,
+            &mut context.session,
+            &connack(1)
+        )
+        .is_ok());
+        assert_eq!(context.session.publish_flows.len(), 1);
+
+        assert!(matches!(
+            TestContext::validate_session(&context.connection, &mut context.session, &connack(0)),
+            Err(MqttError::SessionExpired(_))
+        ));
+        assert!(context.session.publish_flows.is_empty());
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(Some(Err(MqttError::SessionExpired(_))))
+        ));
+        assert!(matches!(
+            callback_receiver.try_recv(),
+            Ok(Some(Err(MqttError::SessionExpired(_))))
+        ));
+        assert!(matches!(subscribe_receiver.try_recv(), Ok(None)));
+        assert_eq!(context.session.awaiting_ack.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn disconnect_bypasses_queued_messages() {
+        use crate::{DisconnectOpts, PublishOpts};
+
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, mut handle) = Context::new();
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        // Enqueue the messages before the context starts processing them.
+        for _ in 0..3 {
+            let publish = handle.publish(PublishOpts::new().topic_name("a").payload(b"ok"));
+            assert!(publish.now_or_never().is_none());
+        }
+        assert!(handle
+            .disconnect(DisconnectOpts::new())
+            .now_or_never()
+            .is_none());
+
+        let client = async move {
+            context
+                .connect(ConnectOpts::new().client_identifier("test"))
+                .await
+                .unwrap();
+            context.run().await
+        };
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+
+            let _ = server.read(&mut buf).await.unwrap();
+            buf[0]
+        };
+
+        match future::select(Box::pin(server), Box::pin(client)).await {
+            future::Either::Left((fixed_hdr, _)) => assert_eq!(fixed_hdr >> 4, 14), // DISCONNECT
+            future::Either::Right(_) => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn inactivity_timeout() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, _handle) = Context::new();
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        let client = async move {
+            context
+                .connect(
+                    ConnectOpts::new()
+                        .client_identifier("test")
+                        .inactivity_timeout(Duration::from_millis(50)),
+                )
+                .await
+                .unwrap();
+            context.run().await
+        };
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+            future::pending::<()>().await; // Keep the connection open
+        };
+
+        match future::select(Box::pin(client), Box::pin(server)).await {
+            future::Either::Left((result, _)) => {
+                assert!(matches!(result, Err(MqttError::InactivityTimeout(_))))
+            }
+            future::Either::Right(_) => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn queue_capacity() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, mut handle) = Context::with_queue_capacity(1);
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        let opts = crate::PublishOpts::new().topic_name("a").payload(b"ok");
+
+        let (sender, receiver) = oneshot::channel();
+        handle
+            .publish_with_callback(opts.clone(), move |result| {
+                let _ = sender.send(result);
+            })
+            .unwrap();
+        assert!(matches!(
+            handle.publish_with_callback(opts.clone(), |_| unreachable!()),
+            Err(MqttError::QueueFull(_))
+        ));
+
+        let client = async move {
+            context
+                .connect(ConnectOpts::new().client_identifier("test"))
+                .await
+                .unwrap();
+            context.run().await
+        };
+
+        let test = async move {
+            receiver.await.unwrap().unwrap();
+
+            // Capacity is released once the context takes the message.
+            handle.publish(opts).await.unwrap();
+        };
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+            future::pending::<()>().await; // Keep the connection open
+        };
+
+        match future::select(Box::pin(test), Box::pin(future::join(client, server))).await {
+            future::Either::Left(_) => {}
+            future::Either::Right(_) => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn publish_without_subscription_identifier() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, mut handle) = Context::new();
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        let client = async move {
+            context
+                .connect(ConnectOpts::new().client_identifier("test"))
+                .await
+                .unwrap();
+            context.run().await
+        };
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+            server
+                .write_all(&[0x32, 7, 0, 1, b'a', 0, 1, 0, b'x']) // PUBLISH, QoS 1, no properties
+                .await
+                .unwrap();
+
+            let mut puback = [0u8; 4];
+            server.read_exact(&mut puback).await.unwrap();
+            assert_eq!(puback, [0x40, 2, 0, 1]);
+
+            let mut pingreq = [0u8; 2];
+            server.read_exact(&mut pingreq).await.unwrap(); // Connection still up
+            server.write_all(&[0xd0, 0]).await.unwrap(); // PINGRESP
+            future::pending::<()>().await; // Keep the connection open
+        };
+
+        let test = async move {
+            while handle.stats().unexpected_publishes == 0 {
+                Delay::new(Duration::from_millis(1)).await;
+            }
+            handle.ping().await.unwrap();
+        };
+
+        match future::select(Box::pin(test), Box::pin(future::join(client, server))).await {
+            future::Either::Left(_) => {}
+            future::Either::Right(_) => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn publish_with_multiple_subscription_identifiers() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, mut handle) = Context::new();
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        let client = async move {
+            context
+                .connect(ConnectOpts::new().client_identifier("test"))
+                .await
+                .unwrap();
+            context.run().await
+        };
+
+        let test = async move {
+            let mut first = handle
+                .subscribe(SubscribeOpts::new().subscription("a/+", SubscriptionOpts::new()))
+                .await
+                .unwrap()
+                .stream();
+            let mut second = handle
+                .subscribe(SubscribeOpts::new().subscription("a/#", SubscriptionOpts::new()))
+                .await
+                .unwrap()
+                .stream();
+
+            assert_eq!(first.next().await.unwrap().payload(), b"ok");
+            assert_eq!(second.next().await.unwrap().payload(), b"ok");
+        };
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+
+            for _ in 0..2 {
+                let _ = server.read(&mut buf).await.unwrap(); // SUBSCRIBE
+                server
+                    .write_all(&[0x90, 4, buf[2], buf[3], 0, 0]) // SUBACK
+                    .await
+                    .unwrap();
+            }
+
+            // PUBLISH matching both subscriptions
+            server
+                .write_all(&[0x30, 10, 0, 1, b'a', 4, 0x0b, 1, 0x0b, 2, b'o', b'k'])
+                .await
+                .unwrap();
+            future::pending::<()>().await; // Keep the connection open
+        };
+
+        match future::select(Box::pin(test), Box::pin(future::join(client, server))).await {
+            future::Either::Left(_) => {}
+            future::Either::Right(_) => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn session_expiry_interval_overridden() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, _handle) = Context::new();
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        let client = async move {
+            let rsp = context
+                .connect(
+                    ConnectOpts::new()
+                        .client_identifier("test")
+                        .session_expiry_interval(Duration::from_secs(60)),
+                )
+                .await
+                .unwrap()
+                .left()
+                .unwrap();
+            assert_eq!(rsp.requested_session_expiry_interval, 60);
+            assert_eq!(rsp.session_expiry_interval(), Duration::from_secs(10));
+            assert_eq!(context.connection.session_expiry_interval, 10);
+        };
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server
+                .write_all(&[0x20, 8, 0, 0, 5, 0x11, 0, 0, 0, 10]) // CONNACK, session expiry interval
+                .await
+                .unwrap();
+        };
+
+        futures::join!(client, server);
+    }
+
+    #[tokio::test]
+    async fn protocol_v311() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, mut handle) = Context::new();
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        let client = async move {
+            let rsp = context
+                .connect(
+                    ConnectOpts::new()
+                        .client_identifier("test")
+                        .protocol_version(MqttVersion::V311),
+                )
+                .await
+                .unwrap();
+            assert!(rsp.left().unwrap().session_present());
+            context.run().await
+        };
+
+        let test = async move {
+            let rsp = handle
+                .subscribe(SubscribeOpts::new().subscription("a/+", SubscriptionOpts::new()))
+                .await
+                .unwrap();
+            assert_eq!(rsp.granted_qos_for(0), Some(QoS::AtLeastOnce));
+
+            let mut stream = rsp.stream();
+            assert_eq!(stream.next().await.unwrap().payload(), b"ok");
+        };
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let len = server.read(&mut buf).await.unwrap(); // CONNECT
+            assert_eq!(buf[8], 4); // Protocol level
+            assert_eq!(&buf[12..len], &[0, 4, b't', b'e', b's', b't']); // No properties
+            server.write_all(&[0x20, 2, 1, 0]).await.unwrap(); // CONNACK
+
+            let len = server.read(&mut buf).await.unwrap(); // SUBSCRIBE
+            assert_eq!(&buf[4..len], &[0, 3, b'a', b'/', b'+', 2]); // No properties
+            server
+                .write_all(&[0x90, 3, buf[2], buf[3], 1]) // SUBACK
+                .await
+                .unwrap();
+
+            // PUBLISH without properties, routed by the topic name
+            server
+                .write_all(&[0x30, 7, 0, 3, b'a', b'/', b'b', b'o', b'k'])
+                .await
+                .unwrap();
+            future::pending::<()>().await; // Keep the connection open
+        };
+
+        match future::select(Box::pin(test), Box::pin(future::join(client, server))).await {
+            future::Either::Left(_) => {}
+            future::Either::Right(_) => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn protocol_v311_unsubscribe() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, mut handle) = Context::new();
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        let client = async move {
+            context
+                .connect(
+                    ConnectOpts::new()
+                        .client_identifier("test")
+                        .protocol_version(MqttVersion::V311),
+                )
+                .await
+                .unwrap();
+            context.run().await
+        };
+
+        let test = async move {
+            let mut stream_a = handle
+                .subscribe(SubscribeOpts::new().subscription("a", SubscriptionOpts::new()))
+                .await
+                .unwrap()
+                .stream();
+            let mut stream_b = handle
+                .subscribe(SubscribeOpts::new().subscription("b", SubscriptionOpts::new()))
+                .await
+                .unwrap()
+                .stream();
+
+            handle
+                .unsubscribe(UnsubscribeOpts::new().topic_filter("a"))
+                .await
+                .unwrap();
+
+            assert_eq!(stream_b.next().await.unwrap().payload(), b"ok");
+            assert!(stream_a.next().now_or_never().is_none());
+        };
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 2, 0, 0]).await.unwrap(); // CONNACK
+
+            for _ in 0..2 {
+                let _ = server.read(&mut buf).await.unwrap(); // SUBSCRIBE
+                server
+                    .write_all(&[0x90, 3, buf[2], buf[3], 0]) // SUBACK
+                    .await
+                    .unwrap();
+            }
+
+            let _ = server.read(&mut buf).await.unwrap(); // UNSUBSCRIBE
+            server
+                .write_all(&[0xb0, 2, buf[2], buf[3]]) // UNSUBACK
+                .await
+                .unwrap();
+
+            // The stale topic filter must no longer route messages.
+            for topic in [b'a', b'b'] {
+                server
+                    .write_all(&[0x30, 5, 0, 1, topic, b'o', b'k']) // PUBLISH
+                    .await
+                    .unwrap();
+            }
+            future::pending::<()>().await; // Keep the connection open
+        };
+
+        match future::select(Box::pin(test), Box::pin(future::join(client, server))).await {
+            future::Either::Left(_) => {}
+            future::Either::Right(_) => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribe_with_filter() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, mut handle) = Context::new();
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        let client = async move {
+            context
+                .connect(ConnectOpts::new().client_identifier("test"))
+                .await
+                .unwrap();
+            context.run().await
+        };
+
+        let test = async move {
+            let mut stream = handle
+                .subscribe_with_filter(
+                    SubscribeOpts::new().subscription("a", SubscriptionOpts::new()),
+                    |msg| msg.payload() != b"no",
+                )
+                .await
+                .unwrap()
+                .stream();
+
+            assert_eq!(stream.next().await.unwrap().payload(), b"ok");
+            assert_eq!(stream.stats().messages_received, 1);
+        };
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+
+            let _ = server.read(&mut buf).await.unwrap(); // SUBSCRIBE
+            server
+                .write_all(&[0x90, 4, buf[2], buf[3], 0, 0]) // SUBACK
+                .await
+                .unwrap();
+
+            for payload in [b"no", b"ok"] {
+                server
+                    .write_all(&[0x30, 8, 0, 1, b'a', 2, 0x0b, 1, payload[0], payload[1]]) // PUBLISH
+                    .await
+                    .unwrap();
+            }
+            future::pending::<()>().await; // Keep the connection open
+        };
+
+        match future::select(Box::pin(test), Box::pin(future::join(client, server))).await {
+            future::Either::Left(_) => {}
+            future::Either::Right(_) => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn paused_subscription() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, mut handle) = Context::new();
+        context.set_up_with_options(
+            (crx.compat(), ctx.compat_write()),
+            ContextIoOptions {
+                pause_buffer_size: 2,
+                ..ContextIoOptions::default()
+            },
+        );
+
+        let client = async move {
+            context
+                .connect(ConnectOpts::new().client_identifier("test"))
+                .await
+                .unwrap();
+            context.run().await
+        };
+
+        let (sender, receiver) = oneshot::channel();
+
+        let test = async move {
+            let mut stream = handle
+                .subscribe(SubscribeOpts::new().subscription("a", SubscriptionOpts::new()))
+                .await
+                .unwrap()
+                .stream();
+
+            stream.pause();
+            tokio::task::yield_now().await; // Let the context handle the request
+            sender.send(()).unwrap();
+
+            // The oldest message did not fit in the buffer.
+            while stream.stats().messages_dropped != 1 {
+                tokio::task::yield_now().await;
+            }
+            assert!(stream.next().now_or_never().is_none());
+            assert_eq!(stream.stats().messages_received, 0);
+
+            stream.resume();
+            assert_eq!(stream.next().await.unwrap().payload(), b"2");
+            assert_eq!(stream.next().await.unwrap().payload(), b"3");
+
+            let stats = stream.stats();
+            assert_eq!(stats.messages_received, 2);
+            assert_eq!(stats.bytes_received, 2);
+            assert_eq!(stats.messages_dropped, 1);
+        };
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+
+            let _ = server.read(&mut buf).await.unwrap(); // SUBSCRIBE
+            server
+                .write_all(&[0x90, 4, buf[2], buf[3], 0, 0]) // SUBACK
+                .await
+                .unwrap();
+
+            receiver.await.unwrap();
+            for payload in [b'1', b'2', b'3'] {
+                server
+                    .write_all(&[0x30, 7, 0, 1, b'a', 2, 0x0b, 1, payload]) // PUBLISH
+                    .await
+                    .unwrap();
+            }
+            future::pending::<()>().await; // Keep the connection open
+        };
+
+        match future::select(Box::pin(test), Box::pin(future::join(client, server))).await {
+            future::Either::Left(_) => {}
+            future::Either::Right(_) => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn wait_until_connected() {
+        let (mut context, handle) = Context::new();
+        let waiter = handle.wait_until_connected();
+
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        let broker = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 3, 0, 0x87, 0]).await.unwrap(); // CONNACK, not authorized
+        };
+
+        let (rsp, _) = future::join(context.connect(ConnectOpts::new()), broker).await;
+        assert!(rsp.is_err());
+        assert!(waiter.await.is_err());
+
+        context.reset(false);
+        let waiter = handle.wait_until_connected();
+
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        let broker = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+        };
+
+        let (rsp, _) = future::join(context.connect(ConnectOpts::new()), broker).await;
+        assert!(rsp.is_ok());
+        assert!(waiter.await.is_ok());
+        assert!(handle.wait_until_connected().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn reset() {
+        let (mut context, mut handle) = Context::new();
+
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        let session = async {
+            context
+                .connect(
+                    ConnectOpts::new()
+                        .client_identifier("test")
+                        .session_expiry_interval(Duration::from_secs(60)),
+                )
+                .await
+                .unwrap();
+            context.run().await
+        };
+
+        let test = async {
+            handle
+                .subscribe(SubscribeOpts::new().subscription("a", SubscriptionOpts::new()))
+                .await
+                .unwrap()
+                .stream()
+        };
+
+        let first_server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+
+            let _ = server.read(&mut buf).await.unwrap(); // SUBSCRIBE
+            server
+                .write_all(&[0x90, 4, buf[2], buf[3], 0, 0]) // SUBACK
+                .await
+                .unwrap();
+            // Connection lost
+        };
+
+        let (result, mut stream, _) = futures::join!(session, test, first_server);
+        assert!(matches!(result, Err(MqttError::SocketClosed(_))));
+
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+        context
+            .reset(true)
+            .set_up((crx.compat(), ctx.compat_write()));
+
+        let second_server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            assert_ne!(buf[9] & 0x02, 0); // Clean start
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+        };
+
+        let (rsp, _) = futures::join!(
+            context.connect(
+                ConnectOpts::new()
+                    .client_identifier("test")
+                    .clean_start_auto()
+            ),
+            second_server
+        );
+        assert!(rsp.is_ok());
+        assert!(stream.next().await.is_none()); // Subscription discarded with the session
+    }
+}
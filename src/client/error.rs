@@ -10,25 +10,70 @@ use std::{
     error::Error,
     fmt::{self, Display},
     io, str,
+    sync::Arc,
     time::{Duration, SystemTimeError},
 };
 
-/// Socket was closed.
+/// Socket was closed. Wraps the underlying [io::Error] describing the cause.
+/// When the remote end closes the connection, the error kind is [io::ErrorKind::UnexpectedEof].
 ///
 #[derive(Debug, Clone)]
-pub struct SocketClosed;
+pub struct SocketClosed {
+    err: Arc<io::Error>,
+}
+
+impl SocketClosed {
+    /// Accesses the kind of the underlying I/O error.
+    ///
+    pub fn kind(&self) -> io::ErrorKind {
+        self.err.kind()
+    }
+
+    /// Returns `true` if the cause of closing the socket is considered transient,
+    /// meaning that reconnection attempt is likely to succeed.
+    ///
+    /// Besides timeouts, resets and broken pipes, this includes [ConnectionAborted](io::ErrorKind::ConnectionAborted),
+    /// reported by some platforms instead of a reset, and [UnexpectedEof](io::ErrorKind::UnexpectedEof), meaning
+    /// the broker closed the connection, e.g. when restarting.
+    ///
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self.kind(),
+            io::ErrorKind::TimedOut
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::BrokenPipe
+                | io::ErrorKind::UnexpectedEof
+        )
+    }
+}
 
 impl fmt::Display for SocketClosed {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "socket closed")
+        write!(f, "socket closed: {}", self.err)
     }
 }
 
-impl Error for SocketClosed {}
+impl Error for SocketClosed {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.err.as_ref())
+    }
+
+    #[cfg(feature = "backtrace")]
+    fn provide<'a>(&'a self, request: &mut std::error::Request<'a>) {
+        self.err.provide(request);
+    }
+}
 
 impl From<io::Error> for SocketClosed {
-    fn from(_: io::Error) -> Self {
-        Self
+    fn from(err: io::Error) -> Self {
+        Self { err: Arc::new(err) }
+    }
+}
+
+impl From<io::ErrorKind> for SocketClosed {
+    fn from(kind: io::ErrorKind) -> Self {
+        Self::from(io::Error::from(kind))
     }
 }
 
@@ -87,7 +132,9 @@ impl Disconnected {
         self.packet.reason
     }
 
-    /// Accesses session expiry interval.
+    /// Accesses session expiry interval. The broker is not allowed to change the session expiry
+    /// interval on disconnection (DISCONNECT with the property is treated as malformed), so the
+    /// interval agreed on during connection remains in effect and this value is always zero.
     ///
     pub fn session_expiry_interval(&self) -> Duration {
         Duration::from_secs(u64::from(u32::from(self.packet.session_expiry_interval)))
@@ -96,13 +143,24 @@ impl Disconnected {
     /// Accesses reason string.
     ///
     pub fn reason_string(&self) -> Option<&str> {
+        self.reason_string_bytes()
+            .map(str::from_utf8)
+            .and_then(Result::ok)
+    }
+
+    /// Accesses raw bytes of the reason string, without UTF-8 validation.
+    ///
+    pub fn reason_string_bytes(&self) -> Option<&[u8]> {
         self.packet
             .reason_string
             .as_ref()
-            .map(|val| &val.0)
-            .map(|val| val.0.as_ref())
-            .map(str::from_utf8)
-            .and_then(Result::ok)
+            .map(|val| val.0 .0.as_ref())
+    }
+
+    /// Checks whether the broker has attached a reason string.
+    ///
+    pub fn has_reason_string(&self) -> bool {
+        self.packet.reason_string.is_some()
     }
 
     /// Accesses server reference.
@@ -117,6 +175,17 @@ impl Disconnected {
             .and_then(Result::ok)
     }
 
+    /// Accesses the server the client should connect to instead. Returns the
+    /// [server reference](Disconnected::server_reference) only if the [reason](Disconnected::reason)
+    /// is a [redirect](DisconnectReason::is_redirect).
+    ///
+    pub fn redirect_target(&self) -> Option<&str> {
+        self.reason()
+            .is_redirect()
+            .then(|| self.server_reference())
+            .flatten()
+    }
+
     /// Accesses user properties.
     ///
     pub fn user_properties(&self) -> &UserProperties {
@@ -198,6 +267,8 @@ impl fmt::Display for QuotaExceeded {
     }
 }
 
+impl Error for QuotaExceeded {}
+
 /// Client attemps to send more data to the server than
 /// [maximum packet size](super::rsp::ConnectRsp::maximum_packet_size)
 /// property allows.
@@ -214,12 +285,307 @@ impl fmt::Display for MaximumPacketSizeExceeded {
     }
 }
 
+impl Error for MaximumPacketSizeExceeded {}
+
+/// Client requested session resumption, but the broker has not found the session
+/// (CONNACK session present flag is not set) while there are still packets awaiting
+/// retransmission. Packets awaiting acknowledgement are discarded.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct SessionExpired;
+
+impl fmt::Display for SessionExpired {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{{ \"type\": \"SessionExpired\", \"message\": \"session expired\" }}"
+        )
+    }
+}
+
+impl Error for SessionExpired {}
+
+/// All packet identifiers are in use by packets awaiting acknowledgement,
+/// a new packet cannot be sent until some of them are acknowledged.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct PacketIdentifierExhausted;
+
+impl fmt::Display for PacketIdentifierExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{{ \"type\": \"PacketIdentifierExhausted\", \"message\": \"packet identifier already in use\" }}"
+        )
+    }
+}
+
+impl Error for PacketIdentifierExhausted {}
+
+/// No packet was received from the broker within the
+/// [inactivity timeout](crate::ConnectOpts::inactivity_timeout).
+///
+#[derive(Debug, Clone, Copy)]
+pub struct InactivityTimeout;
+
+impl fmt::Display for InactivityTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{{ \"type\": \"InactivityTimeout\", \"message\": \"no packet received within the inactivity timeout\" }}"
+        )
+    }
+}
+
+impl Error for InactivityTimeout {}
+
+/// The packet started by the broker was not received completely within the
+/// [read timeout](crate::ContextIoOptions::read_timeout).
+///
+#[derive(Debug, Clone, Copy)]
+pub struct ReadTimeout;
+
+impl fmt::Display for ReadTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{{ \"type\": \"ReadTimeout\", \"message\": \"packet not received completely within the read timeout\" }}"
+        )
+    }
+}
+
+impl Error for ReadTimeout {}
+
+/// Operation performed with the [TimedContextHandle](crate::TimedContextHandle)
+/// did not complete within the deadline.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct OperationTimeout;
+
+impl fmt::Display for OperationTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{{ \"type\": \"OperationTimeout\", \"message\": \"operation did not complete within the deadline\" }}"
+        )
+    }
+}
+
+impl Error for OperationTimeout {}
+
+/// PUBLISH packet received without the subscription identifier, so it cannot be routed
+/// to any subscription. The message is acknowledged and counted in
+/// [unexpected_publishes](crate::ContextStats::unexpected_publishes), the connection stays up.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct UnexpectedPublish;
+
+impl fmt::Display for UnexpectedPublish {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{{ \"type\": \"UnexpectedPublish\", \"message\": \"PUBLISH without subscription identifier received, message not delivered to any subscription\" }}"
+        )
+    }
+}
+
+impl Error for UnexpectedPublish {}
+
+/// Message queue of the [Context](super::context::Context) is at its
+/// [capacity](super::context::Context::with_queue_capacity).
+///
+#[derive(Debug, Clone, Copy)]
+pub struct QueueFull;
+
+impl fmt::Display for QueueFull {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{{ \"type\": \"QueueFull\", \"message\": \"message queue is full\" }}"
+        )
+    }
+}
+
+impl Error for QueueFull {}
+
+/// [Duration](std::time::Duration) supplied to the options builder does not fit
+/// in the range of the corresponding packet field.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct DurationOverflow;
+
+impl fmt::Display for DurationOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{{ \"type\": \"DurationOverflow\", \"message\": \"duration out of range\" }}"
+        )
+    }
+}
+
+impl Error for DurationOverflow {}
+
+/// Compressing or decompressing the payload failed, see [compress](crate::compress).
+/// Wraps the underlying [io::Error].
+///
+#[cfg(feature = "compress")]
+#[derive(Debug, Clone)]
+pub struct CompressionError {
+    err: Arc<io::Error>,
+}
+
+#[cfg(feature = "compress")]
+impl CompressionError {
+    /// Accesses the kind of the underlying I/O error.
+    ///
+    pub fn kind(&self) -> io::ErrorKind {
+        self.err.kind()
+    }
+}
+
+#[cfg(feature = "compress")]
+impl fmt::Display for CompressionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{{ \"type\": \"CompressionError\", \"message\": \"{}\" }}",
+            self.err
+        )
+    }
+}
+
+#[cfg(feature = "compress")]
+impl Error for CompressionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.err.as_ref())
+    }
+}
+
+#[cfg(feature = "compress")]
+impl From<io::Error> for CompressionError {
+    fn from(err: io::Error) -> Self {
+        Self { err: Arc::new(err) }
+    }
+}
+
+/// String supplied as the [topic filter](crate::TopicFilter) is not valid.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct InvalidTopicFilter;
+
+impl fmt::Display for InvalidTopicFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{{ \"type\": \"InvalidTopicFilter\", \"message\": \"invalid topic filter\" }}"
+        )
+    }
+}
+
+impl Error for InvalidTopicFilter {}
+
+/// Violation of the invariants of the [connection options](crate::ConnectOpts),
+/// see [validate](crate::ConnectOpts::validate).
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationError {
+    /// Authentication data is set without the authentication method.
+    ///
+    AuthenticationDataWithoutMethod,
+
+    /// One of the will message fields is set without the will topic.
+    ///
+    WillWithoutTopic,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            Self::AuthenticationDataWithoutMethod => {
+                "authentication data set without authentication method"
+            }
+            Self::WillWithoutTopic => "will message fields set without will topic",
+        };
+
+        write!(
+            f,
+            "{{ \"type\": \"ValidationError\", \"message\": \"{}\" }}",
+            message
+        )
+    }
+}
+
+impl Error for ValidationError {}
+
+/// String supplied to [from_url](crate::ConnectOpts::from_url) is not a valid broker URL.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlParseError {
+    /// Scheme other than `mqtt://`, `mqtts://`, `ws://` or `wss://`.
+    ///
+    UnsupportedScheme,
+
+    /// Host is missing or malformed.
+    ///
+    InvalidHost,
+
+    /// Port is not a number in the [u16] range.
+    ///
+    InvalidPort,
+
+    /// Username or password is percent-encoded, which is not supported.
+    ///
+    EncodedCredentials,
+}
+
+impl fmt::Display for UrlParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            Self::UnsupportedScheme => "unsupported URL scheme",
+            Self::InvalidHost => "invalid host",
+            Self::InvalidPort => "invalid port",
+            Self::EncodedCredentials => "percent-encoded credentials",
+        };
+
+        write!(
+            f,
+            "{{ \"type\": \"UrlParseError\", \"message\": \"{}\" }}",
+            message
+        )
+    }
+}
+
+impl Error for UrlParseError {}
+
+/// Environment variable read by [from_env](crate::ConnectOpts::from_env) has a malformed value.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvConfigError {
+    /// Value of the variable, given as the name and the raw value, could not be parsed.
+    ///
+    ParseError(&'static str, String),
+}
+
+impl fmt::Display for EnvConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ParseError(name, value) => write!(
+                f,
+                "{{ \"type\": \"EnvConfigError\", \"message\": \"invalid value of {}: '{}'\" }}",
+                name, value
+            ),
+        }
+    }
+}
+
+impl Error for EnvConfigError {}
+
 /// Connection could not be established with the server. Accesses
 /// CONNACK packet with reason value greater or equal 0x80.
 ///
 #[derive(Clone)]
 pub struct ConnectError {
-    packet: ConnackRx,
+    packet: Box<ConnackRx>, // Boxed to keep the size of MqttError small
 }
 
 impl ConnectError {
@@ -287,13 +653,95 @@ impl Error for ConnectError {}
 impl From<ConnackRx> for ConnectError {
     fn from(packet: ConnackRx) -> Self {
         debug_assert!(packet.reason as u8 >= 0x80);
-        Self { packet }
+        Self {
+            packet: Box::new(packet),
+        }
     }
 }
 
 impl From<ConnectError> for MqttError {
     fn from(err: ConnectError) -> Self {
-        MqttError::ConnectError(err)
+        match err.reason() {
+            ConnectReason::UseAnotherServer | ConnectReason::ServerMoved => {
+                MqttError::ServerMoved(ServerMovedError { packet: err.packet })
+            }
+            _ => MqttError::ConnectError(err),
+        }
+    }
+}
+
+/// Broker redirected the client to another server. Accesses CONNACK packet with
+/// [UseAnotherServer](ConnectReason::UseAnotherServer) or [ServerMoved](ConnectReason::ServerMoved) reason.
+///
+#[derive(Clone)]
+pub struct ServerMovedError {
+    packet: Box<ConnackRx>,
+}
+
+impl ServerMovedError {
+    /// Accesses reason value.
+    ///
+    pub fn reason(&self) -> ConnectReason {
+        self.packet.reason
+    }
+
+    /// Returns `true` when the server has moved permanently ([ServerMoved](ConnectReason::ServerMoved)),
+    /// `false` when the client should temporarily use another server ([UseAnotherServer](ConnectReason::UseAnotherServer)).
+    ///
+    pub fn is_permanent(&self) -> bool {
+        self.packet.reason == ConnectReason::ServerMoved
+    }
+
+    /// Accesses server reference, i.e. the address of the server to connect to.
+    ///
+    pub fn server_reference(&self) -> Option<&str> {
+        self.packet
+            .server_reference
+            .as_ref()
+            .map(|val| &val.0)
+            .map(|val| val.0.as_ref())
+            .map(str::from_utf8)
+            .and_then(Result::ok)
+    }
+
+    /// Accesses reason string.
+    ///
+    pub fn reason_string(&self) -> Option<&str> {
+        self.packet
+            .reason_string
+            .as_ref()
+            .map(|val| &val.0)
+            .map(|val| val.0.as_ref())
+            .map(str::from_utf8)
+            .and_then(Result::ok)
+    }
+}
+
+impl fmt::Debug for ServerMovedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerMovedError")
+            .field("reason", &self.reason())
+            .field("server_reference", &self.server_reference())
+            .field("reason_string", &self.reason_string())
+            .finish()
+    }
+}
+
+impl fmt::Display for ServerMovedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{{ \"type\": \"ServerMovedError\", \"message\": \"server moved: {}\" }}",
+            self.server_reference().unwrap_or_default()
+        )
+    }
+}
+
+impl Error for ServerMovedError {}
+
+impl From<ServerMovedError> for MqttError {
+    fn from(err: ServerMovedError) -> Self {
+        MqttError::ServerMoved(err)
     }
 }
 
@@ -302,7 +750,7 @@ impl From<ConnectError> for MqttError {
 ///
 #[derive(Clone)]
 pub struct AuthError {
-    packet: AuthRx,
+    packet: Box<AuthRx>,
 }
 
 impl AuthError {
@@ -357,7 +805,9 @@ impl Error for AuthError {}
 impl From<AuthRx> for AuthError {
     fn from(packet: AuthRx) -> Self {
         debug_assert!(packet.reason as u8 >= 0x80);
-        Self { packet }
+        Self {
+            packet: Box::new(packet),
+        }
     }
 }
 
@@ -406,6 +856,33 @@ where
     }
 }
 
+impl<ReasonT> AckError<ReasonT>
+where
+    ReasonT: Default + Copy + fmt::Debug,
+{
+    fn fmt_with_reason_string(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        type_name: &str,
+        reason: u8,
+    ) -> fmt::Result {
+        write!(
+            f,
+            "{{ \"type\": \"{}\", \"message\": \"{} error: {} [{:?}]",
+            type_name,
+            type_name,
+            reason,
+            self.reason()
+        )?;
+
+        if let Some(reason_string) = self.reason_string() {
+            write!(f, ": {}", reason_string.escape_default())?;
+        }
+
+        write!(f, "\" }}")
+    }
+}
+
 impl<ReasonT> fmt::Debug for AckError<ReasonT>
 where
     ReasonT: Copy + Default + fmt::Debug,
@@ -443,11 +920,7 @@ impl Error for PubackError {}
 
 impl Display for PubackError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{{ \"type\": \"PubackError\", \"message\": \"PubackError error: {} [{:?}]\" }}",
-            self.packet.reason as u8, self.packet.reason
-        )
+        self.fmt_with_reason_string(f, "PubackError", self.packet.reason as u8)
     }
 }
 
@@ -466,11 +939,7 @@ impl Error for PubrecError {}
 
 impl Display for PubrecError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{{ \"type\": \"PubrecError\", \"message\": \"PubrecError error: {} [{:?}]\" }}",
-            self.packet.reason as u8, self.packet.reason
-        )
+        self.fmt_with_reason_string(f, "PubrecError", self.packet.reason as u8)
     }
 }
 
@@ -489,11 +958,7 @@ impl Error for PubcompError {}
 
 impl Display for PubcompError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{{ \"type\": \"PubcompError\", \"message\": \"PubcompError error: {} [{:?}]\" }}",
-            self.packet.reason as u8, self.packet.reason
-        )
+        self.fmt_with_reason_string(f, "PubcompError", self.packet.reason as u8)
     }
 }
 
@@ -552,6 +1017,38 @@ pub enum MqttError {
     /// See [MaximumPacketSizeExceeded](crate::client::error::MaximumPacketSizeExceeded)
     ///
     MaximumPacketSizeExceeded(MaximumPacketSizeExceeded),
+
+    /// See [SessionExpired](crate::client::error::SessionExpired)
+    ///
+    SessionExpired(SessionExpired),
+
+    /// See [ServerMovedError](crate::client::error::ServerMovedError)
+    ///
+    ServerMoved(ServerMovedError),
+
+    /// See [PacketIdentifierExhausted](crate::client::error::PacketIdentifierExhausted)
+    ///
+    PacketIdentifierExhausted(PacketIdentifierExhausted),
+
+    /// See [InactivityTimeout](crate::client::error::InactivityTimeout)
+    ///
+    InactivityTimeout(InactivityTimeout),
+
+    /// See [ReadTimeout](crate::client::error::ReadTimeout)
+    ///
+    ReadTimeout(ReadTimeout),
+
+    /// See [QueueFull](crate::client::error::QueueFull)
+    ///
+    QueueFull(QueueFull),
+
+    /// See [OperationTimeout](crate::client::error::OperationTimeout)
+    ///
+    OperationTimeout(OperationTimeout),
+
+    /// See [UnexpectedPublish](crate::client::error::UnexpectedPublish)
+    ///
+    UnexpectedPublish(UnexpectedPublish),
 }
 
 impl fmt::Display for MqttError {
@@ -578,11 +1075,167 @@ impl fmt::Display for MqttError {
             }
             Self::QuotaExceeded(err) => write!(f, "{}", err),
             Self::MaximumPacketSizeExceeded(err) => write!(f, "{}", err),
+            Self::SessionExpired(err) => write!(f, "{}", err),
+            Self::ServerMoved(err) => write!(f, "{}", err),
+            Self::PacketIdentifierExhausted(err) => write!(f, "{}", err),
+            Self::InactivityTimeout(err) => write!(f, "{}", err),
+            Self::ReadTimeout(err) => write!(f, "{}", err),
+            Self::QueueFull(err) => write!(f, "{}", err),
+            Self::OperationTimeout(err) => write!(f, "{}", err),
+            Self::UnexpectedPublish(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl MqttError {
+    /// Returns `true` if the error is transient, meaning that the connection with the broker
+    /// may be reestablished and the operation retried. See [SocketClosed::is_transient].
+    ///
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::SocketClosed(err) => err.is_transient(),
+            _ => false,
+        }
+    }
+}
+
+impl Error for MqttError {
+    #[cfg(feature = "backtrace")]
+    fn provide<'a>(&'a self, request: &mut std::error::Request<'a>) {
+        match self {
+            Self::InternalError(err) => err.provide(request),
+            Self::ConnectError(err) => err.provide(request),
+            Self::AuthError(err) => err.provide(request),
+            Self::PubackError(err) => err.provide(request),
+            Self::PubrecError(err) => err.provide(request),
+            Self::PubcompError(err) => err.provide(request),
+            Self::SocketClosed(err) => err.provide(request),
+            Self::HandleClosed(err) => err.provide(request),
+            Self::ContextExited(err) => err.provide(request),
+            Self::Disconnected(err) => err.provide(request),
+            Self::CodecError(err) => err.provide(request),
+            Self::QuotaExceeded(err) => err.provide(request),
+            Self::MaximumPacketSizeExceeded(err) => err.provide(request),
+            Self::SessionExpired(err) => err.provide(request),
+            Self::ServerMoved(err) => err.provide(request),
+            Self::PacketIdentifierExhausted(err) => err.provide(request),
+            Self::InactivityTimeout(err) => err.provide(request),
+            Self::ReadTimeout(err) => err.provide(request),
+            Self::QueueFull(err) => err.provide(request),
+            Self::OperationTimeout(err) => err.provide(request),
+            Self::UnexpectedPublish(err) => err.provide(request),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct SerializedError<'a, ReasonT> {
+    r#type: &'static str,
+    message: String,
+    reason_code: Option<u8>,
+    reason: Option<ReasonT>,
+    reason_string: Option<&'a str>,
+}
+
+#[cfg(feature = "serde")]
+impl<'a> SerializedError<'a, ()> {
+    fn new(r#type: &'static str, err: &MqttError) -> Self {
+        Self {
+            r#type,
+            message: err.to_string(),
+            reason_code: None,
+            reason: None,
+            reason_string: None,
+        }
+    }
+
+    fn with_reason<ReasonT>(
+        self,
+        reason_code: u8,
+        reason: ReasonT,
+        reason_string: Option<&'a str>,
+    ) -> SerializedError<'a, ReasonT> {
+        SerializedError {
+            r#type: self.r#type,
+            message: self.message,
+            reason_code: Some(reason_code),
+            reason: Some(reason),
+            reason_string,
         }
     }
 }
 
-impl Error for MqttError {}
+/// Serialized as a map holding the error `type`, `message` and, for the errors carrying
+/// the reason value, `reason_code` (numeric value), `reason` (symbolic name) and `reason_string`.
+///
+#[cfg(feature = "serde")]
+impl serde::Serialize for MqttError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Self::InternalError(_) => {
+                SerializedError::new("InternalError", self).serialize(serializer)
+            }
+            Self::ConnectError(err) => SerializedError::new("ConnectError", self)
+                .with_reason(err.reason() as u8, err.reason(), err.reason_string())
+                .serialize(serializer),
+            Self::AuthError(err) => SerializedError::new("AuthError", self)
+                .with_reason(err.reason() as u8, err.reason(), err.reason_string())
+                .serialize(serializer),
+            Self::PubackError(err) => SerializedError::new("PubackError", self)
+                .with_reason(err.reason() as u8, err.reason(), err.reason_string())
+                .serialize(serializer),
+            Self::PubrecError(err) => SerializedError::new("PubrecError", self)
+                .with_reason(err.reason() as u8, err.reason(), err.reason_string())
+                .serialize(serializer),
+            Self::PubcompError(err) => SerializedError::new("PubcompError", self)
+                .with_reason(err.reason() as u8, err.reason(), err.reason_string())
+                .serialize(serializer),
+            Self::SocketClosed(_) => {
+                SerializedError::new("SocketClosed", self).serialize(serializer)
+            }
+            Self::HandleClosed(_) => {
+                SerializedError::new("HandleClosed", self).serialize(serializer)
+            }
+            Self::ContextExited(_) => {
+                SerializedError::new("ContextExited", self).serialize(serializer)
+            }
+            Self::Disconnected(err) => SerializedError::new("Disconnected", self)
+                .with_reason(err.reason() as u8, err.reason(), err.reason_string())
+                .serialize(serializer),
+            Self::CodecError(_) => SerializedError::new("CodecError", self).serialize(serializer),
+            Self::QuotaExceeded(_) => {
+                SerializedError::new("QuotaExceeded", self).serialize(serializer)
+            }
+            Self::MaximumPacketSizeExceeded(_) => {
+                SerializedError::new("MaximumPacketSizeExceeded", self).serialize(serializer)
+            }
+            Self::SessionExpired(_) => {
+                SerializedError::new("SessionExpired", self).serialize(serializer)
+            }
+            Self::ServerMoved(err) => SerializedError::new("ServerMoved", self)
+                .with_reason(err.reason() as u8, err.reason(), err.reason_string())
+                .serialize(serializer),
+            Self::PacketIdentifierExhausted(_) => {
+                SerializedError::new("PacketIdentifierExhausted", self).serialize(serializer)
+            }
+            Self::InactivityTimeout(_) => {
+                SerializedError::new("InactivityTimeout", self).serialize(serializer)
+            }
+            Self::ReadTimeout(_) => SerializedError::new("ReadTimeout", self).serialize(serializer),
+            Self::QueueFull(_) => SerializedError::new("QueueFull", self).serialize(serializer),
+            Self::OperationTimeout(_) => {
+                SerializedError::new("OperationTimeout", self).serialize(serializer)
+            }
+            Self::UnexpectedPublish(_) => {
+                SerializedError::new("UnexpectedPublish", self).serialize(serializer)
+            }
+        }
+    }
+}
 
 impl From<InternalError> for MqttError {
     fn from(err: InternalError) -> Self {
@@ -661,3 +1314,222 @@ impl From<MaximumPacketSizeExceeded> for MqttError {
         Self::MaximumPacketSizeExceeded(err)
     }
 }
+
+impl From<SessionExpired> for MqttError {
+    fn from(err: SessionExpired) -> Self {
+        Self::SessionExpired(err)
+    }
+}
+
+impl From<PacketIdentifierExhausted> for MqttError {
+    fn from(err: PacketIdentifierExhausted) -> Self {
+        Self::PacketIdentifierExhausted(err)
+    }
+}
+
+impl From<InactivityTimeout> for MqttError {
+    fn from(err: InactivityTimeout) -> Self {
+        Self::InactivityTimeout(err)
+    }
+}
+
+impl From<ReadTimeout> for MqttError {
+    fn from(err: ReadTimeout) -> Self {
+        Self::ReadTimeout(err)
+    }
+}
+
+impl From<QueueFull> for MqttError {
+    fn from(err: QueueFull) -> Self {
+        Self::QueueFull(err)
+    }
+}
+
+impl From<OperationTimeout> for MqttError {
+    fn from(err: OperationTimeout) -> Self {
+        Self::OperationTimeout(err)
+    }
+}
+
+impl From<UnexpectedPublish> for MqttError {
+    fn from(err: UnexpectedPublish) -> Self {
+        Self::UnexpectedPublish(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn socket_closed_kind() {
+        let err = SocketClosed::from(io::Error::from(io::ErrorKind::ConnectionReset));
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+    }
+
+    #[test]
+    fn is_transient() {
+        const INPUT: [(io::ErrorKind, bool); 6] = [
+            (io::ErrorKind::TimedOut, true),
+            (io::ErrorKind::ConnectionReset, true),
+            (io::ErrorKind::BrokenPipe, true),
+            (io::ErrorKind::UnexpectedEof, true),
+            (io::ErrorKind::InvalidData, false),
+            (io::ErrorKind::PermissionDenied, false),
+        ];
+
+        for (kind, expected) in INPUT {
+            assert_eq!(
+                MqttError::from(io::Error::from(kind)).is_transient(),
+                expected
+            );
+        }
+
+        assert!(!MqttError::from(QuotaExceeded).is_transient());
+    }
+
+    #[test]
+    fn server_moved() {
+        use crate::core::utils::TryDecode;
+        use bytes::Bytes;
+
+        const PACKET: [u8; 18] = [
+            0x20, // Fixed header
+            16,   // Remaining length
+            0,    // Connect Acknowledge Flags
+            0x9d, // Reason (Server moved)
+            13,   // Property length
+            0x1c, // Server reference
+            0, 10, b'o', b't', b'h', b'e', b'r', b':', b'1', b'8', b'8', b'3',
+        ];
+
+        let packet = ConnackRx::try_decode(Bytes::from_static(&PACKET)).unwrap();
+
+        match MqttError::from(ConnectError::from(packet)) {
+            MqttError::ServerMoved(err) => {
+                assert!(err.is_permanent());
+                assert_eq!(err.server_reference(), Some("other:1883"));
+            }
+            _ => panic!("unexpected error variant"),
+        }
+    }
+
+    #[test]
+    fn ack_error_display_reason_string() {
+        use crate::{codec::PubackRx, core::utils::TryDecode};
+        use bytes::Bytes;
+
+        const PACKET: [u8; 15] = [
+            0x40, // Fixed header
+            13,   // Remaining length
+            0, 1,    // Packet identifier
+            0x87, // Reason (Not authorized)
+            9,    // Property length
+            0x1f, // Reason string
+            0, 6, b'd', b'e', b'n', b'i', b'e', b'd',
+        ];
+
+        let packet = PubackRx::try_decode(Bytes::from_static(&PACKET)).unwrap();
+        assert_eq!(
+            PubackError::from(packet).to_string(),
+            "{ \"type\": \"PubackError\", \"message\": \"PubackError error: 135 [NotAuthorized]: denied\" }"
+        );
+    }
+
+    #[test]
+    fn disconnected_reason_string() {
+        use crate::{codec::DisconnectRx, core::utils::TryDecode};
+        use bytes::Bytes;
+
+        const PACKET: [u8; 11] = [
+            0xe0, // Fixed header
+            9,    // Remaining length
+            0x8b, // Reason (Server shutting down)
+            7,    // Property length
+            0x1f, // Reason string
+            0, 4, b'b', b'y', b'e', b'!',
+        ];
+
+        let err = Disconnected {
+            packet: DisconnectRx::try_decode(Bytes::from_static(&PACKET)).unwrap(),
+        };
+        assert!(err.has_reason_string());
+        assert_eq!(err.reason_string_bytes(), Some(&b"bye!"[..]));
+        assert_eq!(err.reason_string(), Some("bye!"));
+
+        let err = Disconnected {
+            packet: DisconnectRx::try_decode(Bytes::from_static(&[0xe0, 2, 0x8b, 0])).unwrap(),
+        };
+        assert!(!err.has_reason_string());
+        assert_eq!(err.reason_string_bytes(), None);
+        assert_eq!(err.reason_string(), None);
+    }
+
+    #[test]
+    fn disconnected_redirect_target() {
+        use crate::{codec::DisconnectRx, core::utils::TryDecode};
+        use bytes::Bytes;
+
+        fn disconnected(reason: u8) -> Disconnected {
+            let packet = [
+                0xe0,   // Fixed header
+                11,     // Remaining length
+                reason, // Reason
+                9,      // Property length
+                0x1c,   // Server reference
+                0, 6, b'o', b't', b'h', b'e', b'r', b'!',
+            ];
+
+            Disconnected {
+                packet: DisconnectRx::try_decode(Bytes::copy_from_slice(&packet)).unwrap(),
+            }
+        }
+
+        assert_eq!(disconnected(0x9c).redirect_target(), Some("other!"));
+        assert_eq!(disconnected(0x9d).redirect_target(), Some("other!"));
+
+        let err = disconnected(0x8b);
+        assert_eq!(err.server_reference(), Some("other!"));
+        assert_eq!(err.redirect_target(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize() {
+        use crate::{codec::DisconnectRx, core::utils::TryDecode, QoS};
+        use bytes::Bytes;
+
+        const PACKET: [u8; 11] = [
+            0xe0, // Fixed header
+            9,    // Remaining length
+            0x8b, // Reason (Server shutting down)
+            7,    // Property length
+            0x1f, // Reason string
+            0, 4, b'b', b'y', b'e', b'!',
+        ];
+
+        let err = MqttError::from(Disconnected {
+            packet: DisconnectRx::try_decode(Bytes::from_static(&PACKET)).unwrap(),
+        });
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["type"], "Disconnected");
+        assert_eq!(json["message"], err.to_string());
+        assert_eq!(json["reason_code"], 0x8b);
+        assert_eq!(json["reason"], "server_shutting_down");
+        assert_eq!(json["reason_string"], "bye!");
+
+        let json = serde_json::to_value(MqttError::from(QuotaExceeded)).unwrap();
+        assert_eq!(json["type"], "QuotaExceeded");
+        assert!(json["reason_code"].is_null());
+        assert!(json["reason"].is_null());
+
+        assert_eq!(
+            serde_json::to_string(&DisconnectReason::NotAuthorized).unwrap(),
+            "\"not_authorized\""
+        );
+        assert_eq!(
+            serde_json::from_str::<QoS>("\"at_least_once\"").unwrap(),
+            QoS::AtLeastOnce
+        );
+    }
+}
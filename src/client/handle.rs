@@ -1,10 +1,17 @@
+#[cfg(feature = "pool")]
+use crate::client::pool::BufferPool;
 use crate::{
     client::{
+        context::{ContextStats, SharedConnectState, SharedQuotaWakers},
         error::MqttError,
-        error::{PubackError, PubcompError, PubrecError},
+        error::{PubackError, PubcompError, PubrecError, QuotaExceeded},
+        interceptor::SharedInterceptors,
         message::*,
-        opts::{DisconnectOpts, PublishOpts, SubscribeOpts, UnsubscribeOpts},
-        rsp::{SubscribeRsp, UnsubscribeRsp},
+        opts::{DisconnectOpts, PublishOpts, SubscribeOpts, SubscriptionOpts, UnsubscribeOpts},
+        owned_opts::OwnedPublishOpts,
+        rsp::{PublishData, PublishResult, SubscribeRsp, UnsubscribeRsp},
+        stream::SubscriptionStats,
+        timed::TimedContextHandle,
         utils::*,
     },
     codec::*,
@@ -12,29 +19,114 @@ use crate::{
         base_types::{NonZero, QoS},
         utils::{Encode, SizedPacket},
     },
-    PublishData, SubscriptionOpts,
+    io::SharedFramingCounters,
 };
 use bytes::BytesMut;
-use core::sync::atomic::{AtomicU16, AtomicU32, Ordering};
+use core::future::Future;
+use core::sync::atomic::{AtomicU16, AtomicU32, AtomicU8, AtomicUsize, Ordering};
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+};
 use futures::{
     channel::{mpsc, oneshot},
-    future, StreamExt,
+    future::{self, BoxFuture},
+    stream::FuturesUnordered,
+    FutureExt, Sink, StreamExt,
+};
+use futures_timer::Delay;
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
 };
-use std::sync::Arc;
 
 /// Cloneable handle to the client [Context](crate::Context). The [ContextHandle] object is used to perform MQTT operations.
+/// The handle is [Send] + [Sync], so it may be shared between tasks running on a multi-threaded runtime.
+///
+/// When the last handle is dropped while the context is connected, the DISCONNECT packet with the
+/// [Success](crate::reason::DisconnectReason::Success) reason is sent before the context exits.
+///
+/// The handle implements [Sink] for [PublishOpts], so a stream of messages may be published
+/// with [SinkExt::send_all](futures::SinkExt::send_all). Flushing the sink awaits the acknowledgement
+/// of all the messages sent through it.
 ///
-#[derive(Clone)]
 pub struct ContextHandle {
-    pub(crate) sender: mpsc::UnboundedSender<ContextMessage>,
+    pub(crate) sender: MessageSender,
+    pub(crate) priority_sender: mpsc::UnboundedSender<ContextMessage>,
+    pub(crate) control_sender: mpsc::UnboundedSender<ContextMessage>,
     pub(crate) packet_id: Arc<AtomicU16>,
     pub(crate) sub_id: Arc<AtomicU32>,
+    pub(crate) send_quota: Arc<AtomicU16>,
+    pub(crate) quota_wakers: SharedQuotaWakers,
+    pub(crate) remote_maximum_qos: Arc<AtomicU8>,
+    pub(crate) framing_counters: SharedFramingCounters,
+    pub(crate) connect_state: SharedConnectState,
+    pub(crate) pending: Mutex<FuturesUnordered<BoxFuture<'static, Result<(), MqttError>>>>,
+    pub(crate) handles: Arc<AtomicUsize>,
+    pub(crate) interceptors: SharedInterceptors,
+    #[cfg(feature = "pool")]
+    pub(crate) pool: BufferPool,
+}
+
+impl Clone for ContextHandle {
+    fn clone(&self) -> Self {
+        // Publish flows started through the sink are not shared between the handles.
+        Self {
+            sender: self.sender.clone(),
+            priority_sender: self.priority_sender.clone(),
+            control_sender: self.control_sender.clone(),
+            packet_id: self.packet_id.clone(),
+            sub_id: self.sub_id.clone(),
+            send_quota: self.send_quota.clone(),
+            quota_wakers: self.quota_wakers.clone(),
+            remote_maximum_qos: self.remote_maximum_qos.clone(),
+            framing_counters: self.framing_counters.clone(),
+            connect_state: self.connect_state.clone(),
+            pending: Mutex::default(),
+            handles: {
+                self.handles.fetch_add(1, Ordering::Relaxed);
+                self.handles.clone()
+            },
+            interceptors: self.interceptors.clone(),
+            #[cfg(feature = "pool")]
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+impl Drop for ContextHandle {
+    fn drop(&mut self) {
+        if self.handles.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return;
+        }
+
+        // Last handle dropped, disconnect gracefully unless already disconnected.
+        let packet = DisconnectOpts::new().build().unwrap();
+
+        let mut buf = BytesMut::with_capacity(packet.packet_len());
+        packet.encode(&mut buf);
+
+        let _ = self.sender.send(ContextMessage::Detached(buf));
+    }
 }
 
 impl ContextHandle {
+    fn next_packet_identifier(&self) -> u16 {
+        // Packet identifier 0 is not allowed, skip it when the counter wraps around.
+        loop {
+            let val = self.packet_id.fetch_add(1, Ordering::Relaxed);
+            if val != 0 {
+                return val;
+            }
+        }
+    }
+
     /// Performs graceful disconnection with the broker by sending the
     /// [Disconnect](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901205) packet.
     ///
+    /// The DISCONNECT packet is sent ahead of the operations still queued in the context,
+    /// use [drain](ContextHandle::drain) beforehand to complete them.
+    ///
     pub async fn disconnect<'a>(&mut self, opts: DisconnectOpts<'a>) -> Result<(), MqttError> {
         let packet = opts.build()?;
 
@@ -47,16 +139,79 @@ impl ContextHandle {
             response_channel: sender,
         });
 
-        self.sender.unbounded_send(message)?;
+        self.priority_sender.unbounded_send(message)?;
+        receiver.await?
+    }
+
+    /// Waits until all the packets sent so far are acknowledged by the broker. Meant to be
+    /// called before [disconnect](ContextHandle::disconnect) to make sure no QoS>0 messages are lost.
+    /// QoS 2 messages are considered delivered once PUBCOMP arrives.
+    ///
+    /// # Note
+    /// For QoS 2 messages, PUBREL is sent by the publishing task after PUBREC arrives,
+    /// so the publishing futures must be polled for this method to return.
+    ///
+    pub async fn drain(&mut self) -> Result<(), MqttError> {
+        let (sender, receiver) = oneshot::channel();
+        let message = ContextMessage::Drain(Drain {
+            response_channel: sender,
+        });
+
+        self.sender.try_send(message)?;
         receiver.await?
     }
 
+    /// Waits until [connect](crate::Context::connect) completes, resolving with its outcome. Useful when
+    /// the context is connected in a different task than the one using the handle. Resolves immediately
+    /// when the connection attempt has already completed, until the context is [reset](crate::Context::reset).
+    ///
+    /// # Errors
+    /// The error returned by [connect](crate::Context::connect), or [authorize](crate::Context::authorize)
+    /// in the extended authentication mode.
+    ///
+    pub fn wait_until_connected(&self) -> impl Future<Output = Result<(), MqttError>> {
+        let receiver = self
+            .connect_state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .wait();
+
+        async move { receiver.await? }
+    }
+
+    /// Accesses the remaining send quota, i.e. the number of QoS>0 PUBLISH packets that
+    /// may still be sent before the broker's [receive maximum](crate::ConnectRsp::receive_maximum)
+    /// is exhausted. Publishing with the quota equal to 0 results in
+    /// [QuotaExceeded](crate::error::QuotaExceeded) error.
+    ///
+    pub fn remaining_send_quota(&self) -> u16 {
+        self.send_quota.load(Ordering::Relaxed)
+    }
+
+    /// Accesses the snapshot of the [Context](crate::Context) statistics.
+    ///
+    pub fn stats(&self) -> ContextStats {
+        ContextStats {
+            framing_errors: self.framing_counters.framing_errors(),
+            bytes_discarded: self.framing_counters.bytes_discarded(),
+            unexpected_publishes: self.framing_counters.unexpected_publishes(),
+        }
+    }
+
     /// Sends ping to the broker by sending
     /// [Ping](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901195) packet.
     /// This method MUST be called periodically if [session_expiry_interval](crate::ConnectOpts::session_expiry_interval) was
     /// set during connection request in order to maintain the session.
     ///
     pub async fn ping(&mut self) -> Result<(), MqttError> {
+        self.ping_and_wait().await.map(|_| ())
+    }
+
+    /// Sends ping to the broker, same as [ping](ContextHandle::ping), and measures the round-trip
+    /// time between writing the PINGREQ packet to the stream and receiving the PINGRESP packet.
+    /// Time spent in the context queue does not count.
+    ///
+    pub async fn ping_and_wait(&mut self) -> Result<Duration, MqttError> {
         let (sender, receiver) = oneshot::channel();
 
         let builder = PingreqTxBuilder::default();
@@ -65,37 +220,184 @@ impl ContextHandle {
         let mut buf = BytesMut::with_capacity(packet.packet_len());
         packet.encode(&mut buf);
 
-        let message = ContextMessage::AwaitAck(AwaitAck {
-            action_id: tx_action_id(&TxPacket::Pingreq(packet)),
+        let message = ContextMessage::Ping(Ping {
             packet: buf,
             response_channel: sender,
         });
 
-        self.sender.unbounded_send(message)?;
-
-        receiver.await?.map(|rx_packet| match rx_packet {
-            RxPacket::Pingresp(_) => (),
-            _ => unreachable!("Unexpected packet type."),
-        })
+        self.priority_sender.unbounded_send(message)?; // Not delayed by the queued operations
+        receiver.await?
     }
 
     /// Publish data with the parameters set in [PublishOpts]. Acknowledgement of QoS>0
     /// messages is handled automatically.
     ///
+    /// On success returns [PublishResult] with the packet identifier and the reason values
+    /// of the acknowledgements, allowing to correlate the concurrently published messages.
+    ///
+    /// When the requested QoS exceedes the [maximum QoS](crate::ConnectRsp::maximum_qos)
+    /// supported by the broker, the message is published with the maximum supported QoS,
+    /// unless disabled with [allow_qos_downgrade](PublishOpts::allow_qos_downgrade).
+    ///
     /// # Errors
     /// - [MqttError::PubackError](crate::error::MqttError::PubackError) returned when
-    /// [QoS==1](QoS::AtLeastOnce) is performed and the PUBACK reason vaule is greater or equal 0x80.
+    ///   [QoS==1](QoS::AtLeastOnce) is performed and the PUBACK reason vaule is greater or equal 0x80.
     /// - [MqttError::PubrecError](crate::error::MqttError::PubrecError) returned when
-    /// [QoS==2](QoS::ExactlyOnce) is performed and the PUBREC reason value is greater or equal 0x80.
+    ///   [QoS==2](QoS::ExactlyOnce) is performed and the PUBREC reason value is greater or equal 0x80.
     /// - [MqttError::PubcompError](crate::error::MqttError::PubcompError) returned when
-    /// [QoS==2](QoS::ExactlyOnce) is performed and the PUBCOMP reason value is greater or equal 0x80.
+    ///   [QoS==2](QoS::ExactlyOnce) is performed and the PUBCOMP reason value is greater or equal 0x80.
+    /// - [MqttError::PacketIdentifierExhausted](crate::error::MqttError::PacketIdentifierExhausted)
+    ///   returned when the packet identifier is still used by an unacknowledged packet.
+    /// - [MqttError::QuotaExceeded](crate::error::MqttError::QuotaExceeded) returned when QoS>0 is
+    ///   performed and the [send quota](ContextHandle::remaining_send_quota) is exhausted. The quota is
+    ///   checked before the message is enqueued, and once again by the context when sending it.
+    ///
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub async fn publish<'a>(&mut self, opts: PublishOpts<'a>) -> Result<PublishResult, MqttError> {
+        self.start_publish(opts)?.await
+    }
+
+    /// Publishes the message without waiting for the publish flow to complete. The `callback` is invoked
+    /// by the [Context](crate::Context) with the result of the flow, once the final acknowledgement
+    /// is received (QoS>0) or the packet is sent (QoS 0). The errors are the same as the ones of
+    /// [publish](ContextHandle::publish).
+    ///
+    /// # Note
+    /// The callback runs on the context task and must not block. It is dropped without being invoked
+    /// when the context exits before the flow completes.
     ///
-    pub async fn publish<'a>(&mut self, opts: PublishOpts<'a>) -> Result<(), MqttError> {
-        match opts.qos.unwrap_or_default() {
+    pub fn publish_with_callback<'a, F>(
+        &mut self,
+        opts: PublishOpts<'a>,
+        callback: F,
+    ) -> Result<(), MqttError>
+    where
+        F: FnOnce(Result<(), MqttError>) + Send + 'static,
+    {
+        let intercepted = self.intercept(&opts)?;
+        let opts = intercepted.as_ref().map(PublishOpts::from).unwrap_or(opts);
+        let opts = self.negotiate_qos(opts);
+
+        let message = match opts.qos.unwrap_or_default() {
             QoS::AtMostOnce => {
                 let packet = opts.build()?;
 
-                let mut buf = BytesMut::with_capacity(packet.packet_len());
+                let mut buf = self.publish_buffer(packet.packet_len());
+                packet.encode(&mut buf);
+
+                PublishWithCallback {
+                    action_id: None,
+                    packet: buf,
+                    callback: Box::new(callback),
+                }
+            }
+            QoS::AtLeastOnce | QoS::ExactlyOnce => {
+                self.check_send_quota()?;
+
+                let packet = opts
+                    .packet_identifier(self.next_packet_identifier())
+                    .build()?;
+
+                let mut buf = self.publish_buffer(packet.packet_len());
+                packet.encode(&mut buf);
+
+                PublishWithCallback {
+                    action_id: Some(tx_action_id(&TxPacket::Publish(packet))),
+                    packet: buf,
+                    callback: Box::new(callback),
+                }
+            }
+        };
+
+        self.sender
+            .try_send(ContextMessage::PublishWithCallback(message))?;
+        Ok(())
+    }
+
+    /// Publishes the retained message with QoS 0, shortcut for [publish](ContextHandle::publish)
+    /// with [retain](PublishOpts::retain) flag set.
+    ///
+    pub async fn publish_retained(&mut self, topic: &str, payload: &[u8]) -> Result<(), MqttError> {
+        self.publish(
+            PublishOpts::new()
+                .topic_name(topic)
+                .payload(payload)
+                .retain(true),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Clears the message retained by the broker for the `topic` by publishing
+    /// the retained message with empty payload.
+    ///
+    pub async fn clear_retained(&mut self, topic: &str) -> Result<(), MqttError> {
+        self.publish_retained(topic, &[]).await
+    }
+
+    /// Fails early when no QoS>0 message may be sent, without enqueuing it.
+    ///
+    fn check_send_quota(&self) -> Result<(), MqttError> {
+        if self.send_quota.load(Ordering::Relaxed) == 0 {
+            return Err(QuotaExceeded.into());
+        }
+
+        Ok(())
+    }
+
+    /// Applies the [publish interceptors](crate::Context::add_publish_interceptor), returning
+    /// the modified message in the owned form. [None] when no interceptors are registered.
+    ///
+    fn intercept(&self, opts: &PublishOpts<'_>) -> Result<Option<OwnedPublishOpts>, MqttError> {
+        let interceptors = self
+            .interceptors
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        if interceptors.is_empty() {
+            return Ok(None);
+        }
+
+        let mut owned = OwnedPublishOpts::from(opts);
+        for interceptor in interceptors.iter() {
+            interceptor.intercept(&mut owned)?;
+        }
+
+        Ok(Some(owned))
+    }
+
+    /// Downgrades the QoS of the message to the maximum supported by the broker, unless disabled
+    /// with [allow_qos_downgrade](PublishOpts::allow_qos_downgrade).
+    ///
+    fn negotiate_qos<'a>(&self, opts: PublishOpts<'a>) -> PublishOpts<'a> {
+        let qos = opts.qos.unwrap_or_default();
+        let remote_maximum_qos =
+            QoS::try_from(self.remote_maximum_qos.load(Ordering::Relaxed)).unwrap();
+
+        if opts.allow_qos_downgrade.unwrap_or(true) && qos > remote_maximum_qos {
+            opts.qos(remote_maximum_qos)
+        } else {
+            opts
+        }
+    }
+
+    /// Enqueues the PUBLISH packet and returns the future completing the publish flow.
+    /// The returned future borrows neither the handle nor the options.
+    ///
+    fn start_publish<'a>(
+        &self,
+        opts: PublishOpts<'a>,
+    ) -> Result<impl Future<Output = Result<PublishResult, MqttError>> + Send + 'static, MqttError>
+    {
+        let intercepted = self.intercept(&opts)?;
+        let opts = intercepted.as_ref().map(PublishOpts::from).unwrap_or(opts);
+        let opts = self.negotiate_qos(opts);
+        let qos = opts.qos.unwrap_or_default();
+        let receiver = match qos {
+            QoS::AtMostOnce => {
+                let packet = opts.build()?;
+
+                let mut buf = self.publish_buffer(packet.packet_len());
                 packet.encode(&mut buf);
 
                 let (sender, receiver) = oneshot::channel();
@@ -104,15 +406,17 @@ impl ContextHandle {
                     response_channel: sender,
                 });
 
-                self.sender.unbounded_send(message)?;
-                receiver.await?
+                self.sender.try_send(message)?;
+                future::Either::Left(receiver)
             }
-            QoS::AtLeastOnce => {
+            QoS::AtLeastOnce | QoS::ExactlyOnce => {
+                self.check_send_quota()?;
+
                 let packet = opts
-                    .packet_identifier(self.packet_id.fetch_add(1, Ordering::Relaxed))
+                    .packet_identifier(self.next_packet_identifier())
                     .build()?;
 
-                let mut buf = BytesMut::with_capacity(packet.packet_len());
+                let mut buf = self.publish_buffer(packet.packet_len());
                 packet.encode(&mut buf);
 
                 let (sender, receiver) = oneshot::channel();
@@ -123,9 +427,19 @@ impl ContextHandle {
                     response_channel: sender,
                 });
 
-                self.sender.unbounded_send(message)?;
+                self.sender.try_send(message)?;
+                future::Either::Right(receiver)
+            }
+        };
+
+        let context_sender = self.sender.clone();
 
-                receiver
+        Ok(async move {
+            match receiver {
+                future::Either::Left(receiver) => {
+                    receiver.await?.map(|_| PublishResult::AtMostOnce)
+                }
+                future::Either::Right(receiver) if qos == QoS::AtLeastOnce => receiver
                     .await?
                     .map(|rx_packet| match rx_packet {
                         RxPacket::Puback(puback) => puback,
@@ -135,75 +449,65 @@ impl ContextHandle {
                         if puback.reason as u8 >= 0x80 {
                             Err(PubackError::from(puback).into())
                         } else {
-                            Ok(())
+                            Ok(PublishResult::AtLeastOnce {
+                                packet_id: puback.packet_identifier.get(),
+                                reason: puback.reason,
+                            })
                         }
-                    })
-            }
-            QoS::ExactlyOnce => {
-                let packet = opts
-                    .packet_identifier(self.packet_id.fetch_add(1, Ordering::Relaxed))
-                    .build()?;
+                    }),
+                future::Either::Right(receiver) => {
+                    let pubrec = receiver
+                        .await?
+                        .map(|rx_packet| match rx_packet {
+                            RxPacket::Pubrec(pubrec) => pubrec,
+                            _ => unreachable!("Unexpected packet type."),
+                        })
+                        .and_then(|pubrec| {
+                            if pubrec.reason as u8 >= 0x80 {
+                                Err(PubrecError::from(pubrec).into())
+                            } else {
+                                Ok(pubrec)
+                            }
+                        })?;
 
-                let mut buf = BytesMut::with_capacity(packet.packet_len());
-                packet.encode(&mut buf);
+                    let (pubrel_sender, pubrel_receiver) = oneshot::channel();
 
-                let (pubrec_sender, pubrec_receiver) = oneshot::channel();
+                    let mut builder = PubrelTxBuilder::default();
+                    builder.packet_identifier(pubrec.packet_identifier);
 
-                let pub_msg = ContextMessage::AwaitAck(AwaitAck {
-                    action_id: tx_action_id(&TxPacket::Publish(packet)),
-                    packet: buf.split(),
-                    response_channel: pubrec_sender,
-                });
+                    let pubrel = builder.build().unwrap();
 
-                self.sender.unbounded_send(pub_msg)?;
+                    let mut buf = BytesMut::with_capacity(pubrel.packet_len());
+                    pubrel.encode(&mut buf);
 
-                let pubrec = pubrec_receiver
-                    .await?
-                    .map(|rx_packet| match rx_packet {
-                        RxPacket::Pubrec(pubrec) => pubrec,
-                        _ => unreachable!("Unexpected packet type."),
-                    })
-                    .and_then(|pubrec| {
-                        if pubrec.reason as u8 >= 0x80 {
-                            Err(PubrecError::from(pubrec).into())
-                        } else {
-                            Ok(pubrec)
-                        }
-                    })?;
+                    let pubrel_msg = ContextMessage::AwaitAck(AwaitAck {
+                        action_id: tx_action_id(&TxPacket::Pubrel(pubrel)),
+                        packet: buf,
+                        response_channel: pubrel_sender,
+                    });
 
-                let (pubrel_sender, pubrel_receiver) = oneshot::channel();
-
-                let mut builder = PubrelTxBuilder::default();
-                builder.packet_identifier(pubrec.packet_identifier);
-
-                let pubrel = builder.build().unwrap();
-
-                buf.reserve(pubrel.packet_len());
-                pubrel.encode(&mut buf);
-
-                let pubrel_msg = ContextMessage::AwaitAck(AwaitAck {
-                    action_id: tx_action_id(&TxPacket::Pubrel(pubrel)),
-                    packet: buf,
-                    response_channel: pubrel_sender,
-                });
+                    context_sender.send(pubrel_msg)?;
 
-                self.sender.unbounded_send(pubrel_msg)?;
-
-                pubrel_receiver
-                    .await?
-                    .map(|rx_packet| match rx_packet {
-                        RxPacket::Pubcomp(pubcomp) => pubcomp,
-                        _ => unreachable!("Unexpected packet type."),
-                    })
-                    .and_then(|pubcomp| {
-                        if pubcomp.reason as u8 >= 0x80 {
-                            Err(PubcompError::from(pubcomp).into())
-                        } else {
-                            Ok(())
-                        }
-                    })
+                    pubrel_receiver
+                        .await?
+                        .map(|rx_packet| match rx_packet {
+                            RxPacket::Pubcomp(pubcomp) => pubcomp,
+                            _ => unreachable!("Unexpected packet type."),
+                        })
+                        .and_then(|pubcomp| {
+                            if pubcomp.reason as u8 >= 0x80 {
+                                Err(PubcompError::from(pubcomp).into())
+                            } else {
+                                Ok(PublishResult::ExactlyOnce {
+                                    packet_id: pubcomp.packet_identifier.get(),
+                                    pubrec_reason: pubrec.reason,
+                                    pubcomp_reason: pubcomp.reason,
+                                })
+                            }
+                        })
+                }
             }
-        }
+        })
     }
 
     /// Performs subscription to the topics specified in [`opts`](SubscribeOpts). This corresponds to sending the
@@ -215,18 +519,55 @@ impl ContextHandle {
     /// This object can be transformed into the asynchronous stream of messages published to the subscribed
     /// topics by using the [stream](SubscribeRsp::stream) method.
     ///
+    /// Each call creates a separate subscription with its own identifier and stream. Subscribing
+    /// again to a topic filter used in an earlier call replaces the earlier subscription on the broker
+    /// side, so the new subscription identifier is sent along with further messages and the earlier
+    /// stream no longer receives messages matching that filter. Messages matching distinct, but
+    /// overlapping filters (e.g. `a/#` and `a/b`) are delivered to every matching stream.
+    ///
     /// # Errors
     /// Per-topic [reason codes](SubackReason) are retrieved with the [payload](SubscribeRsp::payload) method.
     ///
+    /// The SUBSCRIBE packet must carry at least one topic filter, so [opts](SubscribeOpts) without any
+    /// result in the [EmptyPayload](crate::error::CodecError::EmptyPayload) error.
+    ///
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub async fn subscribe<'a>(
         &mut self,
         opts: SubscribeOpts<'a>,
+    ) -> Result<SubscribeRsp, MqttError> {
+        self.subscribe_with(opts, None).await
+    }
+
+    /// Performs subscription, same as [subscribe](ContextHandle::subscribe), delivering to the
+    /// [stream](SubscribeRsp::stream) only the messages for which the `filter` returns `true`.
+    ///
+    /// # Note
+    /// The filter runs on the context task, so the rejected messages are not sent to the stream
+    /// nor counted in its [stats](crate::SubscriptionStats). It must not block.
+    ///
+    pub async fn subscribe_with_filter<'a, F>(
+        &mut self,
+        opts: SubscribeOpts<'a>,
+        filter: F,
+    ) -> Result<SubscribeRsp, MqttError>
+    where
+        F: Fn(&PublishData) -> bool + Send + 'static,
+    {
+        self.subscribe_with(opts, Some(Box::new(filter))).await
+    }
+
+    async fn subscribe_with<'a>(
+        &mut self,
+        opts: SubscribeOpts<'a>,
+        filter: Option<MessageFilter>,
     ) -> Result<SubscribeRsp, MqttError> {
         let (sender, receiver) = oneshot::channel();
         let (str_sender, str_receiver) = mpsc::unbounded();
+        let stats = Arc::new(Mutex::new(SubscriptionStats::default()));
 
         let packet = opts
-            .packet_identifier(self.packet_id.fetch_add(1, Ordering::Relaxed))
+            .packet_identifier(self.next_packet_identifier())
             .subscription_identifier(self.sub_id.fetch_add(1, Ordering::Relaxed))
             .build()?;
 
@@ -234,6 +575,16 @@ impl ContextHandle {
             .get()
             .value();
 
+        let subscriptions: Vec<_> = packet
+            .payload
+            .iter()
+            .map(|(topic, opts)| (String::from(topic.0), *opts))
+            .collect();
+        let topic_filters = subscriptions
+            .iter()
+            .map(|(topic, _)| topic.clone())
+            .collect();
+
         let mut buf = BytesMut::with_capacity(packet.packet_len());
         packet.encode(&mut buf);
 
@@ -243,25 +594,137 @@ impl ContextHandle {
             packet: buf,
             response_channel: sender,
             stream: str_sender,
+            stats: stats.clone(),
+            topic_filters: subscriptions,
+            filter,
         });
 
-        self.sender.unbounded_send(message)?;
+        self.sender.try_send(message)?;
 
         receiver.await?.map(|rx_packet| match rx_packet {
             RxPacket::Suback(suback) => SubscribeRsp {
                 packet: suback,
                 receiver: str_receiver,
+                stats,
+                topic_filters,
+                subscription_identifier: subscription_identifier as usize,
+                control_sender: self.control_sender.clone(),
             },
             _ => unreachable!("Unexpected packet type."),
         })
     }
 
+    /// Re-issues SUBSCRIBE packets for all subscriptions whose [streams](SubscribeRsp::stream) are still alive,
+    /// using the same topic filters, options and subscription identifiers. Meant to be called after
+    /// reconnecting without the session being present, so that the existing streams receive messages again.
+    ///
+    /// The stored [retain handling](RetainHandling) is replaced with `retain_handling` for every topic filter.
+    /// Passing [NoSendOnSubscribe](RetainHandling::NoSendOnSubscribe) prevents the broker from re-delivering
+    /// all of the retained messages, which the streams have most likely received already.
+    ///
+    /// # Note
+    /// Per-topic [reason codes](SubackReason) returned by the broker are not inspected.
+    ///
+    pub async fn resubscribe_all(
+        &mut self,
+        retain_handling: RetainHandling,
+    ) -> Result<(), MqttError> {
+        let (sender, receiver) = oneshot::channel();
+        let message = ContextMessage::Subscriptions(Subscriptions {
+            response_channel: sender,
+        });
+
+        self.sender.try_send(message)?;
+
+        let mut pending = Vec::new();
+        for (subscription_identifier, topic_filters) in receiver.await? {
+            let packet = topic_filters
+                .iter()
+                .fold(SubscribeOpts::new(), |opts, (topic, options)| {
+                    opts.subscription_options(
+                        topic,
+                        SubscriptionOptions {
+                            retain_handling,
+                            ..*options
+                        },
+                    )
+                })
+                .packet_identifier(self.next_packet_identifier())
+                .subscription_identifier(subscription_identifier as u32)
+                .build()?;
+
+            let mut buf = BytesMut::with_capacity(packet.packet_len());
+            packet.encode(&mut buf);
+
+            let (sender, receiver) = oneshot::channel();
+            let message = ContextMessage::AwaitAck(AwaitAck {
+                action_id: tx_action_id(&TxPacket::Subscribe(packet)),
+                packet: buf,
+                response_channel: sender,
+            });
+
+            self.sender.try_send(message)?;
+            pending.push(receiver);
+        }
+
+        for receiver in pending {
+            receiver.await??;
+        }
+
+        Ok(())
+    }
+
+    /// Performs subscription, same as [subscribe](ContextHandle::subscribe), to the topic `filters`
+    /// determined at runtime, e.g. loaded from a configuration file. Equivalent to chaining
+    /// [subscription](SubscribeOpts::subscription) calls for each of the filters.
+    ///
+    /// # Errors
+    /// The SUBSCRIBE packet must carry at least one topic filter, so an empty slice results in
+    /// the [EmptyPayload](crate::error::CodecError::EmptyPayload) error.
+    ///
+    pub async fn subscribe_dynamic(
+        &mut self,
+        filters: &[(&str, SubscriptionOpts)],
+    ) -> Result<SubscribeRsp, MqttError> {
+        let opts = filters
+            .iter()
+            .fold(SubscribeOpts::new(), |opts, &(topic, subscription_opts)| {
+                opts.subscription(topic, subscription_opts)
+            });
+
+        self.subscribe(opts).await
+    }
+
+    /// Performs multiple [subscription requests](ContextHandle::subscribe) concurrently, one for each
+    /// of the supplied [`groups`](SubscribeOpts). Each group is sent as a separate SUBSCRIBE packet and
+    /// receives its own [stream](SubscribeRsp::stream) of messages. Responses are returned in the order
+    /// of the supplied groups.
+    ///
+    /// # Errors
+    /// The first error encountered is returned. Subscriptions of the groups that succeeded are not
+    /// reverted in that case.
+    ///
+    pub async fn subscribe_many<'a>(
+        &mut self,
+        groups: Vec<SubscribeOpts<'a>>,
+    ) -> Result<Vec<SubscribeRsp>, MqttError> {
+        future::join_all(groups.into_iter().map(|opts| {
+            let mut handle = self.clone();
+            async move { handle.subscribe(opts).await }
+        }))
+        .await
+        .into_iter()
+        .collect()
+    }
+
     /// Unsubscribes from the topics specified in [`opts`](UnsubscribeOpts). This corresponds to sending the
     /// [Unsubscribe](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901179) packet.
     ///
     /// # Errors
-    /// Per-topic [reason codes](UnsubackReason) are retrieved with the [payload](UnsubscribeRsp::payload) method.
+    /// Per-topic [reason codes](UnsubackReason) are retrieved with the [payload](UnsubscribeRsp::payload) method,
+    /// or paired with the corresponding topic filters with the [results](UnsubscribeRsp::results) method.
     ///
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub async fn unsubscribe<'a>(
         &mut self,
         opts: UnsubscribeOpts<'a>,
@@ -269,26 +732,120 @@ impl ContextHandle {
         let (sender, receiver) = oneshot::channel();
 
         let packet = opts
-            .packet_identifier(self.packet_id.fetch_add(1, Ordering::Relaxed))
+            .packet_identifier(self.next_packet_identifier())
             .build()?;
 
+        let topic_filters: Vec<String> = packet
+            .payload
+            .iter()
+            .map(|topic| String::from(topic.0))
+            .collect();
+
         let mut buf = BytesMut::with_capacity(packet.packet_len());
         packet.encode(&mut buf);
 
-        let message = ContextMessage::AwaitAck(AwaitAck {
+        let message = ContextMessage::Unsubscribe(Unsubscribe {
             action_id: tx_action_id(&TxPacket::Unsubscribe(packet)),
             packet: buf,
             response_channel: sender,
+            topic_filters: topic_filters.clone(),
         });
 
-        self.sender.unbounded_send(message)?;
+        self.sender.try_send(message)?;
 
         receiver.await?.map(|rx_packet| match rx_packet {
-            RxPacket::Unsuback(unsuback) => UnsubscribeRsp { packet: unsuback },
+            RxPacket::Unsuback(unsuback) => UnsubscribeRsp {
+                packet: unsuback,
+                topic_filters,
+            },
             _ => unreachable!("Unexpected packet type."),
         })
     }
 
+    /// Shortcut method for receiving a single message. Subscribes to the `topic`, waits for the first
+    /// message published to it and unsubscribes afterwards.
+    ///
+    /// # Arguments
+    /// * `topic` - Topic filter to subscribe to.
+    /// * `timeout` - Maximum time to wait for the message. [None] is returned once it elapses.
+    ///
+    pub async fn wait_for_message(
+        &mut self,
+        topic: &str,
+        timeout: Duration,
+    ) -> Result<Option<PublishData>, MqttError> {
+        self.receive_once(topic, SubscriptionOpts::new(), timeout, None)
+            .await
+    }
+
+    /// Subscribes to the `topic`, waits up to `timeout` for the first message and unsubscribes afterwards.
+    /// Useful for the one-shot patterns, e.g. waiting for a configuration update.
+    ///
+    /// Subscription is made with [SendIfNoSubscription](RetainHandling::SendIfNoSubscription) retain handling,
+    /// so the message retained on the `topic` is returned immediately. [None] is returned on timeout.
+    ///
+    pub async fn subscribe_once(
+        &mut self,
+        topic: &str,
+        timeout: Duration,
+    ) -> Result<Option<PublishData>, MqttError> {
+        self.receive_once(
+            topic,
+            SubscriptionOpts::new().retain_handling(RetainHandling::SendIfNoSubscription),
+            timeout,
+            None,
+        )
+        .await
+    }
+
+    // Common implementation of wait_for_message and subscribe_once, also for the TimedContextHandle,
+    // which limits subscribing and unsubscribing with the `operation_timeout`.
+    pub(crate) async fn receive_once(
+        &mut self,
+        topic: &str,
+        opts: SubscriptionOpts,
+        timeout: Duration,
+        operation_timeout: Option<Duration>,
+    ) -> Result<Option<PublishData>, MqttError> {
+        let subscribe = self.subscribe(SubscribeOpts::new().subscription(topic, opts));
+        let mut stream = match operation_timeout {
+            Some(operation_timeout) => {
+                TimedContextHandle::timed(operation_timeout, subscribe).await
+            }
+            None => subscribe.await,
+        }?
+        .stream();
+
+        let msg = match future::select(stream.next(), Delay::new(timeout)).await {
+            future::Either::Left((msg, _)) => msg,
+            future::Either::Right(_) => None,
+        };
+
+        let unsubscribe = self.unsubscribe(UnsubscribeOpts::new().topic_filter(topic));
+        match operation_timeout {
+            Some(operation_timeout) => {
+                TimedContextHandle::timed(operation_timeout, unsubscribe).await
+            }
+            None => unsubscribe.await,
+        }?;
+        Ok(msg)
+    }
+
+    /// Sends the raw packet bytes to the broker, bypassing all validation, packet size checks
+    /// and quota tracking. Intended for testing the handling of malformed packets.
+    ///
+    #[cfg(feature = "unstable")]
+    pub async fn send_raw(&mut self, packet: bytes::Bytes) -> Result<(), MqttError> {
+        let (sender, receiver) = oneshot::channel();
+        let message = ContextMessage::Raw(FireAndForget {
+            packet: BytesMut::from(packet.as_ref()),
+            response_channel: sender,
+        });
+
+        self.sender.try_send(message)?;
+        receiver.await?
+    }
+
     /// Shortcut method for performing MQTT request/response.
     ///
     #[cfg(feature = "experimental")]
@@ -329,3 +886,675 @@ impl ContextHandle {
         Ok(rsp.unwrap())
     }
 }
+
+impl ContextHandle {
+    fn publish_buffer(&self, capacity: usize) -> BytesMut {
+        #[cfg(feature = "pool")]
+        return self.pool.take(capacity);
+
+        #[cfg(not(feature = "pool"))]
+        BytesMut::with_capacity(capacity)
+    }
+
+    // Quota may be used up by the other handles, so it is checked again after registering the waker.
+    fn poll_send_quota(&self, cx: &mut Context<'_>) -> Poll<Result<(), MqttError>> {
+        if self.send_quota.load(Ordering::Relaxed) > 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        {
+            let mut wakers = self
+                .quota_wakers
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+        }
+
+        if self.send_quota.load(Ordering::Relaxed) > 0 {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), MqttError>> {
+        let pending = self
+            .pending
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        loop {
+            match pending.poll_next_unpin(cx) {
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Err(err)),
+                Poll::Ready(Some(Ok(()))) => continue,
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<'a> Sink<PublishOpts<'a>> for ContextHandle {
+    type Error = MqttError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let Poll::Ready(Err(err)) = self.poll_pending(cx) {
+            return Poll::Ready(Err(err));
+        }
+
+        // Woken up once any of the pending publish flows completes or the quota is released.
+        self.poll_send_quota(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: PublishOpts<'a>) -> Result<(), Self::Error> {
+        let fut = self
+            .start_publish(item)?
+            .map(|result| result.map(|_| ()))
+            .boxed();
+        self.pending
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(fut);
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_pending(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        reason::UnsubackReason,
+        testing::{self, TestBroker},
+        DisconnectOpts,
+    };
+    use futures::{stream, AsyncReadExt, AsyncWriteExt, SinkExt};
+
+    #[test]
+    fn handle_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<ContextHandle>();
+        assert_send_sync::<Arc<ContextHandle>>();
+    }
+
+    #[tokio::test]
+    async fn drop_last_handle_disconnects() {
+        let (handle, mut server, context) = testing::spawn_pair().await;
+
+        let handle_clone = handle.clone();
+        drop(handle);
+        drop(handle_clone);
+
+        assert!(matches!(
+            context.await.unwrap(),
+            Err(MqttError::HandleClosed(_))
+        ));
+
+        let mut rest = Vec::new();
+        server.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest.first().map(|byte| byte >> 4), Some(14)); // DISCONNECT
+    }
+
+    #[tokio::test]
+    async fn ping_and_wait() {
+        let (mut handle, mut server, _) = testing::spawn_pair().await;
+
+        let test = async move {
+            let rtt = handle.ping_and_wait().await.unwrap();
+            assert!(rtt >= Duration::from_millis(20));
+        };
+
+        let peer = async {
+            let mut pingreq = [0u8; 2];
+            server.read_exact(&mut pingreq).await.unwrap();
+            assert_eq!(pingreq, [0xc0, 0]);
+
+            Delay::new(Duration::from_millis(20)).await;
+            server.write_all(&[0xd0, 0]).await.unwrap(); // PINGRESP
+        };
+
+        future::join(test, peer).await;
+    }
+
+    #[tokio::test]
+    async fn publish_retained() {
+        let (mut handle, mut server, _) = testing::spawn_pair().await;
+
+        let test = async move {
+            handle.publish_retained("a", b"ok").await.unwrap();
+            handle.clear_retained("a").await.unwrap();
+        };
+
+        let peer = async {
+            let mut publish = [0u8; 8];
+            server.read_exact(&mut publish).await.unwrap();
+            assert_eq!(publish, [0x31, 6, 0, 1, b'a', 0, b'o', b'k']);
+
+            let mut clear = [0u8; 6];
+            server.read_exact(&mut clear).await.unwrap();
+            assert_eq!(clear, [0x31, 4, 0, 1, b'a', 0]);
+        };
+
+        future::join(test, peer).await;
+    }
+
+    #[test]
+    fn quota_exceeded_before_enqueuing() {
+        let (_context, mut handle) =
+            crate::Context::<futures::io::Cursor<Vec<u8>>, futures::io::Cursor<Vec<u8>>>::new();
+        handle.send_quota.store(0, Ordering::Relaxed);
+
+        let opts = PublishOpts::new().topic_name("a").qos(QoS::AtLeastOnce);
+        assert!(matches!(
+            handle.publish(opts.clone()).now_or_never(),
+            Some(Err(MqttError::QuotaExceeded(_)))
+        ));
+        assert!(matches!(
+            handle.publish_with_callback(opts, |_| unreachable!()),
+            Err(MqttError::QuotaExceeded(_))
+        ));
+
+        // QoS 0 messages are not limited by the quota.
+        handle
+            .publish_with_callback(PublishOpts::new().topic_name("a"), |_| {})
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn publish_with_callback() {
+        let (mut handle, mut server, _) = testing::spawn_pair().await;
+
+        let test = async move {
+            let opts = PublishOpts::new().topic_name("a").payload(b"ok");
+
+            let (sender, receiver) = oneshot::channel();
+            handle
+                .publish_with_callback(opts.clone().qos(QoS::ExactlyOnce), move |result| {
+                    let _ = sender.send(result);
+                })
+                .unwrap();
+            receiver.await.unwrap().unwrap();
+
+            let (sender, receiver) = oneshot::channel();
+            handle
+                .publish_with_callback(opts.qos(QoS::AtLeastOnce), move |result| {
+                    let _ = sender.send(result);
+                })
+                .unwrap();
+            assert!(matches!(
+                receiver.await.unwrap(),
+                Err(MqttError::PubackError(_))
+            ));
+        };
+
+        let peer = async {
+            let mut publish = [0u8; 10];
+            server.read_exact(&mut publish).await.unwrap();
+            assert_eq!(publish[0], 0x34); // PUBLISH, QoS 2
+            let packet_id = [publish[5], publish[6]];
+
+            server
+                .write_all(&[0x50, 2, packet_id[0], packet_id[1]]) // PUBREC
+                .await
+                .unwrap();
+
+            let mut pubrel = [0u8; 4];
+            server.read_exact(&mut pubrel).await.unwrap();
+            assert_eq!(pubrel, [0x62, 2, packet_id[0], packet_id[1]]); // Sent by the context
+
+            server
+                .write_all(&[0x70, 2, packet_id[0], packet_id[1]]) // PUBCOMP
+                .await
+                .unwrap();
+
+            server.read_exact(&mut publish).await.unwrap();
+            assert_eq!(publish[0], 0x32); // PUBLISH, QoS 1
+
+            server
+                .write_all(&[0x40, 3, publish[5], publish[6], 0x80]) // PUBACK, unspecified error
+                .await
+                .unwrap();
+        };
+
+        future::join(test, peer).await;
+    }
+
+    #[tokio::test]
+    async fn publish_result() {
+        let (mut handle, mut server, _) = testing::spawn_pair().await;
+        let (id_sender, mut id_receiver) = mpsc::unbounded();
+
+        let test = async move {
+            let opts = PublishOpts::new().topic_name("a").payload(b"ok");
+
+            let result = handle.publish(opts.clone()).await.unwrap();
+            assert_eq!(result, PublishResult::AtMostOnce);
+            assert_eq!(result.packet_id(), None);
+
+            let result = handle
+                .publish(opts.clone().qos(QoS::AtLeastOnce))
+                .await
+                .unwrap();
+            assert_eq!(
+                result,
+                PublishResult::AtLeastOnce {
+                    packet_id: id_receiver.next().await.unwrap(),
+                    reason: PubackReason::NoMatchingSubscribers,
+                }
+            );
+
+            let result = handle.publish(opts.qos(QoS::ExactlyOnce)).await.unwrap();
+            assert_eq!(
+                result,
+                PublishResult::ExactlyOnce {
+                    packet_id: id_receiver.next().await.unwrap(),
+                    pubrec_reason: PubrecReason::Success,
+                    pubcomp_reason: PubcompReason::Success,
+                }
+            );
+            assert!(result.packet_id().is_some());
+        };
+
+        let peer = async {
+            let mut publish = [0u8; 8];
+            server.read_exact(&mut publish).await.unwrap();
+            assert_eq!(publish[0], 0x30); // PUBLISH, QoS 0
+
+            let mut publish = [0u8; 10];
+            server.read_exact(&mut publish).await.unwrap();
+            assert_eq!(publish[0], 0x32); // PUBLISH, QoS 1
+            id_sender
+                .unbounded_send(u16::from_be_bytes([publish[5], publish[6]]))
+                .unwrap();
+            server
+                .write_all(&[0x40, 3, publish[5], publish[6], 0x10]) // PUBACK, no matching subscribers
+                .await
+                .unwrap();
+
+            server.read_exact(&mut publish).await.unwrap();
+            assert_eq!(publish[0], 0x34); // PUBLISH, QoS 2
+            id_sender
+                .unbounded_send(u16::from_be_bytes([publish[5], publish[6]]))
+                .unwrap();
+            server
+                .write_all(&[0x50, 2, publish[5], publish[6]]) // PUBREC
+                .await
+                .unwrap();
+
+            let mut pubrel = [0u8; 4];
+            server.read_exact(&mut pubrel).await.unwrap();
+            server
+                .write_all(&[0x70, 2, publish[5], publish[6]]) // PUBCOMP
+                .await
+                .unwrap();
+        };
+
+        future::join(test, peer).await;
+    }
+
+    #[tokio::test]
+    async fn publish_sink() {
+        let (mut handle, server, _) = testing::spawn_pair().await;
+        let broker = tokio::spawn(async move { TestBroker::new().serve(server.split()).await });
+
+        let mut stream = handle
+            .subscribe(SubscribeOpts::new().subscription("a/#", SubscriptionOpts::new()))
+            .await
+            .unwrap()
+            .stream();
+
+        const TOPICS: [&str; 3] = ["a/0", "a/1", "a/2"];
+
+        let mut messages = stream::iter(TOPICS).map(|topic| {
+            Ok(PublishOpts::new()
+                .topic_name(topic)
+                .qos(QoS::AtLeastOnce)
+                .payload(b"test"))
+        });
+
+        handle.send_all(&mut messages).await.unwrap();
+
+        for topic in TOPICS {
+            assert_eq!(stream.next().await.unwrap().topic_name(), topic);
+        }
+
+        handle.disconnect(DisconnectOpts::new()).await.unwrap();
+        assert!(broker.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn publish_sink_without_quota() {
+        let (mut handle, mut server, _) = testing::spawn_pair().await;
+        handle.send_quota.store(1, Ordering::Relaxed);
+
+        let opts = PublishOpts::new()
+            .topic_name("a")
+            .qos(QoS::AtLeastOnce)
+            .payload(b"ok");
+
+        // Quota used up by another handle.
+        let other = handle.clone();
+        let first = other.start_publish(opts.clone()).unwrap();
+
+        let mut publish = [0u8; 10];
+        server.read_exact(&mut publish).await.unwrap();
+        assert_eq!(handle.remaining_send_quota(), 0);
+
+        let send = handle.send(opts);
+
+        let peer = async {
+            Delay::new(Duration::from_millis(10)).await;
+            server
+                .write_all(&[0x40, 2, publish[5], publish[6]]) // PUBACK
+                .await
+                .unwrap();
+
+            server.read_exact(&mut publish).await.unwrap(); // Sent once the quota is released
+            server
+                .write_all(&[0x40, 2, publish[5], publish[6]]) // PUBACK
+                .await
+                .unwrap();
+        };
+
+        futures::pin_mut!(send, peer);
+        match future::select(send, peer).await {
+            future::Either::Left((result, peer)) => {
+                result.unwrap(); // Fails with QuotaExceeded if not ready
+                peer.await;
+            }
+            future::Either::Right((_, send)) => send.await.unwrap(),
+        }
+        first.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn drain() {
+        let (mut handle, server, _) = testing::spawn_pair().await;
+        let broker = tokio::spawn(async move { TestBroker::new().serve(server.split()).await });
+
+        let quota = handle.remaining_send_quota();
+
+        for _ in 0..3 {
+            handle
+                .feed(
+                    PublishOpts::new()
+                        .topic_name("a")
+                        .qos(QoS::AtLeastOnce)
+                        .payload(b"test"),
+                )
+                .await
+                .unwrap();
+        }
+
+        handle.drain().await.unwrap();
+        assert_eq!(handle.remaining_send_quota(), quota);
+
+        handle.disconnect(DisconnectOpts::new()).await.unwrap();
+        assert!(broker.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn drain_exactly_once() {
+        let (mut handle, mut server, _) = testing::spawn_pair().await;
+
+        let publish = handle
+            .start_publish(
+                PublishOpts::new()
+                    .topic_name("a")
+                    .qos(QoS::ExactlyOnce)
+                    .payload(b"ok"),
+            )
+            .unwrap();
+
+        let drained = core::cell::Cell::new(false);
+
+        let test = async {
+            handle.drain().await.unwrap();
+            drained.set(true);
+        };
+
+        let peer = async {
+            let mut publish_packet = [0u8; 10];
+            server.read_exact(&mut publish_packet).await.unwrap();
+            let packet_id = [publish_packet[5], publish_packet[6]];
+            server
+                .write_all(&[0x50, 2, packet_id[0], packet_id[1]]) // PUBREC
+                .await
+                .unwrap();
+
+            Delay::new(Duration::from_millis(10)).await;
+            assert!(!drained.get()); // PUBREL not sent yet
+
+            let pubcomp = async {
+                let mut pubrel = [0u8; 4];
+                server.read_exact(&mut pubrel).await.unwrap();
+                assert_eq!(pubrel, [0x62, 2, packet_id[0], packet_id[1]]);
+
+                Delay::new(Duration::from_millis(10)).await;
+                assert!(!drained.get()); // PUBCOMP held back
+
+                server
+                    .write_all(&[0x70, 2, packet_id[0], packet_id[1]]) // PUBCOMP
+                    .await
+                    .unwrap();
+            };
+
+            let (result, _) = future::join(publish, pubcomp).await;
+            result.unwrap();
+        };
+
+        future::join(test, peer).await;
+        assert!(drained.get());
+    }
+
+    #[tokio::test]
+    async fn resubscribe_all() {
+        let (mut handle, server, _) = testing::spawn_pair().await;
+        let broker = tokio::spawn(async move { TestBroker::new().serve(server.split()).await });
+
+        let mut stream = handle
+            .subscribe(SubscribeOpts::new().subscription("a/+", SubscriptionOpts::new()))
+            .await
+            .unwrap()
+            .stream();
+
+        // Dropped stream, must not be subscribed again.
+        let _ = handle
+            .subscribe(SubscribeOpts::new().subscription("b", SubscriptionOpts::new()))
+            .await
+            .unwrap();
+
+        // Remove the subscriptions on the broker side, keeping the stream alive.
+        handle
+            .unsubscribe(UnsubscribeOpts::new().topic_filter("a/+").topic_filter("b"))
+            .await
+            .unwrap();
+
+        handle
+            .resubscribe_all(RetainHandling::NoSendOnSubscribe)
+            .await
+            .unwrap();
+
+        handle
+            .publish(PublishOpts::new().topic_name("a/b").payload(b"test"))
+            .await
+            .unwrap();
+
+        let msg = stream.next().await.unwrap();
+        assert_eq!(msg.payload(), b"test");
+
+        let unsuback = handle
+            .unsubscribe(UnsubscribeOpts::new().topic_filter("a/+").topic_filter("b"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            unsuback.payload(),
+            [
+                UnsubackReason::Success,
+                UnsubackReason::NoSubscriptionExisted
+            ]
+        );
+
+        handle.disconnect(DisconnectOpts::new()).await.unwrap();
+        assert!(broker.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn resubscribe_all_retain_handling() {
+        let (mut handle, mut server, _) = testing::spawn_pair().await;
+
+        let test = async move {
+            let _rsp = handle
+                .subscribe(SubscribeOpts::new().subscription("a", SubscriptionOpts::new()))
+                .await
+                .unwrap();
+            handle
+                .resubscribe_all(RetainHandling::NoSendOnSubscribe)
+                .await
+                .unwrap();
+        };
+
+        let peer = async {
+            for options in [0x02, 0x22] {
+                let mut subscribe = [0u8; 11];
+                server.read_exact(&mut subscribe).await.unwrap();
+                assert_eq!(subscribe[10], options); // Maximum QoS 2, retain handling overridden
+                server
+                    .write_all(&[0x90, 4, subscribe[2], subscribe[3], 0, 0]) // SUBACK
+                    .await
+                    .unwrap();
+            }
+        };
+
+        future::join(test, peer).await;
+    }
+
+    #[tokio::test]
+    async fn subscribe_dynamic() {
+        let (mut handle, mut server, _) = testing::spawn_pair().await;
+
+        let test = async move {
+            assert!(matches!(
+                handle.subscribe_dynamic(&[]).await,
+                Err(MqttError::CodecError(
+                    crate::error::CodecError::EmptyPayload(_)
+                ))
+            ));
+
+            let topics: Vec<String> = (0..2).map(|idx| format!("t/{}", idx)).collect();
+            let filters: Vec<(&str, SubscriptionOpts)> = topics
+                .iter()
+                .map(|topic| (topic.as_str(), SubscriptionOpts::new()))
+                .collect();
+
+            let rsp = handle.subscribe_dynamic(&filters).await.unwrap();
+            assert_eq!(rsp.topic_filters(), topics);
+        };
+
+        let peer = async {
+            let mut subscribe = [0u8; 19];
+            server.read_exact(&mut subscribe).await.unwrap();
+            assert_eq!(&subscribe[7..12], b"\0\x03t/0");
+            assert_eq!(&subscribe[13..18], b"\0\x03t/1");
+            server
+                .write_all(&[0x90, 5, subscribe[2], subscribe[3], 0, 0, 0]) // SUBACK
+                .await
+                .unwrap();
+        };
+
+        future::join(test, peer).await;
+    }
+
+    #[tokio::test]
+    async fn wait_for_message_timeout() {
+        let (mut handle, mut server, _) = testing::spawn_pair().await;
+
+        let test = async move {
+            let msg = handle
+                .wait_for_message("a", Duration::from_millis(10))
+                .await
+                .unwrap();
+            assert!(msg.is_none());
+        };
+
+        let peer = async {
+            let mut subscribe = [0u8; 11];
+            server.read_exact(&mut subscribe).await.unwrap();
+            assert_eq!(subscribe[10] & 0x30, 0); // Retain handling: send on subscribe
+            server
+                .write_all(&[0x90, 4, subscribe[2], subscribe[3], 0, 0]) // SUBACK
+                .await
+                .unwrap();
+
+            let mut unsubscribe = [0u8; 8];
+            server.read_exact(&mut unsubscribe).await.unwrap();
+            assert_eq!(unsubscribe[0], 0xa2); // UNSUBSCRIBE
+            server
+                .write_all(&[0xb0, 4, unsubscribe[2], unsubscribe[3], 0, 0]) // UNSUBACK
+                .await
+                .unwrap();
+        };
+
+        future::join(test, peer).await;
+    }
+
+    #[tokio::test]
+    async fn subscribe_once() {
+        let (mut handle, mut server, _) = testing::spawn_pair().await;
+
+        let test = async move {
+            let msg = handle
+                .subscribe_once("a", Duration::from_secs(10))
+                .await
+                .unwrap();
+            assert_eq!(msg.unwrap().payload(), b"ok");
+
+            let msg = handle
+                .subscribe_once("a", Duration::from_millis(10))
+                .await
+                .unwrap();
+            assert!(msg.is_none());
+        };
+
+        let peer = async {
+            for payload in [Some(b"ok"), None] {
+                let mut subscribe = [0u8; 11];
+                server.read_exact(&mut subscribe).await.unwrap();
+                assert_eq!(subscribe[10] & 0x30, 0x10); // Retain handling: send if no subscription
+                server
+                    .write_all(&[0x90, 4, subscribe[2], subscribe[3], 0, 0]) // SUBACK
+                    .await
+                    .unwrap();
+
+                if let Some(payload) = payload {
+                    let id = subscribe[6];
+                    server
+                        .write_all(&[0x31, 8, 0, 1, b'a', 2, 0x0b, id, payload[0], payload[1]]) // Retained PUBLISH
+                        .await
+                        .unwrap();
+                }
+
+                let mut unsubscribe = [0u8; 8];
+                server.read_exact(&mut unsubscribe).await.unwrap();
+                assert_eq!(unsubscribe[0], 0xa2); // UNSUBSCRIBE
+                server
+                    .write_all(&[0xb0, 4, unsubscribe[2], unsubscribe[3], 0, 0]) // UNSUBACK
+                    .await
+                    .unwrap();
+            }
+        };
+
+        future::join(test, peer).await;
+    }
+}
@@ -0,0 +1,195 @@
+use crate::client::{error::MqttError, owned_opts::OwnedPublishOpts, rsp::PublishData};
+use std::sync::{Arc, RwLock};
+
+pub(crate) type SharedInterceptors = Arc<RwLock<Vec<Box<dyn PublishInterceptor + Send + Sync>>>>;
+
+/// Hook applied to each outgoing message before it is encoded, registered with
+/// [add_publish_interceptor](crate::Context::add_publish_interceptor). Meant for
+/// the concerns shared by all the published messages, e.g. payload compression or signing.
+///
+/// The message is given in the owned form, so that the interceptor may replace
+/// the payload or add properties with the newly allocated data.
+///
+pub trait PublishInterceptor {
+    /// Modifies the message in place. Returning an error aborts the publish operation
+    /// with that error, without sending the message.
+    ///
+    fn intercept(&self, opts: &mut OwnedPublishOpts) -> Result<(), MqttError>;
+}
+
+impl<F> PublishInterceptor for F
+where
+    F: Fn(&mut OwnedPublishOpts) -> Result<(), MqttError>,
+{
+    fn intercept(&self, opts: &mut OwnedPublishOpts) -> Result<(), MqttError> {
+        self(opts)
+    }
+}
+
+/// Hook applied to each incoming message before it is delivered to the subscription streams,
+/// registered with [add_receive_interceptor](crate::Context::add_receive_interceptor).
+/// Counterpart of the [PublishInterceptor], e.g. for payload decompression.
+///
+pub trait ReceiveInterceptor {
+    /// Modifies the message in place. Returning an error drops the message: it is still
+    /// acknowledged to the broker, but not delivered to any of the subscriptions.
+    ///
+    fn intercept(&self, msg: &mut PublishData) -> Result<(), MqttError>;
+}
+
+impl<F> ReceiveInterceptor for F
+where
+    F: Fn(&mut PublishData) -> Result<(), MqttError>,
+{
+    fn intercept(&self, msg: &mut PublishData) -> Result<(), MqttError> {
+        self(msg)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{prelude::*, ConnectOpts, Context, PublishOpts, SubscribeOpts, SubscriptionOpts};
+    use futures::future;
+    use std::mem;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+    #[tokio::test]
+    async fn interceptor_chain() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, mut handle) = Context::new();
+        context
+            .set_up((crx.compat(), ctx.compat_write()))
+            .add_publish_interceptor(|opts: &mut OwnedPublishOpts| {
+                if opts.get_topic_name() == Some("forbidden") {
+                    return Err(MqttError::QueueFull(crate::error::QueueFull));
+                }
+
+                let payload = opts.get_payload().unwrap_or_default().to_ascii_uppercase();
+                *opts = mem::take(opts).payload(payload);
+                Ok(())
+            })
+            .add_publish_interceptor(|opts: &mut OwnedPublishOpts| {
+                let mut payload = opts.get_payload().unwrap_or_default().to_vec();
+                payload.push(b'!');
+                *opts = mem::take(opts).payload(payload);
+                Ok(())
+            });
+
+        let client = async move {
+            context
+                .connect(ConnectOpts::new().client_identifier("test"))
+                .await
+                .unwrap();
+            context.run().await
+        };
+
+        let test = async move {
+            assert!(matches!(
+                handle
+                    .publish(PublishOpts::new().topic_name("forbidden").payload(b"ok"))
+                    .await,
+                Err(MqttError::QueueFull(_))
+            ));
+
+            handle
+                .publish(PublishOpts::new().topic_name("a").payload(b"ok"))
+                .await
+                .unwrap();
+        };
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+
+            let mut publish = [0u8; 9];
+            server.read_exact(&mut publish).await.unwrap();
+            assert_eq!(publish, [0x30, 7, 0, 1, b'a', 0, b'O', b'K', b'!']); // Intercepted PUBLISH
+            future::pending::<()>().await; // Keep the connection open
+        };
+
+        match future::select(Box::pin(test), Box::pin(future::join(client, server))).await {
+            future::Either::Left(_) => {}
+            future::Either::Right(_) => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn receive_interceptor_chain() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, mut handle) = Context::new();
+        context
+            .set_up((crx.compat(), ctx.compat_write()))
+            .add_receive_interceptor(|msg: &mut PublishData| {
+                if msg.payload() == b"no" {
+                    return Err(MqttError::QueueFull(crate::error::QueueFull));
+                }
+
+                let payload = msg.payload().to_ascii_uppercase();
+                msg.set_payload(payload);
+                Ok(())
+            })
+            .add_receive_interceptor(|msg: &mut PublishData| {
+                let mut payload = msg.payload().to_vec();
+                payload.push(b'!');
+                msg.set_payload(payload);
+                Ok(())
+            });
+
+        let client = async move {
+            context
+                .connect(ConnectOpts::new().client_identifier("test"))
+                .await
+                .unwrap();
+            context.run().await
+        };
+
+        let test = async move {
+            let mut stream = handle
+                .subscribe(SubscribeOpts::new().subscription("a", SubscriptionOpts::new()))
+                .await
+                .unwrap()
+                .stream();
+
+            assert_eq!(stream.next().await.unwrap().payload(), b"OK!");
+            assert_eq!(stream.stats().messages_received, 1);
+        };
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+
+            let _ = server.read(&mut buf).await.unwrap(); // SUBSCRIBE
+            server
+                .write_all(&[0x90, 4, buf[2], buf[3], 0, 0]) // SUBACK
+                .await
+                .unwrap();
+
+            server
+                .write_all(&[0x32, 10, 0, 1, b'a', 0, 1, 2, 0x0b, 1, b'n', b'o']) // QoS 1 PUBLISH
+                .await
+                .unwrap();
+
+            let _ = server.read(&mut buf).await.unwrap();
+            assert_eq!(buf[..4], [0x40, 2, 0, 1]); // Rejected message is still acknowledged
+
+            server
+                .write_all(&[0x30, 8, 0, 1, b'a', 2, 0x0b, 1, b'o', b'k']) // PUBLISH
+                .await
+                .unwrap();
+            future::pending::<()>().await; // Keep the connection open
+        };
+
+        match future::select(Box::pin(test), Box::pin(future::join(client, server))).await {
+            future::Either::Left(_) => {}
+            future::Either::Right(_) => unreachable!(),
+        }
+    }
+}
@@ -1,8 +1,75 @@
-use crate::codec::RxPacket;
+use crate::codec::{RxPacket, SubscriptionOptions};
 use bytes::BytesMut;
-use futures::channel::{mpsc, oneshot};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use futures::channel::{
+    mpsc::{self, TrySendError},
+    oneshot,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use super::error::MqttError;
+use super::{
+    error::{MqttError, QueueFull},
+    rsp::PublishData,
+    stream::SubscriptionStats,
+};
+
+pub(crate) type TopicFilters = Vec<(String, SubscriptionOptions)>;
+
+pub(crate) type PublishCallback = Box<dyn FnOnce(Result<(), MqttError>) + Send>;
+
+pub(crate) type MessageFilter = Box<dyn Fn(&PublishData) -> bool + Send>;
+
+/// Sender of the normal message queue, counting the messages not yet taken by the context
+/// against the queue capacity.
+///
+#[derive(Clone)]
+pub(crate) struct MessageSender {
+    sender: mpsc::UnboundedSender<ContextMessage>,
+    queued: Arc<AtomicUsize>,
+    capacity: usize,
+}
+
+impl MessageSender {
+    pub(crate) fn new(
+        sender: mpsc::UnboundedSender<ContextMessage>,
+        queued: Arc<AtomicUsize>,
+        capacity: usize,
+    ) -> Self {
+        Self {
+            sender,
+            queued,
+            capacity,
+        }
+    }
+
+    /// Enqueues the message, failing with [QueueFull] when the queue is at its capacity.
+    ///
+    pub(crate) fn try_send(&self, msg: ContextMessage) -> Result<(), MqttError> {
+        self.queued
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |queued| {
+                (queued < self.capacity).then_some(queued + 1)
+            })
+            .map_err(|_| QueueFull)?;
+
+        self.sender.unbounded_send(msg).map_err(|err| {
+            self.queued.fetch_sub(1, Ordering::AcqRel);
+            err.into()
+        })
+    }
+
+    /// Enqueues the message regardless of the capacity, used for continuing the operations
+    /// already accepted.
+    ///
+    pub(crate) fn send(&self, msg: ContextMessage) -> Result<(), TrySendError<ContextMessage>> {
+        self.queued.fetch_add(1, Ordering::AcqRel);
+        self.sender.unbounded_send(msg).inspect_err(|_| {
+            self.queued.fetch_sub(1, Ordering::AcqRel);
+        })
+    }
+}
 
 pub(crate) struct FireAndForget {
     pub(crate) packet: BytesMut,
@@ -15,16 +82,54 @@ pub(crate) struct AwaitAck {
     pub(crate) response_channel: oneshot::Sender<Result<RxPacket, MqttError>>,
 }
 
+pub(crate) struct Ping {
+    pub(crate) packet: BytesMut,
+    pub(crate) response_channel: oneshot::Sender<Result<Duration, MqttError>>, // Round-trip time
+}
+
+pub(crate) struct PublishWithCallback {
+    pub(crate) action_id: Option<usize>, // None for QoS 0
+    pub(crate) packet: BytesMut,
+    pub(crate) callback: PublishCallback,
+}
+
 pub(crate) struct Subscribe {
     pub(crate) action_id: usize,
     pub(crate) subscription_identifier: usize,
     pub(crate) packet: BytesMut,
     pub(crate) response_channel: oneshot::Sender<Result<RxPacket, MqttError>>,
     pub(crate) stream: mpsc::UnboundedSender<RxPacket>,
+    pub(crate) stats: Arc<Mutex<SubscriptionStats>>,
+    pub(crate) topic_filters: TopicFilters,
+    pub(crate) filter: Option<MessageFilter>,
+}
+
+pub(crate) struct Unsubscribe {
+    pub(crate) action_id: usize,
+    pub(crate) packet: BytesMut,
+    pub(crate) response_channel: oneshot::Sender<Result<RxPacket, MqttError>>,
+    pub(crate) topic_filters: Vec<String>,
+}
+
+pub(crate) struct Drain {
+    pub(crate) response_channel: oneshot::Sender<Result<(), MqttError>>,
+}
+
+pub(crate) struct Subscriptions {
+    pub(crate) response_channel: oneshot::Sender<Vec<(usize, TopicFilters)>>,
 }
 
 pub(crate) enum ContextMessage {
     FireAndForget(FireAndForget),
     AwaitAck(AwaitAck),
+    Ping(Ping),
+    PublishWithCallback(PublishWithCallback),
     Subscribe(Subscribe),
+    Unsubscribe(Unsubscribe),
+    Raw(FireAndForget),
+    Detached(BytesMut),
+    Drain(Drain),
+    Subscriptions(Subscriptions),
+    PauseSubscription(usize),
+    ResumeSubscription(usize),
 }
@@ -1,14 +1,29 @@
+mod auth;
+#[cfg(feature = "compress")]
+pub(crate) mod compress;
 mod context;
 mod handle;
+mod interceptor;
 mod message;
 mod opts;
+mod owned_opts;
+#[cfg(feature = "pool")]
+mod pool;
 mod rsp;
 mod stream;
+mod timed;
+mod topic;
 mod utils;
 
 pub(crate) mod error;
 
-pub use context::Context;
+pub use auth::AuthHandler;
+pub use context::{BoxedContext, Context, ContextStats};
 pub use handle::ContextHandle;
+pub use interceptor::{PublishInterceptor, ReceiveInterceptor};
 pub use opts::*;
+pub use owned_opts::*;
 pub use rsp::*;
+pub use stream::SubscriptionStats;
+pub use timed::TimedContextHandle;
+pub use topic::{TopicFilter, TopicTree};
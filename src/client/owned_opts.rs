@@ -0,0 +1,763 @@
+use crate::{
+    client::opts::{
+        AuthOpts, ConnectOpts, PublishOpts, SubscribeOpts, SubscriptionOpts, UnsubscribeOpts,
+    },
+    codec::{AuthReason, MqttVersion},
+    core::base_types::{PayloadSource, QoS},
+    core::error::ValueIsZero,
+};
+use bytes::Bytes;
+use core::time::Duration;
+
+/// Owned counterpart of [ConnectOpts], represented as a consuming builder.
+/// Does not borrow the supplied data, so it may be moved freely, e.g. into `async move` blocks.
+/// Translated to [ConnectOpts] with [From] implementation for [&OwnedConnectOpts](OwnedConnectOpts).
+///
+#[derive(Clone, Default)]
+pub struct OwnedConnectOpts {
+    opts: ConnectOpts<'static>,
+
+    client_identifier: Option<String>,
+    authentication_method: Option<String>,
+    authentication_data: Option<Vec<u8>>,
+    user_property: Vec<(String, String)>,
+    will_content_type: Option<String>,
+    will_response_topic: Option<String>,
+    will_correlation_data: Option<Vec<u8>>,
+    will_user_property: Vec<(String, String)>,
+    will_topic: Option<String>,
+    will_payload: Option<Vec<u8>>,
+    username: Option<String>,
+    password: Option<Vec<u8>>,
+}
+
+impl OwnedConnectOpts {
+    /// Creates a new [OwnedConnectOpts] instance.
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See [ConnectOpts::client_identifier].
+    ///
+    pub fn client_identifier(mut self, val: impl Into<String>) -> Self {
+        self.client_identifier = Some(val.into());
+        self
+    }
+
+    /// See [ConnectOpts::keep_alive].
+    ///
+    pub fn keep_alive(mut self, val: Duration) -> Self {
+        self.opts = self.opts.keep_alive(val);
+        self
+    }
+
+    /// See [ConnectOpts::session_expiry_interval].
+    ///
+    pub fn session_expiry_interval(mut self, val: Duration) -> Self {
+        self.opts = self.opts.session_expiry_interval(val);
+        self
+    }
+
+    /// See [ConnectOpts::inactivity_timeout].
+    ///
+    pub fn inactivity_timeout(mut self, val: Duration) -> Self {
+        self.opts = self.opts.inactivity_timeout(val);
+        self
+    }
+
+    /// See [ConnectOpts::protocol_version].
+    ///
+    pub fn protocol_version(mut self, val: MqttVersion) -> Self {
+        self.opts = self.opts.protocol_version(val);
+        self
+    }
+
+    /// See [ConnectOpts::receive_maximum].
+    ///
+    #[track_caller]
+    pub fn receive_maximum(mut self, val: u16) -> Self {
+        self.opts = self.opts.receive_maximum(val);
+        self
+    }
+
+    /// See [ConnectOpts::try_receive_maximum].
+    ///
+    pub fn try_receive_maximum(mut self, val: u16) -> Result<Self, ValueIsZero> {
+        self.opts = self.opts.try_receive_maximum(val)?;
+        Ok(self)
+    }
+
+    /// See [ConnectOpts::maximum_packet_size].
+    ///
+    #[track_caller]
+    pub fn maximum_packet_size(mut self, val: u32) -> Self {
+        self.opts = self.opts.maximum_packet_size(val);
+        self
+    }
+
+    /// See [ConnectOpts::try_maximum_packet_size].
+    ///
+    pub fn try_maximum_packet_size(mut self, val: u32) -> Result<Self, ValueIsZero> {
+        self.opts = self.opts.try_maximum_packet_size(val)?;
+        Ok(self)
+    }
+
+    /// See [ConnectOpts::topic_alias_maximum].
+    ///
+    pub fn topic_alias_maximum(mut self, val: u16) -> Self {
+        self.opts = self.opts.topic_alias_maximum(val);
+        self
+    }
+
+    /// See [ConnectOpts::request_response_information].
+    ///
+    pub fn request_response_information(mut self, val: bool) -> Self {
+        self.opts = self.opts.request_response_information(val);
+        self
+    }
+
+    /// See [ConnectOpts::request_problem_information].
+    ///
+    pub fn request_problem_information(mut self, val: bool) -> Self {
+        self.opts = self.opts.request_problem_information(val);
+        self
+    }
+
+    /// See [ConnectOpts::authentication_method].
+    ///
+    pub fn authentication_method(mut self, val: impl Into<String>) -> Self {
+        self.authentication_method = Some(val.into());
+        self
+    }
+
+    /// See [ConnectOpts::authentication_data].
+    ///
+    pub fn authentication_data(mut self, val: impl Into<Vec<u8>>) -> Self {
+        self.authentication_data = Some(val.into());
+        self
+    }
+
+    /// See [ConnectOpts::user_property].
+    ///
+    pub fn user_property(mut self, (key, val): (impl Into<String>, impl Into<String>)) -> Self {
+        self.user_property.push((key.into(), val.into()));
+        self
+    }
+
+    /// See [ConnectOpts::will_qos].
+    ///
+    pub fn will_qos(mut self, val: QoS) -> Self {
+        self.opts = self.opts.will_qos(val);
+        self
+    }
+
+    /// See [ConnectOpts::will_retain].
+    ///
+    pub fn will_retain(mut self, val: bool) -> Self {
+        self.opts = self.opts.will_retain(val);
+        self
+    }
+
+    /// See [ConnectOpts::clean_start].
+    ///
+    pub fn clean_start(mut self, val: bool) -> Self {
+        self.opts = self.opts.clean_start(val);
+        self
+    }
+
+    /// See [ConnectOpts::clean_start_auto].
+    ///
+    pub fn clean_start_auto(mut self) -> Self {
+        self.opts = self.opts.clean_start_auto();
+        self
+    }
+
+    /// See [ConnectOpts::will_delay_interval].
+    ///
+    pub fn will_delay_interval(mut self, val: Duration) -> Self {
+        self.opts = self.opts.will_delay_interval(val);
+        self
+    }
+
+    /// See [ConnectOpts::will_payload_format_indicator].
+    ///
+    pub fn will_payload_format_indicator(mut self, val: bool) -> Self {
+        self.opts = self.opts.will_payload_format_indicator(val);
+        self
+    }
+
+    /// See [ConnectOpts::will_message_expiry_interval].
+    ///
+    pub fn will_message_expiry_interval(mut self, val: Duration) -> Self {
+        self.opts = self.opts.will_message_expiry_interval(val);
+        self
+    }
+
+    /// See [ConnectOpts::will_content_type].
+    ///
+    pub fn will_content_type(mut self, val: impl Into<String>) -> Self {
+        self.will_content_type = Some(val.into());
+        self
+    }
+
+    /// See [ConnectOpts::will_response_topic].
+    ///
+    pub fn will_response_topic(mut self, val: impl Into<String>) -> Self {
+        self.will_response_topic = Some(val.into());
+        self
+    }
+
+    /// See [ConnectOpts::will_correlation_data].
+    ///
+    pub fn will_correlation_data(mut self, val: impl Into<Vec<u8>>) -> Self {
+        self.will_correlation_data = Some(val.into());
+        self
+    }
+
+    /// See [ConnectOpts::will_user_property].
+    ///
+    pub fn will_user_property(
+        mut self,
+        (key, val): (impl Into<String>, impl Into<String>),
+    ) -> Self {
+        self.will_user_property.push((key.into(), val.into()));
+        self
+    }
+
+    /// See [ConnectOpts::will_topic].
+    ///
+    pub fn will_topic(mut self, val: impl Into<String>) -> Self {
+        self.will_topic = Some(val.into());
+        self
+    }
+
+    /// See [ConnectOpts::will_payload].
+    ///
+    pub fn will_payload(mut self, val: impl Into<Vec<u8>>) -> Self {
+        self.will_payload = Some(val.into());
+        self
+    }
+
+    /// See [ConnectOpts::username].
+    ///
+    pub fn username(mut self, val: impl Into<String>) -> Self {
+        self.username = Some(val.into());
+        self
+    }
+
+    /// See [ConnectOpts::password].
+    ///
+    pub fn password(mut self, val: impl Into<Vec<u8>>) -> Self {
+        self.password = Some(val.into());
+        self
+    }
+}
+
+impl<'a> From<&'a OwnedConnectOpts> for ConnectOpts<'a> {
+    fn from(owned: &'a OwnedConnectOpts) -> Self {
+        let mut opts = owned.opts.clone();
+
+        if let Some(val) = owned.client_identifier.as_deref() {
+            opts = opts.client_identifier(val);
+        }
+
+        if let Some(val) = owned.authentication_method.as_deref() {
+            opts = opts.authentication_method(val);
+        }
+
+        if let Some(val) = owned.authentication_data.as_deref() {
+            opts = opts.authentication_data(val);
+        }
+
+        for (key, val) in owned.user_property.iter() {
+            opts = opts.user_property((key, val));
+        }
+
+        if let Some(val) = owned.will_content_type.as_deref() {
+            opts = opts.will_content_type(val);
+        }
+
+        if let Some(val) = owned.will_response_topic.as_deref() {
+            opts = opts.will_response_topic(val);
+        }
+
+        if let Some(val) = owned.will_correlation_data.as_deref() {
+            opts = opts.will_correlation_data(val);
+        }
+
+        for (key, val) in owned.will_user_property.iter() {
+            opts = opts.will_user_property((key, val));
+        }
+
+        if let Some(val) = owned.will_topic.as_deref() {
+            opts = opts.will_topic(val);
+        }
+
+        if let Some(val) = owned.will_payload.as_deref() {
+            opts = opts.will_payload(val);
+        }
+
+        if let Some(val) = owned.username.as_deref() {
+            opts = opts.username(val);
+        }
+
+        if let Some(val) = owned.password.as_deref() {
+            opts = opts.password(val);
+        }
+
+        opts
+    }
+}
+
+/// Owned counterpart of [PublishOpts], represented as a consuming builder.
+/// Does not borrow the supplied data, so it may be moved freely, e.g. into `async move` blocks.
+/// Translated to [PublishOpts] with [From] implementation for [&OwnedPublishOpts](OwnedPublishOpts).
+///
+#[derive(Clone, Default)]
+pub struct OwnedPublishOpts {
+    opts: PublishOpts<'static>,
+
+    topic_name: Option<String>,
+    correlation_data: Option<Vec<u8>>,
+    response_topic: Option<String>,
+    content_type: Option<String>,
+    user_property: Vec<(String, String)>,
+    payload: Option<Bytes>,
+}
+
+impl OwnedPublishOpts {
+    /// Creates a new [OwnedPublishOpts] instance.
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See [PublishOpts::retain].
+    ///
+    pub fn retain(mut self, val: bool) -> Self {
+        self.opts = self.opts.retain(val);
+        self
+    }
+
+    /// See [PublishOpts::qos].
+    ///
+    pub fn qos(mut self, val: QoS) -> Self {
+        self.opts = self.opts.qos(val);
+        self
+    }
+
+    /// See [PublishOpts::allow_qos_downgrade].
+    ///
+    pub fn allow_qos_downgrade(mut self, val: bool) -> Self {
+        self.opts = self.opts.allow_qos_downgrade(val);
+        self
+    }
+
+    /// See [PublishOpts::topic_name].
+    ///
+    pub fn topic_name(mut self, val: impl Into<String>) -> Self {
+        self.topic_name = Some(val.into());
+        self
+    }
+
+    /// See [PublishOpts::payload_format_indicator].
+    ///
+    pub fn payload_format_indicator(mut self, val: bool) -> Self {
+        self.opts = self.opts.payload_format_indicator(val);
+        self
+    }
+
+    /// See [PublishOpts::topic_alias].
+    ///
+    #[track_caller]
+    pub fn topic_alias(mut self, val: u16) -> Self {
+        self.opts = self.opts.topic_alias(val);
+        self
+    }
+
+    /// See [PublishOpts::try_topic_alias].
+    ///
+    pub fn try_topic_alias(mut self, val: u16) -> Result<Self, ValueIsZero> {
+        self.opts = self.opts.try_topic_alias(val)?;
+        Ok(self)
+    }
+
+    /// See [PublishOpts::message_expiry_interval].
+    ///
+    pub fn message_expiry_interval(mut self, val: Duration) -> Self {
+        self.opts = self.opts.message_expiry_interval(val);
+        self
+    }
+
+    /// See [PublishOpts::correlation_data].
+    ///
+    pub fn correlation_data(mut self, val: impl Into<Vec<u8>>) -> Self {
+        self.correlation_data = Some(val.into());
+        self
+    }
+
+    /// See [PublishOpts::response_topic].
+    ///
+    pub fn response_topic(mut self, val: impl Into<String>) -> Self {
+        self.response_topic = Some(val.into());
+        self
+    }
+
+    /// See [PublishOpts::content_type].
+    ///
+    pub fn content_type(mut self, val: impl Into<String>) -> Self {
+        self.content_type = Some(val.into());
+        self
+    }
+
+    /// See [PublishOpts::user_property].
+    ///
+    pub fn user_property(mut self, (key, val): (impl Into<String>, impl Into<String>)) -> Self {
+        self.user_property.push((key.into(), val.into()));
+        self
+    }
+
+    /// See [PublishOpts::payload].
+    ///
+    pub fn payload(mut self, val: impl Into<Vec<u8>>) -> Self {
+        self.payload = Some(Bytes::from(val.into()));
+        self
+    }
+
+    /// See [PublishOpts::payload_bytes].
+    ///
+    pub fn payload_bytes(mut self, val: Bytes) -> Self {
+        self.payload = Some(val);
+        self
+    }
+
+    /// Accesses the topic name set so far.
+    ///
+    pub fn get_topic_name(&self) -> Option<&str> {
+        self.topic_name.as_deref()
+    }
+
+    /// Accesses the payload set so far.
+    ///
+    pub fn get_payload(&self) -> Option<&[u8]> {
+        self.payload.as_deref()
+    }
+}
+
+impl From<&PublishOpts<'_>> for OwnedPublishOpts {
+    fn from(opts: &PublishOpts<'_>) -> Self {
+        let (opts, refs) = opts.split_borrowed();
+
+        Self {
+            opts,
+            topic_name: refs.topic_name.map(String::from),
+            correlation_data: refs.correlation_data.map(Vec::from),
+            response_topic: refs.response_topic.map(String::from),
+            content_type: refs.content_type.map(String::from),
+            user_property: refs
+                .user_property
+                .into_iter()
+                .map(|(key, val)| (String::from(key), String::from(val)))
+                .collect(),
+            payload: refs.payload.map(PayloadSource::into_bytes),
+        }
+    }
+}
+
+impl<'a> From<&'a OwnedPublishOpts> for PublishOpts<'a> {
+    fn from(owned: &'a OwnedPublishOpts) -> Self {
+        let mut opts = owned.opts.clone();
+
+        if let Some(val) = owned.topic_name.as_deref() {
+            opts = opts.topic_name(val);
+        }
+
+        if let Some(val) = owned.correlation_data.as_deref() {
+            opts = opts.correlation_data(val);
+        }
+
+        if let Some(val) = owned.response_topic.as_deref() {
+            opts = opts.response_topic(val);
+        }
+
+        if let Some(val) = owned.content_type.as_deref() {
+            opts = opts.content_type(val);
+        }
+
+        for (key, val) in owned.user_property.iter() {
+            opts = opts.user_property((key, val));
+        }
+
+        if let Some(val) = owned.payload.clone() {
+            opts = opts.payload_bytes(val);
+        }
+
+        opts
+    }
+}
+
+/// Owned counterpart of [SubscribeOpts], represented as a consuming builder.
+/// Does not borrow the supplied data, so it may be moved freely, e.g. into `async move` blocks.
+/// Translated to [SubscribeOpts] with [From] implementation for [&OwnedSubscribeOpts](OwnedSubscribeOpts).
+///
+#[derive(Clone, Default)]
+pub struct OwnedSubscribeOpts {
+    subscription: Vec<(String, SubscriptionOpts)>,
+    user_property: Vec<(String, String)>,
+}
+
+impl OwnedSubscribeOpts {
+    /// Creates a new [OwnedSubscribeOpts] instance.
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See [SubscribeOpts::subscription].
+    ///
+    pub fn subscription(mut self, topic: impl Into<String>, opts: SubscriptionOpts) -> Self {
+        self.subscription.push((topic.into(), opts));
+        self
+    }
+
+    /// See [SubscribeOpts::user_property].
+    ///
+    pub fn user_property(mut self, (key, val): (impl Into<String>, impl Into<String>)) -> Self {
+        self.user_property.push((key.into(), val.into()));
+        self
+    }
+}
+
+impl<'a> From<&'a OwnedSubscribeOpts> for SubscribeOpts<'a> {
+    fn from(owned: &'a OwnedSubscribeOpts) -> Self {
+        let mut opts = SubscribeOpts::new();
+
+        for (topic, subscription_opts) in owned.subscription.iter() {
+            opts = opts.subscription(topic, *subscription_opts);
+        }
+
+        for (key, val) in owned.user_property.iter() {
+            opts = opts.user_property((key, val));
+        }
+
+        opts
+    }
+}
+
+/// Owned counterpart of [UnsubscribeOpts], represented as a consuming builder.
+/// Does not borrow the supplied data, so it may be moved freely, e.g. into `async move` blocks.
+/// Translated to [UnsubscribeOpts] with [From] implementation for [&OwnedUnsubscribeOpts](OwnedUnsubscribeOpts).
+///
+#[derive(Clone, Default)]
+pub struct OwnedUnsubscribeOpts {
+    topic_filter: Vec<String>,
+    user_property: Vec<(String, String)>,
+}
+
+impl OwnedUnsubscribeOpts {
+    /// Creates a new [OwnedUnsubscribeOpts] instance.
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See [UnsubscribeOpts::topic_filter].
+    ///
+    pub fn topic_filter(mut self, val: impl Into<String>) -> Self {
+        self.topic_filter.push(val.into());
+        self
+    }
+
+    /// See [UnsubscribeOpts::user_property].
+    ///
+    pub fn user_property(mut self, (key, val): (impl Into<String>, impl Into<String>)) -> Self {
+        self.user_property.push((key.into(), val.into()));
+        self
+    }
+}
+
+impl<'a> From<&'a OwnedUnsubscribeOpts> for UnsubscribeOpts<'a> {
+    fn from(owned: &'a OwnedUnsubscribeOpts) -> Self {
+        let mut opts = UnsubscribeOpts::new();
+
+        for topic in owned.topic_filter.iter() {
+            opts = opts.topic_filter(topic);
+        }
+
+        for (key, val) in owned.user_property.iter() {
+            opts = opts.user_property((key, val));
+        }
+
+        opts
+    }
+}
+
+/// Owned counterpart of [AuthOpts], represented as a consuming builder.
+/// Does not borrow the supplied data, so it may be moved freely, e.g. into `async move` blocks.
+/// Translated to [AuthOpts] with [From] implementation for [&OwnedAuthOpts](OwnedAuthOpts).
+///
+#[derive(Clone, Default)]
+pub struct OwnedAuthOpts {
+    reason: Option<AuthReason>,
+    reason_string: Option<String>,
+    authentication_method: Option<String>,
+    authentication_data: Option<Vec<u8>>,
+    user_property: Vec<(String, String)>,
+}
+
+impl OwnedAuthOpts {
+    /// Creates a new [OwnedAuthOpts] instance.
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See [AuthOpts::reason].
+    ///
+    pub fn reason(mut self, val: AuthReason) -> Self {
+        self.reason = Some(val);
+        self
+    }
+
+    /// See [AuthOpts::reason_string].
+    ///
+    pub fn reason_string(mut self, val: impl Into<String>) -> Self {
+        self.reason_string = Some(val.into());
+        self
+    }
+
+    /// See [AuthOpts::authentication_method].
+    ///
+    pub fn authentication_method(mut self, val: impl Into<String>) -> Self {
+        self.authentication_method = Some(val.into());
+        self
+    }
+
+    /// See [AuthOpts::authentication_data].
+    ///
+    pub fn authentication_data(mut self, val: impl Into<Vec<u8>>) -> Self {
+        self.authentication_data = Some(val.into());
+        self
+    }
+
+    /// See [AuthOpts::user_property].
+    ///
+    pub fn user_property(mut self, (key, val): (impl Into<String>, impl Into<String>)) -> Self {
+        self.user_property.push((key.into(), val.into()));
+        self
+    }
+}
+
+impl<'a> From<&'a OwnedAuthOpts> for AuthOpts<'a> {
+    fn from(owned: &'a OwnedAuthOpts) -> Self {
+        let mut opts = AuthOpts::new();
+
+        if let Some(val) = owned.reason {
+            opts = opts.reason(val);
+        }
+
+        if let Some(val) = owned.reason_string.as_ref() {
+            opts = opts.reason_string(val);
+        }
+
+        if let Some(val) = owned.authentication_method.as_ref() {
+            opts = opts.authentication_method(val);
+        }
+
+        if let Some(val) = owned.authentication_data.as_ref() {
+            opts = opts.authentication_data(val);
+        }
+
+        for (key, val) in owned.user_property.iter() {
+            opts = opts.user_property((key, val));
+        }
+
+        opts
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::utils::{Encode, SizedPacket};
+    use bytes::BytesMut;
+
+    #[test]
+    fn connect_opts_from_owned() {
+        let owned = OwnedConnectOpts::new()
+            .client_identifier(String::from("test"))
+            .keep_alive(Duration::from_secs(30))
+            .user_property(("key", String::from("val")))
+            .username("user")
+            .password(b"pass".to_vec());
+
+        let expected = ConnectOpts::new()
+            .client_identifier("test")
+            .keep_alive(Duration::from_secs(30))
+            .user_property(("key", "val"))
+            .username("user")
+            .password(b"pass");
+
+        let packet = ConnectOpts::from(&owned).build().unwrap();
+        let mut buf = BytesMut::with_capacity(packet.packet_len());
+        packet.encode(&mut buf);
+
+        let expected_packet = expected.build().unwrap();
+        let mut expected_buf = BytesMut::with_capacity(expected_packet.packet_len());
+        expected_packet.encode(&mut expected_buf);
+
+        assert_eq!(buf, expected_buf);
+    }
+
+    #[test]
+    fn publish_opts_to_owned() {
+        let opts = PublishOpts::new()
+            .qos(QoS::AtMostOnce)
+            .retain(true)
+            .topic_name("a")
+            .message_expiry_interval(Duration::from_secs(30))
+            .correlation_data(b"data")
+            .response_topic("b")
+            .content_type("text")
+            .user_property(("key", "val"))
+            .payload(b"payload");
+
+        let owned = OwnedPublishOpts::from(&opts);
+        assert_eq!(owned.get_topic_name(), Some("a"));
+        assert_eq!(owned.get_payload(), Some(&b"payload"[..]));
+
+        let packet = PublishOpts::from(&owned).build().unwrap();
+        let mut buf = BytesMut::with_capacity(packet.packet_len());
+        packet.encode(&mut buf);
+
+        let expected_packet = opts.build().unwrap();
+        let mut expected_buf = BytesMut::with_capacity(expected_packet.packet_len());
+        expected_packet.encode(&mut expected_buf);
+
+        assert_eq!(buf, expected_buf);
+    }
+
+    #[test]
+    fn try_zero_values() {
+        assert!(OwnedConnectOpts::new().try_receive_maximum(0).is_err());
+        assert!(OwnedConnectOpts::new().try_maximum_packet_size(0).is_err());
+        assert!(OwnedPublishOpts::new().try_topic_alias(0).is_err());
+
+        let owned = OwnedPublishOpts::new()
+            .topic_name("a")
+            .try_topic_alias(3)
+            .unwrap();
+        let expected = PublishOpts::new().topic_name("a").topic_alias(3);
+
+        let packet = PublishOpts::from(&owned).build().unwrap();
+        let mut buf = BytesMut::with_capacity(packet.packet_len());
+        packet.encode(&mut buf);
+
+        let expected_packet = expected.build().unwrap();
+        let mut expected_buf = BytesMut::with_capacity(expected_packet.packet_len());
+        expected_packet.encode(&mut expected_buf);
+
+        assert_eq!(buf, expected_buf);
+    }
+}
@@ -0,0 +1,131 @@
+use bytes::{Bytes, BytesMut};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex, PoisonError,
+};
+
+/// Bounded pool of buffers for the outgoing packets, shared between the
+/// [Context](super::context::Context) and its handles.
+///
+#[derive(Clone)]
+pub(crate) struct BufferPool {
+    buffers: Arc<Mutex<Vec<BytesMut>>>,
+    max_size: Arc<AtomicUsize>,
+}
+
+impl BufferPool {
+    pub(crate) fn new(max_size: usize) -> Self {
+        Self {
+            buffers: Arc::new(Mutex::new(Vec::with_capacity(max_size))),
+            max_size: Arc::new(AtomicUsize::from(max_size)),
+        }
+    }
+
+    /// Sets the maximum number of buffers kept in the pool, dropping the excess ones.
+    ///
+    pub(crate) fn set_max_size(&self, max_size: usize) {
+        self.max_size.store(max_size, Ordering::Relaxed);
+        self.buffers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .truncate(max_size);
+    }
+
+    /// Takes an empty buffer with at least `capacity` bytes of capacity,
+    /// allocating a new one when the pool is empty.
+    ///
+    pub(crate) fn take(&self, capacity: usize) -> BytesMut {
+        let buf = self
+            .buffers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop();
+
+        match buf {
+            Some(mut buf) => {
+                buf.reserve(capacity);
+                buf
+            }
+            None => BytesMut::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the buffer to the pool, unless the pool is full.
+    ///
+    pub(crate) fn give_back(&self, mut buf: BytesMut) {
+        buf.clear();
+
+        let mut buffers = self.buffers.lock().unwrap_or_else(PoisonError::into_inner);
+        if buffers.len() < self.max_size.load(Ordering::Relaxed) {
+            buffers.push(buf);
+        }
+    }
+
+    /// Returns the frozen buffer to the pool, if it is not shared.
+    ///
+    pub(crate) fn give_back_frozen(&self, buf: Bytes) {
+        if let Ok(buf) = buf.try_into_mut() {
+            self.give_back(buf);
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.buffers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reuse() {
+        let pool = BufferPool::new(2);
+
+        let mut buf = pool.take(64);
+        buf.extend_from_slice(b"packet");
+        let ptr = buf.as_ptr();
+        pool.give_back(buf);
+        assert_eq!(pool.len(), 1);
+
+        let buf = pool.take(16);
+        assert!(buf.is_empty());
+        assert!(buf.capacity() >= 64);
+        assert_eq!(buf.as_ptr(), ptr);
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn bounded() {
+        let pool = BufferPool::new(2);
+
+        for _ in 0..3 {
+            pool.give_back(BytesMut::with_capacity(8));
+        }
+        assert_eq!(pool.len(), 2);
+
+        pool.set_max_size(1);
+        assert_eq!(pool.len(), 1);
+
+        pool.set_max_size(0);
+        pool.give_back(BytesMut::with_capacity(8));
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn frozen() {
+        let pool = BufferPool::new(2);
+
+        let bytes = pool.take(8).freeze();
+        let shared = bytes.clone();
+        pool.give_back_frozen(bytes);
+        assert_eq!(pool.len(), 0); // Still referenced
+
+        pool.give_back_frozen(shared);
+        assert_eq!(pool.len(), 1);
+    }
+}
@@ -2,29 +2,46 @@ use crate::{
     client::error::{AuthError, ConnectError},
     codec::*,
     core::{
-        base_types::{NonZero, QoS},
+        base_types::{NonZero, Payload, QoS},
         collections::UserProperties,
     },
 };
+use bytes::Bytes;
 use futures::channel::mpsc::{self};
-use std::{str, time::Duration};
+use std::{
+    str,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use super::{
     error::{PubackError, PubcompError, PubrecError},
-    stream::SubscribeStream,
+    message::ContextMessage,
+    stream::{SubscribeStream, SubscriptionStats},
 };
 
 /// Response from connection request.
 /// Accesses data in CONNACK packet.
 ///
 pub struct ConnectRsp {
-    packet: ConnackRx,
+    pub(crate) packet: ConnackRx,
+    pub(crate) requested_session_expiry_interval: u32,
 }
 
 impl TryFrom<ConnackRx> for ConnectRsp {
     type Error = ConnectError;
 
     fn try_from(packet: ConnackRx) -> Result<Self, Self::Error> {
+        Self::from_connack(packet)
+    }
+}
+
+impl ConnectRsp {
+    /// Creates the response from the CONNACK packet. This is the single place where
+    /// the connection result is checked: reason values greater or equal 0x80 result
+    /// in [ConnectError].
+    ///
+    pub(crate) fn from_connack(packet: ConnackRx) -> Result<Self, ConnectError> {
         if packet.reason as u8 >= 0x80 {
             return Err(ConnectError::from(packet));
         }
@@ -33,11 +50,12 @@ impl TryFrom<ConnackRx> for ConnectRsp {
             bool::from(packet.subscription_identifier_available),
             "Subscription identifier support is required. Check your broker settings."
         );
-        Ok(Self { packet })
+        Ok(Self {
+            packet,
+            requested_session_expiry_interval: 0,
+        })
     }
-}
 
-impl ConnectRsp {
     /// Accesses session present flag.
     ///
     pub fn session_present(&self) -> bool {
@@ -90,26 +108,31 @@ impl ConnectRsp {
             .map(Duration::from_secs)
     }
 
-    /// Accesses server receive maximum value.
+    /// Accesses server receive maximum value, being the effective limit of QoS>0 messages
+    /// the client may have awaiting acknowledgement. Messages sent by the broker are limited
+    /// by the [receive maximum](crate::ConnectOpts::receive_maximum) sent in the CONNECT packet.
     ///
     pub fn receive_maximum(&self) -> u16 {
         NonZero::from(self.packet.receive_maximum).get()
     }
 
-    /// Accesses topic alias maximum value.
+    /// Accesses topic alias maximum value, being the effective limit of topic aliases the
+    /// client may use when publishing. Value of 0 means that topic aliases are not accepted.
     ///
     pub fn topic_alias_maximum(&self) -> u16 {
         u16::from(self.packet.topic_alias_maximum)
     }
 
-    /// Accesses session expiry interval value.
+    /// Accesses session expiry interval value confirmed by the broker. When not present
+    /// in the CONNACK packet, the value sent in the CONNECT packet is returned.
     ///
-    pub fn session_expiry_interval(&self) -> Option<Duration> {
-        self.packet
-            .session_expiry_interval
-            .map(u32::from)
-            .map(u64::from)
-            .map(Duration::from_secs)
+    pub fn session_expiry_interval(&self) -> Duration {
+        Duration::from_secs(u64::from(
+            self.packet
+                .session_expiry_interval
+                .map(u32::from)
+                .unwrap_or(self.requested_session_expiry_interval),
+        ))
     }
 
     /// Accesses server maximum packet size.
@@ -273,6 +296,10 @@ impl AuthRsp {
 pub struct SubscribeRsp {
     pub(crate) packet: SubackRx,
     pub(crate) receiver: mpsc::UnboundedReceiver<RxPacket>,
+    pub(crate) stats: Arc<Mutex<SubscriptionStats>>,
+    pub(crate) topic_filters: Vec<String>,
+    pub(crate) subscription_identifier: usize,
+    pub(crate) control_sender: mpsc::UnboundedSender<ContextMessage>,
 }
 
 impl SubscribeRsp {
@@ -282,9 +309,19 @@ impl SubscribeRsp {
     pub fn stream(self) -> SubscribeStream {
         SubscribeStream {
             receiver: self.receiver,
+            stats: self.stats,
+            subscription_identifier: self.subscription_identifier,
+            control_sender: self.control_sender,
         }
     }
 
+    /// Accesses the packet identifier of the SUBACK packet, matching the identifier
+    /// of the corresponding SUBSCRIBE request.
+    ///
+    pub fn packet_identifier(&self) -> u16 {
+        self.packet.packet_identifier.get()
+    }
+
     /// Accesses reason string property.
     ///
     pub fn reason_string(&self) -> Option<&str> {
@@ -310,15 +347,42 @@ impl SubscribeRsp {
     pub fn payload(&self) -> &[SubackReason] {
         &self.packet.payload
     }
+
+    /// Returns the QoS granted for the topic filter at the given index of the request.
+    /// Returns [None] if the subscription failed or the index is out of bounds.
+    ///
+    pub fn granted_qos_for(&self, topic_idx: usize) -> Option<QoS> {
+        match self.packet.payload.get(topic_idx)? {
+            SubackReason::GranteedQoS0 => Some(QoS::AtMostOnce),
+            SubackReason::GranteedQoS1 => Some(QoS::AtLeastOnce),
+            SubackReason::GranteedQoS2 => Some(QoS::ExactlyOnce),
+            _ => None,
+        }
+    }
+
+    /// Accesses the topic filters of the subscribe request, in the same order
+    /// as the reason codes in the [payload](SubscribeRsp::payload).
+    ///
+    pub fn topic_filters(&self) -> &[String] {
+        &self.topic_filters
+    }
 }
 
 /// Response to the unsubscribe request, representing the UNSUBACK packet.
 ///
 pub struct UnsubscribeRsp {
     pub(crate) packet: UnsubackRx,
+    pub(crate) topic_filters: Vec<String>,
 }
 
 impl UnsubscribeRsp {
+    /// Accesses the packet identifier of the UNSUBACK packet, matching the identifier
+    /// of the corresponding UNSUBSCRIBE request.
+    ///
+    pub fn packet_identifier(&self) -> u16 {
+        self.packet.packet_identifier.get()
+    }
+
     /// Accesses reason string property.
     ///
     pub fn reason_string(&self) -> Option<&str> {
@@ -343,6 +407,23 @@ impl UnsubscribeRsp {
     pub fn payload(&self) -> &[UnsubackReason] {
         &self.packet.payload
     }
+
+    /// Accesses the topic filters of the unsubscribe request, in the same order
+    /// as the reason codes in the [payload](UnsubscribeRsp::payload).
+    ///
+    pub fn topic_filters(&self) -> &[String] {
+        &self.topic_filters
+    }
+
+    /// Iterates over the topic filters of the unsubscribe request, each paired
+    /// with its [UnsubackReason] code.
+    ///
+    pub fn results(&self) -> impl Iterator<Item = (&str, UnsubackReason)> {
+        self.topic_filters
+            .iter()
+            .map(String::as_str)
+            .zip(self.packet.payload.iter().copied())
+    }
 }
 
 /// Accesses data in the incoming PUBLISH packet.
@@ -447,18 +528,88 @@ impl PublishData {
         self.packet.payload.0.as_ref()
     }
 
+    /// Accesses payload as the reference-counted [Bytes] buffer, allowing to
+    /// [forward](crate::PublishOpts::payload_bytes) it without copying.
+    ///
+    pub fn payload_bytes(&self) -> Bytes {
+        self.packet.payload.0.clone()
+    }
+
+    /// Replaces payload, e.g. with the decompressed one in the
+    /// [receive interceptor](crate::ReceiveInterceptor).
+    ///
+    pub fn set_payload(&mut self, val: impl Into<Vec<u8>>) {
+        self.packet.payload = Payload(Bytes::from(val.into()));
+    }
+
     /// Accesses user properties.
     ///
     pub fn user_properties(&self) -> &UserProperties {
         &self.packet.user_property
     }
 
-    pub(crate) fn subscription_identifier(&self) -> Option<u32> {
+    pub(crate) fn into_inner(self) -> PublishRx {
+        self.packet
+    }
+
+    pub(crate) fn subscription_identifiers(&self) -> impl Iterator<Item = u32> + '_ {
         self.packet
-            .subscription_identifier
+            .subscription_identifiers
+            .iter()
+            .copied()
             .map(NonZero::from)
-            .map(|val| val.get())
-            .map(|val| val.value())
+            .map(|val| val.get().value())
+    }
+}
+
+/// Result of the completed [publish](crate::ContextHandle::publish) flow, with the packet
+/// identifier allowing to correlate the concurrently published QoS>0 messages.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublishResult {
+    /// QoS==0 message was sent, no packet identifier is used.
+    ///
+    AtMostOnce,
+
+    /// QoS==1 message was acknowledged with the PUBACK packet.
+    ///
+    AtLeastOnce {
+        /// Packet identifier of the message.
+        ///
+        packet_id: u16,
+
+        /// PUBACK reason value.
+        ///
+        reason: PubackReason,
+    },
+
+    /// QoS==2 message was acknowledged with the PUBREC and PUBCOMP packets.
+    ///
+    ExactlyOnce {
+        /// Packet identifier of the message.
+        ///
+        packet_id: u16,
+
+        /// PUBREC reason value.
+        ///
+        pubrec_reason: PubrecReason,
+
+        /// PUBCOMP reason value.
+        ///
+        pubcomp_reason: PubcompReason,
+    },
+}
+
+impl PublishResult {
+    /// Accesses the packet identifier, [None] for QoS==0 messages.
+    ///
+    pub fn packet_id(&self) -> Option<u16> {
+        match self {
+            Self::AtMostOnce => None,
+            Self::AtLeastOnce { packet_id, .. } | Self::ExactlyOnce { packet_id, .. } => {
+                Some(*packet_id)
+            }
+        }
     }
 }
 
@@ -593,3 +744,91 @@ impl TryFrom<PubcompRx> for PubcompRsp {
         Ok(Self { packet })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::utils::TryDecode;
+    use bytes::Bytes;
+
+    fn connack(reason: u8) -> ConnackRx {
+        ConnackRx::try_decode(Bytes::copy_from_slice(&[0x20, 3, 0, reason, 0])).unwrap()
+    }
+
+    #[test]
+    fn from_connack() {
+        let rsp = ConnectRsp::from_connack(connack(0x00)).unwrap();
+        assert_eq!(rsp.reason(), ConnectReason::Success);
+
+        let err = ConnectRsp::from_connack(connack(0x87)).err().unwrap();
+        assert_eq!(err.reason(), ConnectReason::NotAuthorized);
+    }
+
+    #[test]
+    fn capability_flags() {
+        let rsp = ConnectRsp::from_connack(connack(0x00)).unwrap();
+        assert!(rsp.retain_available()); // Absent properties mean the feature is available
+        assert!(rsp.wildcard_subscription_available());
+        assert!(rsp.subscription_identifier_available());
+
+        let packet =
+            ConnackRx::try_decode(Bytes::from_static(&[0x20, 7, 0, 0, 4, 0x25, 0, 0x28, 0]))
+                .unwrap();
+        let rsp = ConnectRsp::from_connack(packet).unwrap();
+        assert!(!rsp.retain_available());
+        assert!(!rsp.wildcard_subscription_available());
+    }
+
+    #[test]
+    fn granted_qos_for() {
+        let (_, receiver) = mpsc::unbounded();
+        let (control_sender, _) = mpsc::unbounded();
+        let rsp = SubscribeRsp {
+            packet: SubackRx::try_decode(Bytes::from_static(&[0x90, 7, 0, 1, 0, 0, 2, 0x87, 1]))
+                .unwrap(),
+            receiver,
+            stats: Arc::default(),
+            topic_filters: vec![
+                String::from("a"),
+                String::from("b"),
+                String::from("c"),
+                String::from("d"),
+            ],
+            subscription_identifier: 1,
+            control_sender,
+        };
+
+        assert_eq!(rsp.packet_identifier(), 1);
+        assert_eq!(rsp.granted_qos_for(0), Some(QoS::AtMostOnce));
+        assert_eq!(rsp.granted_qos_for(1), Some(QoS::ExactlyOnce));
+        assert_eq!(rsp.granted_qos_for(2), None);
+        assert_eq!(rsp.granted_qos_for(3), Some(QoS::AtLeastOnce));
+        assert_eq!(rsp.granted_qos_for(4), None);
+    }
+
+    #[test]
+    fn packet_identifier() {
+        let rsp = UnsubscribeRsp {
+            packet: UnsubackRx::try_decode(Bytes::from_static(&[0xb0, 4, 0x45, 0x73, 0, 0]))
+                .unwrap(),
+            topic_filters: vec![String::from("a")],
+        };
+        assert_eq!(rsp.packet_identifier(), 0x4573);
+    }
+
+    #[test]
+    fn unsubscribe_results() {
+        let rsp = UnsubscribeRsp {
+            packet: UnsubackRx::try_decode(Bytes::from_static(&[0xb0, 5, 0, 1, 0, 0x00, 0x11]))
+                .unwrap(),
+            topic_filters: vec![String::from("a/+"), String::from("b")],
+        };
+        assert_eq!(
+            rsp.results().collect::<Vec<_>>(),
+            [
+                ("a/+", UnsubackReason::Success),
+                ("b", UnsubackReason::NoSubscriptionExisted)
+            ]
+        );
+    }
+}
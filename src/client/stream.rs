@@ -1,15 +1,76 @@
-use crate::{client::rsp::PublishData, codec::RxPacket};
+use crate::{
+    client::{message::ContextMessage, rsp::PublishData},
+    codec::RxPacket,
+};
 use futures::{
     channel::mpsc::{self},
     Stream, StreamExt,
 };
 use std::{
     pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
     task::{Context, Poll},
+    time::SystemTime,
 };
 
+/// Snapshot of the statistics of a single subscription.
+///
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SubscriptionStats {
+    /// Number of messages received. Messages buffered while paused are counted once delivered.
+    ///
+    pub messages_received: u64,
+
+    /// Total size of the payload of the received messages, in bytes.
+    ///
+    pub bytes_received: u64,
+
+    /// Time of receiving the last message.
+    ///
+    pub last_message_at: Option<SystemTime>,
+
+    /// Number of messages dropped while paused, not fitting in the buffer.
+    ///
+    pub messages_dropped: u64,
+}
+
 pub struct SubscribeStream {
     pub(crate) receiver: mpsc::UnboundedReceiver<RxPacket>,
+    pub(crate) stats: Arc<Mutex<SubscriptionStats>>,
+    pub(crate) subscription_identifier: usize,
+    pub(crate) control_sender: mpsc::UnboundedSender<ContextMessage>,
+}
+
+impl SubscribeStream {
+    /// Accesses the snapshot of the subscription statistics.
+    ///
+    pub fn stats(&self) -> SubscriptionStats {
+        *self.stats.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Pauses the delivery of messages to this stream, e.g. when the consumer falls behind.
+    /// Messages received in the meantime are buffered by the [Context](crate::Context), up to
+    /// [pause_buffer_size](crate::ContextIoOptions::pause_buffer_size), dropping the oldest ones.
+    /// Messages delivered before pausing may still be read from the stream.
+    ///
+    pub fn pause(&self) {
+        let _ = self // Context may have exited.
+            .control_sender
+            .unbounded_send(ContextMessage::PauseSubscription(
+                self.subscription_identifier,
+            ));
+    }
+
+    /// Resumes the delivery of messages to this stream, starting with the ones buffered
+    /// while [paused](SubscribeStream::pause).
+    ///
+    pub fn resume(&self) {
+        let _ = self
+            .control_sender
+            .unbounded_send(ContextMessage::ResumeSubscription(
+                self.subscription_identifier,
+            ));
+    }
 }
 
 impl Stream for SubscribeStream {
@@ -28,3 +89,14 @@ impl Stream for SubscribeStream {
         }
     }
 }
+
+/// Allows iterating over the messages with `for await` on nightly Rust.
+///
+#[cfg(feature = "nightly")]
+impl core::async_iter::AsyncIterator for SubscribeStream {
+    type Item = PublishData;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Stream::poll_next(self, cx)
+    }
+}
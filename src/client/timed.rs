@@ -0,0 +1,302 @@
+use crate::{
+    client::{
+        error::{MqttError, OperationTimeout},
+        handle::ContextHandle,
+        opts::{DisconnectOpts, PublishOpts, SubscribeOpts, SubscriptionOpts, UnsubscribeOpts},
+        rsp::{PublishData, PublishResult, SubscribeRsp, UnsubscribeRsp},
+    },
+    codec::RetainHandling,
+};
+use core::future::Future;
+use futures::future;
+use futures_timer::Delay;
+use std::time::Duration;
+
+/// [ContextHandle] wrapper limiting the time each operation may take, created with
+/// [with_timeout](ContextHandle::with_timeout). Operations not completed within the
+/// deadline are aborted with [OperationTimeout](crate::error::OperationTimeout) error.
+///
+/// # Note
+/// Aborting the operation does not revert its effects, e.g. the message may still be
+/// delivered to the broker after the timeout elapses.
+///
+#[derive(Clone)]
+pub struct TimedContextHandle {
+    handle: ContextHandle,
+    timeout: Duration,
+}
+
+impl ContextHandle {
+    /// Creates the [TimedContextHandle], performing all operations with the given `timeout`.
+    ///
+    pub fn with_timeout(&self, timeout: Duration) -> TimedContextHandle {
+        TimedContextHandle {
+            handle: self.clone(),
+            timeout,
+        }
+    }
+}
+
+impl TimedContextHandle {
+    pub(crate) async fn timed<T, Fut>(timeout: Duration, fut: Fut) -> Result<T, MqttError>
+    where
+        Fut: Future<Output = Result<T, MqttError>>,
+    {
+        futures::pin_mut!(fut);
+        match future::select(fut, Delay::new(timeout)).await {
+            future::Either::Left((result, _)) => result,
+            future::Either::Right(_) => Err(OperationTimeout.into()),
+        }
+    }
+
+    /// Accesses the timeout applied to each operation.
+    ///
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Converts back into the underlying [ContextHandle].
+    ///
+    pub fn into_inner(self) -> ContextHandle {
+        self.handle
+    }
+
+    /// See [ContextHandle::disconnect].
+    ///
+    pub async fn disconnect<'a>(&mut self, opts: DisconnectOpts<'a>) -> Result<(), MqttError> {
+        Self::timed(self.timeout, self.handle.disconnect(opts)).await
+    }
+
+    /// See [ContextHandle::drain].
+    ///
+    pub async fn drain(&mut self) -> Result<(), MqttError> {
+        Self::timed(self.timeout, self.handle.drain()).await
+    }
+
+    /// See [ContextHandle::remaining_send_quota].
+    ///
+    pub fn remaining_send_quota(&self) -> u16 {
+        self.handle.remaining_send_quota()
+    }
+
+    /// See [ContextHandle::ping].
+    ///
+    pub async fn ping(&mut self) -> Result<(), MqttError> {
+        Self::timed(self.timeout, self.handle.ping()).await
+    }
+
+    /// See [ContextHandle::ping_and_wait].
+    ///
+    pub async fn ping_and_wait(&mut self) -> Result<Duration, MqttError> {
+        Self::timed(self.timeout, self.handle.ping_and_wait()).await
+    }
+
+    /// See [ContextHandle::publish].
+    ///
+    pub async fn publish<'a>(&mut self, opts: PublishOpts<'a>) -> Result<PublishResult, MqttError> {
+        Self::timed(self.timeout, self.handle.publish(opts)).await
+    }
+
+    /// See [ContextHandle::publish_with_callback]. The timeout does not apply, as the method
+    /// only enqueues the message.
+    ///
+    pub fn publish_with_callback<'a, F>(
+        &mut self,
+        opts: PublishOpts<'a>,
+        callback: F,
+    ) -> Result<(), MqttError>
+    where
+        F: FnOnce(Result<(), MqttError>) + Send + 'static,
+    {
+        self.handle.publish_with_callback(opts, callback)
+    }
+
+    /// See [ContextHandle::publish_retained].
+    ///
+    pub async fn publish_retained(&mut self, topic: &str, payload: &[u8]) -> Result<(), MqttError> {
+        Self::timed(self.timeout, self.handle.publish_retained(topic, payload)).await
+    }
+
+    /// See [ContextHandle::clear_retained].
+    ///
+    pub async fn clear_retained(&mut self, topic: &str) -> Result<(), MqttError> {
+        Self::timed(self.timeout, self.handle.clear_retained(topic)).await
+    }
+
+    /// See [ContextHandle::subscribe].
+    ///
+    pub async fn subscribe<'a>(
+        &mut self,
+        opts: SubscribeOpts<'a>,
+    ) -> Result<SubscribeRsp, MqttError> {
+        Self::timed(self.timeout, self.handle.subscribe(opts)).await
+    }
+
+    /// See [ContextHandle::subscribe_with_filter].
+    ///
+    pub async fn subscribe_with_filter<'a, F>(
+        &mut self,
+        opts: SubscribeOpts<'a>,
+        filter: F,
+    ) -> Result<SubscribeRsp, MqttError>
+    where
+        F: Fn(&PublishData) -> bool + Send + 'static,
+    {
+        Self::timed(
+            self.timeout,
+            self.handle.subscribe_with_filter(opts, filter),
+        )
+        .await
+    }
+
+    /// See [ContextHandle::resubscribe_all].
+    ///
+    pub async fn resubscribe_all(
+        &mut self,
+        retain_handling: RetainHandling,
+    ) -> Result<(), MqttError> {
+        Self::timed(self.timeout, self.handle.resubscribe_all(retain_handling)).await
+    }
+
+    /// See [ContextHandle::subscribe_many]. The timeout applies to all the groups together.
+    ///
+    pub async fn subscribe_many<'a>(
+        &mut self,
+        groups: Vec<SubscribeOpts<'a>>,
+    ) -> Result<Vec<SubscribeRsp>, MqttError> {
+        Self::timed(self.timeout, self.handle.subscribe_many(groups)).await
+    }
+
+    /// See [ContextHandle::unsubscribe].
+    ///
+    pub async fn unsubscribe<'a>(
+        &mut self,
+        opts: UnsubscribeOpts<'a>,
+    ) -> Result<UnsubscribeRsp, MqttError> {
+        Self::timed(self.timeout, self.handle.unsubscribe(opts)).await
+    }
+
+    /// See [ContextHandle::wait_for_message]. The timeout applies to subscribing and
+    /// unsubscribing, while waiting for the message is limited by `timeout` only.
+    ///
+    pub async fn wait_for_message(
+        &mut self,
+        topic: &str,
+        timeout: Duration,
+    ) -> Result<Option<PublishData>, MqttError> {
+        self.handle
+            .receive_once(topic, SubscriptionOpts::new(), timeout, Some(self.timeout))
+            .await
+    }
+
+    /// See [ContextHandle::subscribe_once]. The timeout applies to subscribing and
+    /// unsubscribing, while waiting for the message is limited by `timeout` only.
+    ///
+    pub async fn subscribe_once(
+        &mut self,
+        topic: &str,
+        timeout: Duration,
+    ) -> Result<Option<PublishData>, MqttError> {
+        self.handle
+            .receive_once(
+                topic,
+                SubscriptionOpts::new().retain_handling(RetainHandling::SendIfNoSubscription),
+                timeout,
+                Some(self.timeout),
+            )
+            .await
+    }
+
+    /// See [ContextHandle::send_raw].
+    ///
+    #[cfg(feature = "unstable")]
+    pub async fn send_raw(&mut self, packet: bytes::Bytes) -> Result<(), MqttError> {
+        Self::timed(self.timeout, self.handle.send_raw(packet)).await
+    }
+
+    /// See [ContextHandle::request].
+    ///
+    #[cfg(feature = "experimental")]
+    pub async fn request(
+        &mut self,
+        topic: &str,
+        response_topic: &str,
+        payload: &[u8],
+    ) -> Result<PublishData, MqttError> {
+        Self::timed(
+            self.timeout,
+            self.handle.request(topic, response_topic, payload),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ConnectOpts, Context, QoS};
+    use futures::channel::oneshot;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+    #[tokio::test]
+    async fn operation_timeout() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, handle) = Context::new();
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        let client = async move {
+            context
+                .connect(ConnectOpts::new().client_identifier("test"))
+                .await
+                .unwrap();
+            context.run().await
+        };
+
+        let (sender, receiver) = oneshot::channel();
+
+        let test = async move {
+            let mut timed = handle.with_timeout(Duration::from_millis(50));
+            assert!(matches!(
+                timed.ping().await,
+                Err(MqttError::OperationTimeout(_))
+            ));
+            sender.send(()).unwrap();
+
+            // Late PINGRESP does not break the context.
+            timed
+                .publish(
+                    PublishOpts::new()
+                        .topic_name("a")
+                        .qos(QoS::AtLeastOnce)
+                        .payload(b"ok"),
+                )
+                .await
+                .unwrap();
+        };
+
+        let server = async move {
+            let mut buf = [0u8; 64];
+            let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+            server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+
+            let _ = server.read(&mut buf).await.unwrap(); // PINGREQ
+            receiver.await.unwrap();
+            server.write_all(&[0xd0, 0]).await.unwrap(); // PINGRESP
+
+            let _ = server.read(&mut buf).await.unwrap(); // PUBLISH
+            server
+                .write_all(&[0x40, 2, buf[5], buf[6]]) // PUBACK
+                .await
+                .unwrap();
+            future::pending::<()>().await; // Keep the connection open
+        };
+
+        match future::select(Box::pin(test), Box::pin(future::join(client, server))).await {
+            future::Either::Left(_) => {}
+            future::Either::Right(_) => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use crate::client::error::InvalidTopicFilter;
+use std::collections::HashMap;
+
+/// Validated topic filter, used for [subscription requests](super::handle::ContextHandle::subscribe).
+///
+/// A valid topic filter is non-empty, does not contain the null character, fits in 65535 bytes,
+/// and uses the wildcards correctly: the single-level wildcard `+` occupies an entire level,
+/// while the multi-level wildcard `#` occupies the last level.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TopicFilter(String);
+
+impl TopicFilter {
+    /// Accesses the topic filter as a string slice.
+    ///
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    fn validate(val: &str) -> bool {
+        if val.is_empty() || val.len() > u16::MAX as usize || val.contains('\0') {
+            return false;
+        }
+
+        let mut levels = val.split('/').peekable();
+        while let Some(level) = levels.next() {
+            if level.contains('#') && (level != "#" || levels.peek().is_some()) {
+                return false;
+            }
+
+            if level.contains('+') && level != "+" {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl TryFrom<&str> for TopicFilter {
+    type Error = InvalidTopicFilter;
+
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        if !Self::validate(val) {
+            return Err(InvalidTopicFilter);
+        }
+
+        Ok(Self(String::from(val)))
+    }
+}
+
+impl AsRef<str> for TopicFilter {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// Trie of [topic filters](TopicFilter) split on the `/` level separator, mapping each filter to a value.
+/// Finding the values of all filters matching the topic name takes time proportional to the number
+/// of topic levels, rather than the number of filters.
+///
+/// Wildcards in the first level do not match topic names beginning with `$`.
+///
+#[derive(Debug, Clone)]
+pub struct TopicTree<V> {
+    root: TopicNode<V>,
+}
+
+#[derive(Debug, Clone)]
+struct TopicNode<V> {
+    children: HashMap<String, TopicNode<V>>,
+    value: Option<V>,
+}
+
+impl<V> Default for TopicNode<V> {
+    fn default() -> Self {
+        Self {
+            children: HashMap::new(),
+            value: None,
+        }
+    }
+}
+
+impl<V> TopicNode<V> {
+    fn is_empty(&self) -> bool {
+        self.value.is_none() && self.children.is_empty()
+    }
+
+    fn remove<'a>(&mut self, mut levels: impl Iterator<Item = &'a str>) -> Option<V> {
+        let level = match levels.next() {
+            Some(level) => level,
+            None => return self.value.take(),
+        };
+
+        let child = self.children.get_mut(level)?;
+        let value = child.remove(levels);
+
+        if child.is_empty() {
+            self.children.remove(level);
+        }
+
+        value
+    }
+
+    fn collect<'a>(&'a self, levels: &[&str], first: bool, matching: &mut Vec<&'a V>) {
+        let wildcards_allowed =
+            !(first && levels.first().is_some_and(|level| level.starts_with('$')));
+
+        // Multi-level wildcard matches the parent level and any number of child levels.
+        if wildcards_allowed {
+            if let Some(node) = self.children.get("#") {
+                matching.extend(node.value.as_ref());
+            }
+        }
+
+        let (level, rest) = match levels.split_first() {
+            Some(val) => val,
+            None => {
+                matching.extend(self.value.as_ref());
+                return;
+            }
+        };
+
+        if let Some(node) = self.children.get(*level) {
+            node.collect(rest, false, matching);
+        }
+
+        if wildcards_allowed {
+            if let Some(node) = self.children.get("+") {
+                node.collect(rest, false, matching);
+            }
+        }
+    }
+}
+
+impl<V> Default for TopicTree<V> {
+    fn default() -> Self {
+        Self {
+            root: TopicNode::default(),
+        }
+    }
+}
+
+impl<V> TopicTree<V> {
+    /// Creates an empty [TopicTree].
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks if the tree holds no topic filters.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.root.is_empty()
+    }
+
+    /// Inserts the `value` for the topic `filter`, returning the value previously stored for it.
+    ///
+    pub fn insert(&mut self, filter: &TopicFilter, value: V) -> Option<V> {
+        let node = filter
+            .as_str()
+            .split('/')
+            .fold(&mut self.root, |node, level| {
+                node.children.entry(String::from(level)).or_default()
+            });
+
+        node.value.replace(value)
+    }
+
+    /// Removes the topic `filter`, returning the value stored for it.
+    ///
+    pub fn remove(&mut self, filter: &TopicFilter) -> Option<V> {
+        self.root.remove(filter.as_str().split('/'))
+    }
+
+    /// Iterates over the values of all topic filters matching the `topic` name.
+    ///
+    pub fn matching(&self, topic: &str) -> impl Iterator<Item = &V> {
+        let levels: Vec<&str> = topic.split('/').collect();
+
+        let mut matching = Vec::new();
+        self.root.collect(&levels, true, &mut matching);
+        matching.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn valid() {
+        const INPUT: [&str; 7] = ["a", "a/b", "a/+", "+/b", "#", "a/#", "+/+/#"];
+
+        for val in INPUT {
+            assert!(TopicFilter::try_from(val).is_ok(), "{}", val);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        const INPUT: [&str; 6] = ["", "a/#/b", "a#", "a/b+", "+a/b", "a/\0"];
+
+        for val in INPUT {
+            assert!(TopicFilter::try_from(val).is_err(), "{:?}", val);
+        }
+    }
+
+    fn tree(filters: &[&str]) -> TopicTree<String> {
+        let mut tree = TopicTree::new();
+        for &filter in filters {
+            tree.insert(
+                &TopicFilter::try_from(filter).unwrap(),
+                String::from(filter),
+            );
+        }
+        tree
+    }
+
+    fn matching(tree: &TopicTree<String>, topic: &str) -> Vec<String> {
+        let mut matching: Vec<String> = tree.matching(topic).cloned().collect();
+        matching.sort();
+        matching
+    }
+
+    #[test]
+    fn tree_matching() {
+        let tree = tree(&["a/b", "a/+", "+/b", "a/#", "#", "a/b/c", "+/+/c"]);
+
+        assert_eq!(matching(&tree, "a/b"), ["#", "+/b", "a/#", "a/+", "a/b"]);
+        assert_eq!(matching(&tree, "a"), ["#", "a/#"]);
+        assert_eq!(matching(&tree, "x/b"), ["#", "+/b"]);
+        assert_eq!(matching(&tree, "a/b/c"), ["#", "+/+/c", "a/#", "a/b/c"]);
+        assert_eq!(matching(&tree, "x/y/z"), ["#"]);
+    }
+
+    #[test]
+    fn tree_dollar_topics() {
+        let tree = tree(&["#", "+/info", "$SYS/#", "$SYS/+"]);
+
+        assert_eq!(matching(&tree, "$SYS/info"), ["$SYS/#", "$SYS/+"]);
+        assert_eq!(matching(&tree, "a/info"), ["#", "+/info"]);
+    }
+
+    #[test]
+    fn tree_remove() {
+        let mut tree = tree(&["a/b", "a/#"]);
+        let filter = |val: &str| TopicFilter::try_from(val).unwrap();
+
+        assert_eq!(tree.remove(&filter("a/+")), None);
+        assert_eq!(tree.remove(&filter("a/b")), Some(String::from("a/b")));
+        assert_eq!(matching(&tree, "a/b"), ["a/#"]);
+        assert_eq!(
+            tree.insert(&filter("a/#"), String::from("x")),
+            Some(String::from("a/#"))
+        );
+        assert_eq!(tree.remove(&filter("a/#")), Some(String::from("x")));
+        assert!(tree.is_empty());
+    }
+}
@@ -78,3 +78,41 @@ where
 {
     deque.iter().position(|(k, _)| *k == key)
 }
+
+/// Checks whether the packet identifier carried by the given action ID is already
+/// used by a packet awaiting acknowledgement, regardless of the acknowledgement type.
+///
+pub(crate) fn packet_identifier_in_use<V>(deque: &VecDeque<(usize, V)>, action_id: usize) -> bool {
+    let packet_identifier = (action_id >> 8) & 0xffff;
+    if packet_identifier == 0 {
+        return false;
+    }
+
+    deque
+        .iter()
+        .any(|(key, _)| (key >> 8) & 0xffff == packet_identifier)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn packet_identifier_in_use_ignores_ack_type() {
+        let mut deque = VecDeque::new();
+        deque.push_back(((PubackRx::PACKET_ID as usize) << 24 | (1 << 8), ()));
+
+        assert!(packet_identifier_in_use(
+            &deque,
+            (SubackRx::PACKET_ID as usize) << 24 | (1 << 8)
+        ));
+        assert!(!packet_identifier_in_use(
+            &deque,
+            (PubackRx::PACKET_ID as usize) << 24 | (2 << 8)
+        ));
+        assert!(!packet_identifier_in_use(
+            &deque,
+            (PingrespRx::PACKET_ID as usize) << 24
+        ));
+    }
+}
@@ -224,8 +224,8 @@ where
         encoder.encode(self.reason);
         encoder.encode(self.property_len());
 
-        if self.reason_string.is_some() {
-            encoder.encode(self.reason_string.unwrap());
+        if let Some(val) = self.reason_string {
+            encoder.encode(val);
         }
 
         for property in self.user_property.iter().copied() {
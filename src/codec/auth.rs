@@ -3,18 +3,23 @@ use crate::core::{
     collections::UserProperties,
     error::{
         CodecError, ConversionError, InvalidPacketHeader, InvalidPacketSize, InvalidPropertyLength,
-        InvalidValue, MandatoryPropertyMissing, UnexpectedProperty,
+        InvalidValue, MandatoryPropertyMissing, MissingDependentProperty, UnexpectedProperty,
     },
     properties::*,
     utils::{ByteLen, Decoder, Encode, Encoder, PacketID, SizedPacket, TryDecode},
 };
 use bytes::{BufMut, Bytes, BytesMut};
-use core::mem;
+use core::{fmt, mem};
 use derive_builder::Builder;
 
 /// Reason for AUTH packet.
 ///
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum AuthReason {
     /// Success
     ///
@@ -42,12 +47,23 @@ impl TryFrom<u8> for AuthReason {
     }
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for AuthReason {
     fn default() -> Self {
         Self::Success
     }
 }
 
+impl fmt::Display for AuthReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Success => "Success",
+            Self::ContinueAuthentication => "Continue authentication",
+            Self::ReAuthenticate => "Re-authenticate",
+        })
+    }
+}
+
 impl ByteLen for AuthReason {
     fn byte_len(&self) -> usize {
         (*self as u8).byte_len()
@@ -98,6 +114,10 @@ impl<'a> AuthTxBuilder<'a> {
     }
 
     fn validate(&self) -> Result<(), CodecError> {
+        if self.authentication_method.is_none() && self.authentication_data.is_some() {
+            return Err(MissingDependentProperty.into()); // Cannot include authentication data when authentication method is absent.
+        }
+
         let shortened = self
             .reason
             .filter(|&reason| reason != AuthReason::Success)
@@ -194,8 +214,8 @@ impl<'a> Encode for AuthTx<'a> {
         encoder.encode(self.authentication_method.unwrap());
         encoder.encode(self.authentication_data.unwrap());
 
-        if self.reason_string.is_some() {
-            encoder.encode(self.reason_string.unwrap());
+        if let Some(val) = self.reason_string {
+            encoder.encode(val);
         }
 
         for val in self.user_property.iter().copied() {
@@ -398,4 +418,16 @@ mod test {
 
         assert_eq!(&buf.split().freeze()[..], EXPECTED);
     }
+
+    #[test]
+    fn authentication_data_without_method() {
+        let mut builder = AuthTxBuilder::default();
+        builder.reason(AuthReason::ContinueAuthentication);
+        builder.authentication_data(AuthenticationDataRef::from(BinaryRef(b"data")));
+
+        assert!(matches!(
+            builder.build(),
+            Err(CodecError::MissingDependentProperty(_))
+        ));
+    }
 }
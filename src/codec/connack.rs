@@ -9,13 +9,18 @@ use crate::core::{
     utils::{ByteLen, Decoder, PacketID, TryDecode},
 };
 use bytes::Bytes;
-use core::mem;
+use core::{fmt, mem};
 use derive_builder::Builder;
 
 /// Reason for CONNACK packet.
 ///
 #[allow(missing_docs)]
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum ConnectReason {
     Success = 0x00,
     UnspecifiedError = 0x80,
@@ -73,12 +78,42 @@ impl TryFrom<u8> for ConnectReason {
     }
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for ConnectReason {
     fn default() -> Self {
         Self::Success
     }
 }
 
+impl fmt::Display for ConnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Success => "Success",
+            Self::UnspecifiedError => "Unspecified error",
+            Self::MalformedPacket => "Malformed Packet",
+            Self::ProtocolError => "Protocol Error",
+            Self::ImplementationSpecificError => "Implementation specific error",
+            Self::UnsupportedProtocolVersion => "Unsupported Protocol Version",
+            Self::ClientIdentifierNotValid => "Client Identifier not valid",
+            Self::BadUserNameOrPassword => "Bad User Name or Password",
+            Self::NotAuthorized => "Not authorized",
+            Self::ServerUnavailable => "Server unavailable",
+            Self::ServerBusy => "Server busy",
+            Self::Banned => "Banned",
+            Self::BadUthenticationMethod => "Bad authentication method",
+            Self::TopicNameInvalid => "Topic Name invalid",
+            Self::PacketTooLarge => "Packet too large",
+            Self::QuotaExceeded => "Quota exceeded",
+            Self::PayloadFormatInvalid => "Payload format invalid",
+            Self::RetainNotSupported => "Retain not supported",
+            Self::QoSNotSupported => "QoS not supported",
+            Self::UseAnotherServer => "Use another server",
+            Self::ServerMoved => "Server moved",
+            Self::ConnectionRateExceeded => "Connection rate exceeded",
+        })
+    }
+}
+
 impl ByteLen for ConnectReason {
     fn byte_len(&self) -> usize {
         (*self as u8).byte_len()
@@ -264,6 +299,15 @@ impl TryDecode for ConnackRx {
 mod test {
     use super::*;
 
+    #[test]
+    fn reason_display() {
+        assert_eq!(ConnectReason::NotAuthorized.to_string(), "Not authorized");
+        assert_eq!(
+            ConnectReason::BadUthenticationMethod.to_string(),
+            "Bad authentication method"
+        );
+    }
+
     #[test]
     fn from_bytes_0() {
         const PACKET: [u8; 5] = [
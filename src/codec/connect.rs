@@ -2,7 +2,7 @@ use bytes::BytesMut;
 
 use crate::core::{
     base_types::*,
-    error::{CodecError, UnexpectedProperty},
+    error::{CodecError, MissingDependentProperty, MissingMandatoryField},
     properties::*,
     utils::{ByteLen, Encode, Encoder, PacketID, SizedPacket},
 };
@@ -71,11 +71,40 @@ pub(crate) struct ConnectTx<'a> {
 
 impl<'a> ConnectTxBuilder<'a> {
     fn validate(&self) -> Result<(), CodecError> {
-        if self.authentication_method.is_none() && self.authentication_data.is_some() {
-            Err(UnexpectedProperty.into()) // Cannot include authentication data when authentication method is absent.
-        } else {
-            Ok(())
+        if self.authentication_data_without_method() {
+            return Err(MissingDependentProperty.into()); // Cannot include authentication data when authentication method is absent.
+        }
+
+        if self.will_without_topic() {
+            return Err(MissingMandatoryField.into()); // Will Message requires Will Topic.
         }
+
+        Ok(())
+    }
+
+    pub(crate) fn authentication_data_without_method(&self) -> bool {
+        self.authentication_method.is_none() && self.authentication_data.is_some()
+    }
+
+    pub(crate) fn will_without_topic(&self) -> bool {
+        self.has_will_properties() && self.will_topic.is_none()
+    }
+
+    pub(crate) fn has_will(&self) -> bool {
+        self.will_topic.is_some() || self.has_will_properties()
+    }
+
+    fn has_will_properties(&self) -> bool {
+        self.will_payload.is_some()
+            || self.will_qos.is_some()
+            || self.will_retain.is_some()
+            || self.will_delay_interval.is_some()
+            || self.will_payload_format_indicator.is_some()
+            || self.will_message_expiry_interval.is_some()
+            || self.will_content_type.is_some()
+            || self.will_response_topic.is_some()
+            || self.will_correlation_data.is_some()
+            || self.will_user_property.is_some()
     }
 
     pub(crate) fn user_property(&mut self, value: UserPropertyRef<'a>) {
@@ -409,4 +438,31 @@ mod test {
 
         assert_eq!(&buf.split().freeze()[..], &EXPECTED[..]);
     }
+
+    #[test]
+    fn authentication_data_without_method() {
+        let mut builder = ConnectTxBuilder::default();
+        builder.client_identifier(UTF8StringRef("test123"));
+        builder.authentication_data(AuthenticationDataRef::from(BinaryRef(b"data")));
+
+        assert!(matches!(
+            builder.build(),
+            Err(CodecError::MissingDependentProperty(_))
+        ));
+    }
+
+    #[test]
+    fn will_without_topic() {
+        let mut builder = ConnectTxBuilder::default();
+        builder.client_identifier(UTF8StringRef("test123"));
+        builder.will_payload(BinaryRef(b"will"));
+
+        assert!(matches!(
+            builder.build(),
+            Err(CodecError::MissingMandatoryField(_))
+        ));
+
+        builder.will_topic(UTF8StringRef("topic"));
+        assert!(builder.build().is_ok());
+    }
 }
@@ -9,13 +9,18 @@ use crate::core::{
     utils::{ByteLen, Decoder, Encode, Encoder, PacketID, SizedPacket, TryDecode},
 };
 use bytes::{Bytes, BytesMut};
-use core::mem;
+use core::{fmt, mem};
 use derive_builder::Builder;
 
 /// Reason for DISCONNECT packet.
 ///
 #[allow(missing_docs)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum DisconnectReason {
     Success = 0x00,
     DisconnectWithWillMessage = 0x04,
@@ -48,6 +53,15 @@ pub enum DisconnectReason {
     WildcardSubscriptionsNotSupported = 0xa2,
 }
 
+impl DisconnectReason {
+    /// Checks whether the reason asks the client to connect to another server,
+    /// i.e. is [UseAnotherServer](DisconnectReason::UseAnotherServer) or [ServerMoved](DisconnectReason::ServerMoved).
+    ///
+    pub fn is_redirect(&self) -> bool {
+        matches!(self, Self::UseAnotherServer | Self::ServerMoved)
+    }
+}
+
 impl TryFrom<u8> for DisconnectReason {
     type Error = ConversionError;
 
@@ -87,12 +101,49 @@ impl TryFrom<u8> for DisconnectReason {
     }
 }
 
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Success => "Normal disconnection",
+            Self::DisconnectWithWillMessage => "Disconnect with Will Message",
+            Self::UnspecifiedError => "Unspecified error",
+            Self::MalformedPacket => "Malformed Packet",
+            Self::ProtocolError => "Protocol Error",
+            Self::ImplementationSpecificError => "Implementation specific error",
+            Self::NotAuthorized => "Not authorized",
+            Self::ServerBusy => "Server busy",
+            Self::ServerShuttingDown => "Server shutting down",
+            Self::KeepAliveTimeout => "Keep Alive timeout",
+            Self::SessionTakenOver => "Session taken over",
+            Self::TopicFilterInvalid => "Topic Filter invalid",
+            Self::TopicNameInvalid => "Topic Name invalid",
+            Self::ReceiveMaximumExcceeded => "Receive Maximum exceeded",
+            Self::TopicAliasInvalid => "Topic Alias invalid",
+            Self::PacketTooLarge => "Packet too large",
+            Self::MessageRateTooHigh => "Message rate too high",
+            Self::QuotaExceeded => "Quota exceeded",
+            Self::AdministrativeAction => "Administrative action",
+            Self::PayloadFormatInvalid => "Payload format invalid",
+            Self::RetainNotSupported => "Retain not supported",
+            Self::QoSNotSupported => "QoS not supported",
+            Self::UseAnotherServer => "Use another server",
+            Self::ServerMoved => "Server moved",
+            Self::SharedSubscriptionsNotSupported => "Shared Subscriptions not supported",
+            Self::ConnectionRateExceeded => "Connection rate exceeded",
+            Self::MaximumConnectTime => "Maximum connect time",
+            Self::SubscriptionIdentifiersNotSupported => "Subscription Identifiers not supported",
+            Self::WildcardSubscriptionsNotSupported => "Wildcard Subscriptions not supported",
+        })
+    }
+}
+
 impl ByteLen for DisconnectReason {
     fn byte_len(&self) -> usize {
         mem::size_of::<u8>()
     }
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for DisconnectReason {
     fn default() -> Self {
         Self::Success
@@ -341,6 +392,25 @@ mod test {
         b'l',
     ];
 
+    #[test]
+    fn reason_display() {
+        assert_eq!(
+            DisconnectReason::Success.to_string(),
+            "Normal disconnection"
+        );
+        assert_eq!(
+            format!("{}", DisconnectReason::KeepAliveTimeout),
+            "Keep Alive timeout"
+        );
+    }
+
+    #[test]
+    fn is_redirect() {
+        assert!(DisconnectReason::UseAnotherServer.is_redirect());
+        assert!(DisconnectReason::ServerMoved.is_redirect());
+        assert!(!DisconnectReason::ServerShuttingDown.is_redirect());
+    }
+
     #[test]
     fn from_bytes_0() {
         let packet = DisconnectRx::try_decode(Bytes::from_static(&PACKET)).unwrap();
@@ -369,4 +439,15 @@ mod test {
 
         assert_eq!(&buf.split().freeze()[..], &PACKET);
     }
+
+    #[test]
+    fn from_bytes_session_expiry_interval() {
+        // The server must not send the session expiry interval in DISCONNECT.
+        const PACKET: [u8; 9] = [0xe0, 7, 0x00, 5, 0x11, 0, 0, 0, 60];
+
+        assert!(matches!(
+            DisconnectRx::try_decode(Bytes::from_static(&PACKET)),
+            Err(CodecError::UnexpectedProperty(_))
+        ));
+    }
 }
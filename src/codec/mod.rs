@@ -18,6 +18,7 @@ mod subscribe;
 mod unsubscribe;
 
 mod packet;
+pub(crate) mod v311;
 
 pub(crate) use ack::{AckRx, AckTx, AckTxBuilder, FixedHeader};
 
@@ -30,7 +31,7 @@ pub(crate) use pubcomp::{PubcompRx, PubcompTx};
 pub(crate) use pubrec::{PubrecRx, PubrecTx};
 pub(crate) use pubrel::{PubrelRx, PubrelTx, PubrelTxBuilder};
 
-pub(crate) use publish::{PublishRx, PublishTx, PublishTxBuilder};
+pub(crate) use publish::{PublishRx, PublishTx, PublishTxBuilder, PublishTxRefs};
 
 pub(crate) use subscribe::{SubscribeTx, SubscribeTxBuilder, SubscriptionOptions};
 pub(crate) use unsubscribe::{UnsubscribeTx, UnsubscribeTxBuilder};
@@ -43,6 +44,8 @@ pub(crate) use unsuback::UnsubackRx;
 
 pub(crate) use packet::{RxPacket, TxPacket};
 
+pub use packet::packet_type_name;
+
 pub use auth::AuthReason;
 pub use connack::ConnectReason;
 pub use disconnect::DisconnectReason;
@@ -53,3 +56,4 @@ pub use pubrel::PubrelReason;
 pub use suback::SubackReason;
 pub use subscribe::RetainHandling;
 pub use unsuback::UnsubackReason;
+pub use v311::MqttVersion;
@@ -1,7 +1,7 @@
 use crate::{
     codec::*,
     core::{
-        error::{CodecError, InvalidPacketHeader},
+        error::{CodecError, InvalidPacketHeader, InvalidPacketSize},
         utils::{Encode, PacketID, SizedPacket, TryDecode},
     },
 };
@@ -21,6 +21,54 @@ pub(crate) enum RxPacket {
     Auth(AuthRx),
 }
 
+impl RxPacket {
+    pub(crate) fn packet_type(&self) -> &'static str {
+        packet_type_name(match self {
+            RxPacket::Connack(_) => ConnackRx::PACKET_ID,
+            RxPacket::Publish(_) => PublishRx::PACKET_ID,
+            RxPacket::Puback(_) => PubackRx::PACKET_ID,
+            RxPacket::Pubrec(_) => PubrecRx::PACKET_ID,
+            RxPacket::Pubrel(_) => PubrelRx::PACKET_ID,
+            RxPacket::Pubcomp(_) => PubcompRx::PACKET_ID,
+            RxPacket::Suback(_) => SubackRx::PACKET_ID,
+            RxPacket::Unsuback(_) => UnsubackRx::PACKET_ID,
+            RxPacket::Pingresp(_) => PingrespRx::PACKET_ID,
+            RxPacket::Disconnect(_) => DisconnectRx::PACKET_ID,
+            RxPacket::Auth(_) => AuthRx::PACKET_ID,
+        })
+    }
+}
+
+/// Maps the MQTT control packet type (the four most significant bits of the fixed header)
+/// to the packet name, e.g. `3` to `"PUBLISH"`. Returns `"UNKNOWN"` for invalid values.
+///
+/// # Example
+/// ```
+/// let fixed_hdr = 0x82u8;
+/// assert_eq!(poster::packet_type_name(fixed_hdr >> 4), "SUBSCRIBE");
+/// ```
+///
+pub fn packet_type_name(packet_id: u8) -> &'static str {
+    match packet_id {
+        1 => "CONNECT",
+        2 => "CONNACK",
+        3 => "PUBLISH",
+        4 => "PUBACK",
+        5 => "PUBREC",
+        6 => "PUBREL",
+        7 => "PUBCOMP",
+        8 => "SUBSCRIBE",
+        9 => "SUBACK",
+        10 => "UNSUBSCRIBE",
+        11 => "UNSUBACK",
+        12 => "PINGREQ",
+        13 => "PINGRESP",
+        14 => "DISCONNECT",
+        15 => "AUTH",
+        _ => "UNKNOWN",
+    }
+}
+
 impl TryDecode for RxPacket {
     type Error = CodecError;
 
@@ -28,7 +76,9 @@ impl TryDecode for RxPacket {
     where
         Self: Sized,
     {
-        match bytes[0] >> 4 {
+        let fixed_hdr = bytes.first().copied().ok_or(InvalidPacketSize)?;
+
+        match fixed_hdr >> 4 {
             ConnackRx::PACKET_ID => ConnackRx::try_decode(bytes).map(RxPacket::Connack),
             PublishRx::PACKET_ID => PublishRx::try_decode(bytes).map(RxPacket::Publish),
             PubackRx::PACKET_ID => PubackRx::try_decode(bytes).map(RxPacket::Puback),
@@ -94,3 +144,37 @@ impl<'a> Encode for TxPacket<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        assert!(RxPacket::try_decode(Bytes::new()).is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        const INPUT: [&[u8]; 5] = [
+            &[
+                0x20, 16, 0, 0x9d, 13, 0x1c, 0, 10, b'o', b't', b'h', b'e', b'r', b':', b'1', b'8',
+                b'8', b'3',
+            ],
+            &[
+                0x40, 13, 0, 1, 0x87, 9, 0x1f, 0, 6, b'd', b'e', b'n', b'i', b'e', b'd',
+            ],
+            &[0x90, 4, 0, 1, 0, 0x00],
+            &[0xb0, 4, 0, 1, 0, 0x00],
+            &[0xe0, 9, 0x8b, 7, 0x1f, 0, 4, b'b', b'y', b'e', b'!'],
+        ];
+
+        for packet in INPUT {
+            assert!(RxPacket::try_decode(Bytes::copy_from_slice(packet)).is_ok());
+
+            for len in 0..packet.len() {
+                assert!(RxPacket::try_decode(Bytes::copy_from_slice(&packet[..len])).is_err());
+            }
+        }
+    }
+}
@@ -7,12 +7,17 @@ use crate::{
         utils::{ByteLen, Encode, PacketID, TryDecode},
     },
 };
-use core::mem;
+use core::{fmt, mem};
 
 /// Reason for PUBACK packet.
 ///
 #[allow(missing_docs)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum PubackReason {
     Success = 0x00,
     NoMatchingSubscribers = 0x10,
@@ -44,12 +49,29 @@ impl TryFrom<u8> for PubackReason {
     }
 }
 
+impl fmt::Display for PubackReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Success => "Success",
+            Self::NoMatchingSubscribers => "No matching subscribers",
+            Self::UnspecifiedError => "Unspecified error",
+            Self::ImplementationSpecificError => "Implementation specific error",
+            Self::NotAuthorized => "Not authorized",
+            Self::TopicNameInvalid => "Topic Name invalid",
+            Self::PacketIdentifierInUse => "Packet Identifier in use",
+            Self::QuotaExceeded => "Quota exceeded",
+            Self::PayloadFormatInvalid => "Payload format invalid",
+        })
+    }
+}
+
 impl ByteLen for PubackReason {
     fn byte_len(&self) -> usize {
         mem::size_of::<u8>()
     }
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for PubackReason {
     fn default() -> Self {
         Self::Success
@@ -6,12 +6,17 @@ use crate::{
     },
 };
 use bytes::{Bytes, BytesMut};
-use core::mem;
+use core::{fmt, mem};
 
 /// Reason for PUBCOMP packet.
 ///
 #[allow(missing_docs)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum PubcompReason {
     Success = 0x00,
     PacketIdentifierNotFound = 0x92,
@@ -29,12 +34,22 @@ impl TryFrom<u8> for PubcompReason {
     }
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for PubcompReason {
     fn default() -> Self {
         Self::Success
     }
 }
 
+impl fmt::Display for PubcompReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Success => "Success",
+            Self::PacketIdentifierNotFound => "Packet Identifier not found",
+        })
+    }
+}
+
 impl ByteLen for PubcompReason {
     fn byte_len(&self) -> usize {
         mem::size_of::<u8>()
@@ -12,7 +12,7 @@ use bytes::{Bytes, BytesMut};
 use core::mem;
 use derive_builder::Builder;
 
-#[derive(Builder)]
+#[derive(Builder, Clone)]
 #[builder(build_fn(error = "CodecError", validate = "Self::validate"))]
 pub(crate) struct PublishRx {
     #[builder(default)]
@@ -32,8 +32,8 @@ pub(crate) struct PublishRx {
     pub(crate) topic_alias: Option<TopicAlias>,
     #[builder(setter(strip_option), default)]
     pub(crate) message_expiry_interval: Option<MessageExpiryInterval>,
-    #[builder(setter(strip_option), default)]
-    pub(crate) subscription_identifier: Option<SubscriptionIdentifier>,
+    #[builder(setter(custom), default)]
+    pub(crate) subscription_identifiers: Vec<SubscriptionIdentifier>,
     #[builder(setter(strip_option), default)]
     pub(crate) correlation_data: Option<CorrelationData>,
     #[builder(setter(strip_option), default)]
@@ -58,6 +58,12 @@ impl PublishRxBuilder {
         }
     }
 
+    fn subscription_identifier(&mut self, value: SubscriptionIdentifier) {
+        self.subscription_identifiers
+            .get_or_insert_with(Vec::new)
+            .push(value);
+    }
+
     fn user_property(&mut self, value: UserProperty) {
         match self.user_property.as_mut() {
             Some(user_property) => {
@@ -158,7 +164,12 @@ impl TryDecode for PublishRx {
 }
 
 #[derive(Builder)]
-#[builder(build_fn(error = "CodecError", validate = "Self::validate"))]
+#[builder(build_fn(
+    private,
+    name = "build_uncached",
+    error = "CodecError",
+    validate = "Self::validate"
+))]
 pub(crate) struct PublishTx<'a> {
     #[builder(default)]
     pub(crate) dup: bool,
@@ -188,10 +199,79 @@ pub(crate) struct PublishTx<'a> {
     #[builder(setter(custom), default)]
     pub(crate) user_property: Vec<UserPropertyRef<'a>>,
     #[builder(setter(strip_option), default)]
-    pub(crate) payload: Option<PayloadRef<'a>>,
+    pub(crate) payload: Option<PayloadSource<'a>>,
+
+    // Computed once in PublishTxBuilder::build, as the encoding needs them more than once.
+    #[builder(setter(skip))]
+    property_len: VarSizeInt,
+    #[builder(setter(skip))]
+    remaining_len: VarSizeInt,
+}
+
+/// Borrowed fields of the [PublishTxBuilder], see [split_borrowed](PublishTxBuilder::split_borrowed).
+///
+#[derive(Default)]
+pub(crate) struct PublishTxRefs<'a> {
+    pub(crate) topic_name: Option<&'a str>,
+    pub(crate) correlation_data: Option<&'a [u8]>,
+    pub(crate) response_topic: Option<&'a str>,
+    pub(crate) content_type: Option<&'a str>,
+    pub(crate) user_property: Vec<(&'a str, &'a str)>,
+    pub(crate) payload: Option<PayloadSource<'a>>,
 }
 
 impl<'a> PublishTxBuilder<'a> {
+    /// Splits the builder into the one holding only the fields not borrowing any data
+    /// and the borrowed fields.
+    ///
+    pub(crate) fn split_borrowed(&self) -> (PublishTxBuilder<'static>, PublishTxRefs<'a>) {
+        let builder = PublishTxBuilder {
+            dup: self.dup,
+            retain: self.retain,
+            qos: self.qos,
+            packet_identifier: self.packet_identifier,
+            payload_format_indicator: self.payload_format_indicator,
+            topic_alias: self.topic_alias,
+            message_expiry_interval: self.message_expiry_interval,
+            ..Default::default()
+        };
+
+        let refs = PublishTxRefs {
+            topic_name: self.topic_name.map(|val| val.0),
+            correlation_data: self
+                .correlation_data
+                .flatten()
+                .map(|val| BinaryRef::from(val).0),
+            response_topic: self
+                .response_topic
+                .flatten()
+                .map(|val| UTF8StringRef::from(val).0),
+            content_type: self
+                .content_type
+                .flatten()
+                .map(|val| UTF8StringRef::from(val).0),
+            user_property: self
+                .user_property
+                .iter()
+                .flatten()
+                .map(|&val| UTF8StringPairRef::from(val))
+                .map(|val| (val.0, val.1))
+                .collect(),
+            payload: self.payload.clone().flatten(),
+        };
+
+        (builder, refs)
+    }
+
+    /// Builds the packet, calculating its property and remaining length up front.
+    ///
+    pub(crate) fn build(&self) -> Result<PublishTx<'a>, CodecError> {
+        let mut packet = self.build_uncached()?;
+        packet.property_len = packet.calc_property_len();
+        packet.remaining_len = packet.calc_remaining_len();
+        Ok(packet)
+    }
+
     fn validate(&self) -> Result<(), CodecError> {
         match self.qos.unwrap_or_default() {
             QoS::AtMostOnce => Ok(()),
@@ -213,6 +293,21 @@ impl<'a> PublishTxBuilder<'a> {
             }
         }
     }
+
+    // Fields not set yet are substituted with placeholders of the same encoded size,
+    // i.e. an empty topic name and a packet identifier when QoS>0.
+    pub(crate) fn packet_len(&self) -> usize {
+        let mut builder = self.clone();
+        builder.topic_name.get_or_insert(UTF8StringRef(""));
+
+        if builder.qos.unwrap_or_default() != QoS::AtMostOnce {
+            builder
+                .packet_identifier
+                .get_or_insert(Some(NonZero::try_from(1).unwrap()));
+        }
+
+        builder.build().map(|packet| packet.packet_len()).unwrap()
+    }
 }
 
 impl<'a> PublishTx<'a> {
@@ -223,7 +318,7 @@ impl<'a> PublishTx<'a> {
             | (self.retain as u8)
     }
 
-    fn property_len(&self) -> VarSizeInt {
+    fn calc_property_len(&self) -> VarSizeInt {
         VarSizeInt::try_from(
             self.payload_format_indicator
                 .as_ref()
@@ -268,8 +363,8 @@ impl<'a> PublishTx<'a> {
         .unwrap()
     }
 
-    fn remaining_len(&self) -> VarSizeInt {
-        let property_len = self.property_len();
+    fn calc_remaining_len(&self) -> VarSizeInt {
+        let property_len = self.property_len;
         VarSizeInt::try_from(
             self.topic_name.byte_len()
                 + self
@@ -291,10 +386,9 @@ impl<'a> PacketID for PublishTx<'a> {
 
 impl<'a> SizedPacket for PublishTx<'a> {
     fn packet_len(&self) -> usize {
-        let remaining_len = self.remaining_len();
         mem::size_of::<u8>() // Fixed header size
-            + remaining_len.len()
-            + remaining_len.value() as usize
+            + self.remaining_len.len()
+            + self.remaining_len.value() as usize
     }
 }
 
@@ -304,8 +398,7 @@ impl<'a> Encode for PublishTx<'a> {
 
         encoder.encode(self.fixed_hdr());
 
-        let remaining_len = self.remaining_len();
-        encoder.encode(remaining_len);
+        encoder.encode(self.remaining_len);
 
         encoder.encode(self.topic_name);
 
@@ -313,7 +406,7 @@ impl<'a> Encode for PublishTx<'a> {
             encoder.encode(val);
         }
 
-        encoder.encode(self.property_len());
+        encoder.encode(self.property_len);
 
         if let Some(val) = self.payload_format_indicator {
             encoder.encode(val);
@@ -347,7 +440,7 @@ impl<'a> Encode for PublishTx<'a> {
             encoder.encode(val);
         }
 
-        if let Some(payload) = self.payload {
+        if let Some(payload) = self.payload.clone() {
             encoder.encode(payload);
         }
     }
@@ -390,7 +483,7 @@ mod test {
         builder.retain(true);
         builder.packet_identifier(NonZero::try_from(13).unwrap());
         builder.topic_name(UTF8StringRef("test"));
-        builder.payload(PayloadRef(&[b't', b'e', b's', b't']));
+        builder.payload(PayloadSource::Borrowed(PayloadRef(b"test")));
 
         let packet = builder.build().unwrap();
         let mut buf = BytesMut::new();
@@ -398,4 +491,52 @@ mod test {
 
         assert_eq!(&buf.split().freeze()[..], &PACKET);
     }
+
+    #[test]
+    fn from_bytes_subscription_identifiers() {
+        const PACKET: [u8; 12] = [
+            PublishRx::PACKET_ID << 4,
+            10, // Remaining length
+            0,
+            1,    // Topic length
+            b'a', // Topic
+            4,    // Property length
+            0x0b, // Subscription identifier
+            1,
+            0x0b, // Subscription identifier
+            2,
+            // Payload
+            b'o',
+            b'k',
+        ];
+
+        let packet = PublishRx::try_decode(Bytes::from_static(&PACKET)).unwrap();
+        let identifiers: Vec<u32> = packet
+            .subscription_identifiers
+            .into_iter()
+            .map(|val| NonZero::from(val).get().value())
+            .collect();
+
+        assert_eq!(identifiers, [1, 2]);
+    }
+
+    #[test]
+    fn rebuild_recalculates_len() {
+        let mut builder = PublishTxBuilder::default();
+        builder.topic_name(UTF8StringRef("test"));
+
+        let packet = builder.build().unwrap();
+        let mut buf = BytesMut::new();
+        packet.encode(&mut buf);
+        assert_eq!(packet.packet_len(), buf.len());
+
+        builder.content_type(ContentTypeRef::from(UTF8StringRef("text")));
+        builder.payload(PayloadSource::Borrowed(PayloadRef(b"test")));
+
+        let packet = builder.build().unwrap();
+        let mut buf = BytesMut::new();
+        packet.encode(&mut buf);
+        assert_eq!(packet.packet_len(), buf.len());
+        assert_eq!(usize::from(buf[1]), buf.len() - 2); // Remaining length
+    }
 }
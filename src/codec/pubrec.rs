@@ -1,4 +1,5 @@
 use bytes::{Bytes, BytesMut};
+use core::fmt;
 
 use crate::{
     codec::ack::{AckRx, AckTx, AckTxBuilder, FixedHeader},
@@ -12,6 +13,11 @@ use crate::{
 ///
 #[allow(missing_docs)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum PubrecReason {
     Success = 0x00,
     NoMatchingSubscribers = 0x10,
@@ -43,12 +49,29 @@ impl TryFrom<u8> for PubrecReason {
     }
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for PubrecReason {
     fn default() -> Self {
         Self::Success
     }
 }
 
+impl fmt::Display for PubrecReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Success => "Success",
+            Self::NoMatchingSubscribers => "No matching subscribers",
+            Self::UnspecifiedError => "Unspecified error",
+            Self::ImplementationSpecificError => "Implementation specific error",
+            Self::NotAuthorized => "Not authorized",
+            Self::TopicNameInvalid => "Topic Name invalid",
+            Self::PacketIdentifierInUse => "Packet Identifier in use",
+            Self::QuotaExceeded => "Quota exceeded",
+            Self::PayloadFormatInvalid => "Payload format invalid",
+        })
+    }
+}
+
 impl ByteLen for PubrecReason {
     fn byte_len(&self) -> usize {
         (*self as u8).byte_len()
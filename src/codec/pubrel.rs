@@ -1,4 +1,5 @@
 use bytes::{Bytes, BytesMut};
+use core::fmt;
 
 use crate::{
     codec::ack::{AckRx, AckTx, AckTxBuilder, FixedHeader},
@@ -12,6 +13,11 @@ use crate::{
 ///
 #[allow(missing_docs)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum PubrelReason {
     Success = 0x00,
     PacketIdentifierNotFound = 0x92,
@@ -29,12 +35,22 @@ impl TryFrom<u8> for PubrelReason {
     }
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for PubrelReason {
     fn default() -> Self {
         Self::Success
     }
 }
 
+impl fmt::Display for PubrelReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Success => "Success",
+            Self::PacketIdentifierNotFound => "Packet Identifier not found",
+        })
+    }
+}
+
 impl ByteLen for PubrelReason {
     fn byte_len(&self) -> usize {
         (*self as u8).byte_len()
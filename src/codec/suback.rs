@@ -9,6 +9,7 @@ use crate::core::{
     utils::{ByteLen, Decoder, PacketID, TryDecode},
 };
 use bytes::Bytes;
+use core::fmt;
 
 use derive_builder::Builder;
 
@@ -16,6 +17,11 @@ use derive_builder::Builder;
 ///
 #[allow(missing_docs)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum SubackReason {
     GranteedQoS0 = 0x00,
     GranteedQoS1 = 0x01,
@@ -53,12 +59,51 @@ impl TryFrom<u8> for SubackReason {
     }
 }
 
+impl SubackReason {
+    /// Checks if the subscription was rejected, i.e. the reason code is greater or equal 0x80.
+    ///
+    pub fn is_error(&self) -> bool {
+        *self as u8 >= 0x80
+    }
+
+    /// Accesses the maximum [QoS] granted by the broker, or `None` if the subscription was rejected.
+    ///
+    pub fn granted_qos(&self) -> Option<QoS> {
+        match self {
+            Self::GranteedQoS0 => Some(QoS::AtMostOnce),
+            Self::GranteedQoS1 => Some(QoS::AtLeastOnce),
+            Self::GranteedQoS2 => Some(QoS::ExactlyOnce),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SubackReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::GranteedQoS0 => "Granted QoS 0",
+            Self::GranteedQoS1 => "Granted QoS 1",
+            Self::GranteedQoS2 => "Granted QoS 2",
+            Self::UnspecifiedError => "Unspecified error",
+            Self::ImplementationSpecificError => "Implementation specific error",
+            Self::NotAuthorized => "Not authorized",
+            Self::TopicFilterInvalid => "Topic Filter invalid",
+            Self::PacketIdentifierInUse => "Packet Identifier in use",
+            Self::QuotaExceeded => "Quota exceeded",
+            Self::SharedSubscriptionsNotSupported => "Shared Subscriptions not supported",
+            Self::SubscriptionIdentifiersNotSupported => "Subscription Identifiers not supported",
+            Self::WildcardSubscriptionsNotSupported => "Wildcard Subscriptions not supported",
+        })
+    }
+}
+
 impl ByteLen for SubackReason {
     fn byte_len(&self) -> usize {
         (*self as u8).byte_len()
     }
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for SubackReason {
     fn default() -> Self {
         Self::GranteedQoS0
@@ -184,6 +229,17 @@ impl TryDecode for SubackRx {
 #[cfg(test)]
 mod test {
     use super::*;
+
+    #[test]
+    fn reason_accessors() {
+        assert!(!SubackReason::GranteedQoS1.is_error());
+        assert_eq!(
+            SubackReason::GranteedQoS1.granted_qos(),
+            Some(QoS::AtLeastOnce)
+        );
+        assert!(SubackReason::NotAuthorized.is_error());
+        assert_eq!(SubackReason::NotAuthorized.granted_qos(), None);
+    }
     use crate::core::utils::PropertyID;
 
     #[test]
@@ -1,6 +1,6 @@
 use crate::core::{
     base_types::*,
-    error::{CodecError, MandatoryPropertyMissing},
+    error::{CodecError, EmptyPayload},
     properties::*,
     utils::{ByteLen, Encode, Encoder, PacketID, SizedPacket},
 };
@@ -55,9 +55,9 @@ impl Encode for SubscriptionOptions {
         let mut encoder = Encoder::from(buf);
         let qos = self.maximum_qos;
         let val = (qos as u8)
-            | ((self.no_local as u8) << 3)
-            | ((self.retain_as_published as u8) << 4)
-            | ((self.retain_handling as u8) << 5);
+            | ((self.no_local as u8) << 2)
+            | ((self.retain_as_published as u8) << 3)
+            | ((self.retain_handling as u8) << 4);
         encoder.encode(val);
     }
 }
@@ -79,7 +79,7 @@ pub(crate) struct SubscribeTx<'a> {
 impl<'a> SubscribeTxBuilder<'a> {
     fn validate(&self) -> Result<(), CodecError> {
         if self.payload.is_none() {
-            Err(MandatoryPropertyMissing.into()) // Empty payload is a protocol error
+            Err(EmptyPayload.into()) // Empty payload is a protocol error
         } else {
             Ok(())
         }
@@ -217,4 +217,11 @@ mod test {
 
         assert_eq!(&buf.split().freeze()[..], &EXPECTED);
     }
+
+    #[test]
+    fn empty_payload() {
+        let mut builder = SubscribeTxBuilder::default();
+        builder.packet_identifier(NonZero::try_from(32).unwrap());
+        assert!(matches!(builder.build(), Err(CodecError::EmptyPayload(_))));
+    }
 }
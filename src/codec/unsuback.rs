@@ -9,6 +9,7 @@ use crate::core::{
     utils::{ByteLen, Decoder, PacketID, TryDecode},
 };
 use bytes::Bytes;
+use core::fmt;
 
 use derive_builder::Builder;
 
@@ -16,6 +17,11 @@ use derive_builder::Builder;
 ///
 #[allow(missing_docs)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum UnsubackReason {
     Success = 0x00,
     NoSubscriptionExisted = 0x11,
@@ -43,12 +49,36 @@ impl TryFrom<u8> for UnsubackReason {
     }
 }
 
+impl UnsubackReason {
+    /// Checks if unsubscribing failed, i.e. the reason code is greater or equal 0x80.
+    /// Note that [NoSubscriptionExisted](UnsubackReason::NoSubscriptionExisted) is not an error.
+    ///
+    pub fn is_error(&self) -> bool {
+        *self as u8 >= 0x80
+    }
+}
+
+impl fmt::Display for UnsubackReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Success => "Success",
+            Self::NoSubscriptionExisted => "No subscription existed",
+            Self::UnspecifiedError => "Unspecified error",
+            Self::ImplementationSpecificError => "Implementation specific error",
+            Self::NotAuthorized => "Not authorized",
+            Self::TopicFilterInvalid => "Topic Filter invalid",
+            Self::PacketIdentifierInUse => "Packet Identifier in use",
+        })
+    }
+}
+
 impl ByteLen for UnsubackReason {
     fn byte_len(&self) -> usize {
         (*self as u8).byte_len()
     }
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for UnsubackReason {
     fn default() -> Self {
         Self::Success
@@ -173,6 +203,13 @@ mod test {
     use super::*;
     use crate::core::utils::PropertyID;
 
+    #[test]
+    fn reason_accessors() {
+        assert!(!UnsubackReason::Success.is_error());
+        assert!(!UnsubackReason::NoSubscriptionExisted.is_error());
+        assert!(UnsubackReason::TopicFilterInvalid.is_error());
+    }
+
     #[test]
     fn from_bytes_0() {
         const FIXED_HDR: u8 = UnsubackRx::PACKET_ID << 4;
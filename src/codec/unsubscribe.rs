@@ -1,6 +1,6 @@
 use crate::core::{
     base_types::*,
-    error::{CodecError, MandatoryPropertyMissing},
+    error::{CodecError, EmptyPayload},
     properties::*,
     utils::{ByteLen, Encode, Encoder, PacketID, SizedPacket},
 };
@@ -20,7 +20,7 @@ pub(crate) struct UnsubscribeTx<'a> {
 impl<'a> UnsubscribeTxBuilder<'a> {
     fn validate(&self) -> Result<(), CodecError> {
         if self.payload.is_none() {
-            Err(MandatoryPropertyMissing.into()) // Empty payload is a protocol error
+            Err(EmptyPayload.into()) // Empty payload is a protocol error
         } else {
             Ok(())
         }
@@ -0,0 +1,327 @@
+//! MQTT v3.1.1 wire format. Packets are encoded and decoded by the MQTT 5 codec and translated
+//! at the stream boundary: outgoing packets are stripped of the MQTT 5 only fields, incoming
+//! ones are extended with the empty property sections expected by the MQTT 5 decoders.
+
+use crate::core::{
+    base_types::VarSizeInt,
+    error::{
+        CodecError, ConversionError, InvalidPacketHeader, InvalidPacketSize, InvalidValue,
+        MissingMandatoryField,
+    },
+    utils::Encode,
+};
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// Version of the MQTT protocol spoken with the broker,
+/// see [protocol_version](crate::ConnectOpts::protocol_version).
+///
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MqttVersion {
+    /// MQTT v3.1.1, for legacy brokers. MQTT 5 only features are not sent over the wire.
+    ///
+    V311,
+
+    /// MQTT v5.0.
+    ///
+    #[default]
+    V500,
+}
+
+impl MqttVersion {
+    /// Protocol level byte of the CONNECT packet.
+    ///
+    pub(crate) fn protocol_level(&self) -> u8 {
+        match self {
+            Self::V311 => 4,
+            Self::V500 => 5,
+        }
+    }
+}
+
+const EMPTY_PROPERTIES: [u8; 1] = [0];
+
+/// Splits the packet into the fixed header and the bytes following the remaining length.
+///
+fn split(packet: &[u8]) -> Result<(u8, &[u8]), CodecError> {
+    let fixed_hdr = packet.first().copied().ok_or(InvalidPacketSize)?;
+    let (remaining_len, consumed) = VarSizeInt::decode_from_slice(&packet[1..])?;
+    let body = packet
+        .get(1 + consumed..)
+        .filter(|body| body.len() == remaining_len.value() as usize)
+        .ok_or(InvalidPacketSize)?;
+    Ok((fixed_hdr, body))
+}
+
+/// Splits the bytes at `mid`, failing when there are not enough of them.
+///
+fn split_at(body: &[u8], mid: usize) -> Result<(&[u8], &[u8]), CodecError> {
+    if body.len() < mid {
+        return Err(InvalidPacketSize.into());
+    }
+
+    Ok(body.split_at(mid))
+}
+
+/// Length of the string or binary data at the beginning of the bytes, including its 2 byte length.
+///
+fn prefixed_len(body: &[u8]) -> Result<usize, CodecError> {
+    match body {
+        [msb, lsb, ..] => Ok(2 + usize::from(u16::from_be_bytes([*msb, *lsb]))),
+        _ => Err(InvalidPacketSize.into()),
+    }
+}
+
+/// Skips the property section at the beginning of the bytes.
+///
+fn skip_properties(body: &[u8]) -> Result<&[u8], CodecError> {
+    let (property_len, consumed) = VarSizeInt::decode_from_slice(body)?;
+    split_at(body, consumed + property_len.value() as usize).map(|(_, rest)| rest)
+}
+
+fn assemble(fixed_hdr: u8, parts: &[&[u8]]) -> Result<BytesMut, CodecError> {
+    let len = parts.iter().map(|part| part.len()).sum::<usize>();
+    let remaining_len = VarSizeInt::try_from(len)?;
+
+    let mut buf = BytesMut::with_capacity(1 + remaining_len.len() + len);
+    buf.put_u8(fixed_hdr);
+    remaining_len.encode(&mut buf);
+    for part in parts {
+        buf.put_slice(part);
+    }
+
+    Ok(buf)
+}
+
+/// Translates the encoded MQTT 5 packet to the MQTT v3.1.1 one, dropping the properties and
+/// the reason codes. AUTH packet has no MQTT v3.1.1 counterpart and is rejected.
+///
+pub(crate) fn downgrade(packet: &[u8]) -> Result<BytesMut, CodecError> {
+    let (fixed_hdr, body) = split(packet)?;
+
+    match fixed_hdr >> 4 {
+        // CONNECT
+        1 => {
+            let (var_hdr, rest) = split_at(body, prefixed_len(body)? + 4)?;
+            let mut var_hdr = var_hdr.to_vec();
+            let level = var_hdr.len() - 4;
+            var_hdr[level] = MqttVersion::V311.protocol_level();
+            let will = var_hdr[level + 1] & 0x04 != 0;
+
+            let rest = skip_properties(rest)?;
+            let (client_identifier, rest) = split_at(rest, prefixed_len(rest)?)?;
+            let rest = if will { skip_properties(rest)? } else { rest };
+
+            assemble(fixed_hdr, &[&var_hdr, client_identifier, rest])
+        }
+        // PUBLISH
+        3 => {
+            let topic_len = prefixed_len(body)?;
+            if topic_len == 2 {
+                // Topic aliases are not supported, the topic name cannot be empty.
+                return Err(MissingMandatoryField.into());
+            }
+
+            let packet_id_len = if (fixed_hdr >> 1) & 0x03 != 0 { 2 } else { 0 };
+            let (var_hdr, rest) = split_at(body, topic_len + packet_id_len)?;
+            assemble(fixed_hdr, &[var_hdr, skip_properties(rest)?])
+        }
+        // PUBACK, PUBREC, PUBREL, PUBCOMP
+        4..=7 => assemble(fixed_hdr, &[split_at(body, 2)?.0]),
+        // SUBSCRIBE
+        8 => {
+            let (packet_id, mut rest) = split_at(body, 2)?;
+            rest = skip_properties(rest)?;
+
+            // Only the maximum QoS is kept from the subscription options.
+            let mut payload = Vec::with_capacity(rest.len());
+            while !rest.is_empty() {
+                let (topic, tail) = split_at(rest, prefixed_len(rest)?)?;
+                let (opts, tail) = split_at(tail, 1)?;
+                payload.extend_from_slice(topic);
+                payload.push(opts[0] & 0x03);
+                rest = tail;
+            }
+
+            assemble(fixed_hdr, &[packet_id, &payload])
+        }
+        // UNSUBSCRIBE
+        10 => {
+            let (packet_id, rest) = split_at(body, 2)?;
+            assemble(fixed_hdr, &[packet_id, skip_properties(rest)?])
+        }
+        // DISCONNECT
+        14 => assemble(fixed_hdr, &[]),
+        // AUTH
+        15 => Err(InvalidPacketHeader.into()),
+        _ => Ok(BytesMut::from(packet)),
+    }
+}
+
+/// Translates the received MQTT v3.1.1 packet to the MQTT 5 one, inserting the empty
+/// property sections and mapping the CONNACK return codes to the reason codes.
+///
+pub(crate) fn upgrade(packet: Bytes) -> Result<Bytes, CodecError> {
+    let (fixed_hdr, body) = split(&packet)?;
+
+    let upgraded = match fixed_hdr >> 4 {
+        // CONNACK
+        2 => {
+            let (flags, return_code) = match body {
+                [flags, return_code] => (*flags, *return_code),
+                _ => return Err(InvalidPacketSize.into()),
+            };
+
+            let reason = match return_code {
+                0x00 => 0x00, // Success
+                0x01 => 0x84, // Unsupported Protocol Version
+                0x02 => 0x85, // Client Identifier not valid
+                0x03 => 0x88, // Server unavailable
+                0x04 => 0x86, // Bad User Name or Password
+                0x05 => 0x87, // Not authorized
+                _ => return Err(ConversionError::from(InvalidValue).into()),
+            };
+
+            assemble(fixed_hdr, &[&[flags, reason], &EMPTY_PROPERTIES])?
+        }
+        // PUBLISH
+        3 => {
+            let packet_id_len = if (fixed_hdr >> 1) & 0x03 != 0 { 2 } else { 0 };
+            let (var_hdr, payload) = split_at(body, prefixed_len(body)? + packet_id_len)?;
+            assemble(fixed_hdr, &[var_hdr, &EMPTY_PROPERTIES, payload])?
+        }
+        // SUBACK
+        9 => {
+            let (packet_id, payload) = split_at(body, 2)?;
+            assemble(fixed_hdr, &[packet_id, &EMPTY_PROPERTIES, payload])?
+        }
+        // UNSUBACK, carrying no reason codes in MQTT v3.1.1.
+        11 => assemble(fixed_hdr, &[split_at(body, 2)?.0, &EMPTY_PROPERTIES])?,
+        _ => return Ok(packet),
+    };
+
+    Ok(upgraded.freeze())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn downgrade_connect() {
+        const INPUT: [u8; 31] = [
+            0x10, 29, // Fixed header, remaining length
+            0, 4, b'M', b'Q', b'T', b'T', // Protocol name
+            5,    // Protocol level
+            0x06, // Flags (will, clean start)
+            0, 60, // Keep alive
+            5,  // Property length
+            0x11, 0, 0, 0, 10, // Session expiry interval
+            0, 1, b'c', // Client identifier
+            2,    // Will property length
+            0x01, 1, // Payload format indicator
+            0, 1, b't', // Will topic
+            0, 2, b'w', b'p', // Will payload
+        ];
+
+        let expected: &[u8] = &[
+            0x10, 20, 0, 4, b'M', b'Q', b'T', b'T', 4, 0x06, 0, 60, 0, 1, b'c', 0, 1, b't', 0, 2,
+            b'w', b'p',
+        ];
+
+        assert_eq!(downgrade(&INPUT).unwrap().as_ref(), expected);
+    }
+
+    #[test]
+    fn downgrade_publish() {
+        const INPUT: [u8; 12] = [
+            0x32, 10, // Fixed header (QoS 1), remaining length
+            0, 1, b'a', // Topic name
+            0, 7, // Packet identifier
+            3, // Property length
+            0x23, 0, 1,    // Topic alias
+            b'x', // Payload
+        ];
+
+        assert_eq!(
+            downgrade(&INPUT).unwrap().as_ref(),
+            [0x32, 6, 0, 1, b'a', 0, 7, b'x']
+        );
+
+        // Topic alias without the topic name cannot be expressed.
+        assert!(downgrade(&[0x30, 6, 0, 0, 3, 0x23, 0, 1]).is_err());
+    }
+
+    #[test]
+    fn downgrade_subscribe() {
+        const INPUT: [u8; 15] = [
+            0x82, 13, // Fixed header, remaining length
+            0, 1, // Packet identifier
+            2, 0x0b, 1, // Subscription identifier
+            0, 1, b'a', 0x2e, // Topic filter, options
+            0, 1, b'b', 0x01, // Topic filter, options
+        ];
+
+        assert_eq!(
+            downgrade(&INPUT).unwrap().as_ref(),
+            [0x82, 10, 0, 1, 0, 1, b'a', 0x02, 0, 1, b'b', 0x01]
+        );
+    }
+
+    #[test]
+    fn downgrade_others() {
+        // PUBACK with reason code and properties
+        assert_eq!(
+            downgrade(&[0x40, 4, 0, 1, 0x10, 0]).unwrap().as_ref(),
+            [0x40, 2, 0, 1]
+        );
+        // UNSUBSCRIBE
+        assert_eq!(
+            downgrade(&[0xa2, 6, 0, 1, 0, 0, 1, b'a']).unwrap().as_ref(),
+            [0xa2, 5, 0, 1, 0, 1, b'a']
+        );
+        // DISCONNECT
+        assert_eq!(downgrade(&[0xe0, 2, 0x04, 0]).unwrap().as_ref(), [0xe0, 0]);
+        // PINGREQ
+        assert_eq!(downgrade(&[0xc0, 0]).unwrap().as_ref(), [0xc0, 0]);
+        // AUTH
+        assert!(downgrade(&[0xf0, 0]).is_err());
+    }
+
+    #[test]
+    fn upgrade_packets() {
+        // CONNACK
+        assert_eq!(
+            upgrade(Bytes::from_static(&[0x20, 2, 1, 0])).unwrap(),
+            [0x20, 3, 1, 0, 0][..]
+        );
+        assert_eq!(
+            upgrade(Bytes::from_static(&[0x20, 2, 0, 5])).unwrap(),
+            [0x20, 3, 0, 0x87, 0][..]
+        );
+        assert!(upgrade(Bytes::from_static(&[0x20, 2, 0, 6])).is_err());
+        // PUBLISH
+        assert_eq!(
+            upgrade(Bytes::from_static(&[0x30, 4, 0, 1, b'a', b'x'])).unwrap(),
+            [0x30, 5, 0, 1, b'a', 0, b'x'][..]
+        );
+        assert_eq!(
+            upgrade(Bytes::from_static(&[0x34, 6, 0, 1, b'a', 0, 9, b'x'])).unwrap(),
+            [0x34, 7, 0, 1, b'a', 0, 9, 0, b'x'][..]
+        );
+        // SUBACK
+        assert_eq!(
+            upgrade(Bytes::from_static(&[0x90, 4, 0, 1, 0x01, 0x80])).unwrap(),
+            [0x90, 5, 0, 1, 0, 0x01, 0x80][..]
+        );
+        // UNSUBACK
+        assert_eq!(
+            upgrade(Bytes::from_static(&[0xb0, 2, 0, 1])).unwrap(),
+            [0xb0, 3, 0, 1, 0][..]
+        );
+        // PUBACK
+        assert_eq!(
+            upgrade(Bytes::from_static(&[0x40, 2, 0, 1])).unwrap(),
+            [0x40, 2, 0, 1][..]
+        );
+    }
+}
@@ -45,6 +45,56 @@ impl VarSizeInt {
             VarSizeIntState::FourByte(val) => val,
         }
     }
+
+    /// Decodes the value from the beginning of the slice, returning it together
+    /// with the number of bytes consumed. Trailing bytes are ignored.
+    ///
+    pub(crate) fn decode_from_slice(buf: &[u8]) -> Result<(Self, usize), ConversionError> {
+        Self::try_from(buf).map(|val| (val, val.len()))
+    }
+
+    /// Addition returning [None] when the result exceeds [VarSizeInt::MAX].
+    ///
+    pub(crate) fn checked_add(self, rhs: Self) -> Option<Self> {
+        Self::try_from(self.value() + rhs.value()).ok() // Operands are at most 28 bits wide.
+    }
+
+    /// Subtraction returning [None] on underflow.
+    ///
+    pub(crate) fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.value()
+            .checked_sub(rhs.value())
+            .and_then(|val| Self::try_from(val).ok())
+    }
+
+    /// Multiplication returning [None] when the result exceeds [VarSizeInt::MAX].
+    ///
+    pub(crate) fn checked_mul(self, rhs: Self) -> Option<Self> {
+        self.value()
+            .checked_mul(rhs.value())
+            .and_then(|val| Self::try_from(val).ok())
+    }
+
+    /// Division returning [None] when `rhs` is zero.
+    ///
+    pub(crate) fn checked_div(self, rhs: Self) -> Option<Self> {
+        self.value()
+            .checked_div(rhs.value())
+            .and_then(|val| Self::try_from(val).ok())
+    }
+
+    /// Addition clamping the result to [VarSizeInt::MAX].
+    ///
+    pub(crate) fn saturating_add(self, rhs: Self) -> Self {
+        self.checked_add(rhs)
+            .unwrap_or_else(|| Self::try_from(Self::MAX).unwrap())
+    }
+
+    /// Subtraction clamping the result to zero.
+    ///
+    pub(crate) fn saturating_sub(self, rhs: Self) -> Self {
+        self.checked_sub(rhs).unwrap_or_default()
+    }
 }
 
 impl TryFrom<&[u8]> for VarSizeInt {
@@ -55,12 +105,11 @@ impl TryFrom<&[u8]> for VarSizeInt {
         let mut val = 0u32;
 
         for (idx, &byte) in bytes.iter().enumerate() {
-            val += (byte as u32 & 127) * mult;
-
             if mult as usize > Self::MAX {
                 return Err(ValueExceedesMaximum.into());
             }
 
+            val += (byte as u32 & 127) * mult;
             mult *= 128;
 
             if byte & 128 == 0 {
@@ -270,28 +319,28 @@ impl PartialOrd<isize> for VarSizeInt {
 impl Add for VarSizeInt {
     type Output = Self;
     fn add(self, rhs: Self) -> Self::Output {
-        Self::try_from(self.value() + rhs.value()).unwrap()
+        self.checked_add(rhs).unwrap()
     }
 }
 
 impl Sub for VarSizeInt {
     type Output = Self;
     fn sub(self, rhs: Self) -> Self::Output {
-        Self::try_from(self.value() - rhs.value()).unwrap()
+        self.checked_sub(rhs).unwrap()
     }
 }
 
 impl Mul for VarSizeInt {
     type Output = Self;
     fn mul(self, rhs: Self) -> Self::Output {
-        Self::try_from(self.value() * rhs.value()).unwrap()
+        self.checked_mul(rhs).unwrap()
     }
 }
 
 impl Div for VarSizeInt {
     type Output = Self;
     fn div(self, rhs: Self) -> Self::Output {
-        Self::try_from(self.value() / rhs.value()).unwrap()
+        self.checked_div(rhs).unwrap()
     }
 }
 
@@ -435,10 +484,17 @@ impl Encode for u8 {
     }
 }
 
-/// Enum representing Quality Of Service
+/// Enum representing Quality Of Service.
+/// Variants are ordered by their numeric value, i.e. `AtMostOnce < AtLeastOnce < ExactlyOnce`.
 ///
 #[allow(clippy::enum_variant_names)]
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[repr(u8)]
 pub enum QoS {
     /// At most once QoS
     ///
@@ -466,6 +522,25 @@ impl TryFrom<u8> for QoS {
     }
 }
 
+impl From<QoS> for u8 {
+    fn from(val: QoS) -> Self {
+        val as u8
+    }
+}
+
+impl From<QoS> for u16 {
+    fn from(val: QoS) -> Self {
+        val as u16
+    }
+}
+
+impl From<QoS> for u32 {
+    fn from(val: QoS) -> Self {
+        val as u32
+    }
+}
+
+#[allow(clippy::derivable_impls)]
 impl Default for QoS {
     fn default() -> Self {
         QoS::AtMostOnce
@@ -533,6 +608,10 @@ impl TryDecode for u16 {
     type Error = ConversionError;
 
     fn try_decode(bytes: Bytes) -> Result<Self, Self::Error> {
+        if bytes.len() < mem::size_of::<u16>() {
+            return Err(InsufficientBufferSize.into());
+        }
+
         bytes
             .iter()
             .take(mem::size_of::<u16>())
@@ -564,6 +643,10 @@ impl TryDecode for u32 {
     type Error = ConversionError;
 
     fn try_decode(bytes: Bytes) -> Result<Self, Self::Error> {
+        if bytes.len() < mem::size_of::<u32>() {
+            return Err(InsufficientBufferSize.into());
+        }
+
         bytes
             .iter()
             .take(mem::size_of::<u32>())
@@ -838,6 +921,44 @@ impl<'a> Encode for PayloadRef<'a> {
     }
 }
 
+/// Outgoing payload, either borrowed or shared with the [Bytes] buffer it comes from.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum PayloadSource<'a> {
+    Borrowed(PayloadRef<'a>),
+    Shared(Payload),
+}
+
+impl<'a> PayloadSource<'a> {
+    pub(crate) fn into_bytes(self) -> Bytes {
+        match self {
+            Self::Borrowed(val) => Bytes::copy_from_slice(val.0),
+            Self::Shared(val) => val.0,
+        }
+    }
+}
+
+impl<'a> AsRef<[u8]> for PayloadSource<'a> {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            Self::Borrowed(val) => val.0,
+            Self::Shared(val) => val.0.as_ref(),
+        }
+    }
+}
+
+impl<'a> ByteLen for PayloadSource<'a> {
+    fn byte_len(&self) -> usize {
+        self.as_ref().len()
+    }
+}
+
+impl<'a> Encode for PayloadSource<'a> {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_slice(self.as_ref());
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct UTF8String(pub(crate) Bytes);
 
@@ -1245,6 +1366,12 @@ mod test {
             assert_eq!(result, EXPECTED_VALUE);
         }
 
+        #[test]
+        fn insufficient_buffer_size() {
+            assert!(u16::try_decode(Bytes::from_static(&[0x01])).is_err());
+            assert!(u32::try_decode(Bytes::from_static(&[0x01, 0x02, 0x03])).is_err());
+        }
+
         #[test]
         fn var_size_int() {
             const INPUT: [(&[u8], usize, u32); 4] = [
@@ -1263,13 +1390,35 @@ mod test {
             }
         }
 
+        #[test]
+        fn var_size_int_decode_from_slice() {
+            const INPUT: [(&[u8], usize, u32); 4] = [
+                (&[0x7f, 0xff], 1, 127),
+                (&[0xff, 0x7f, 0x00], 2, 16383),
+                (&[0xff, 0xff, 0x7f], 3, 2097151),
+                (&[0xff, 0xff, 0xff, 0x7f, 0x01], 4, 268435455),
+            ];
+
+            for (bytes, expected_consumed, expected_value) in INPUT {
+                let (result, consumed) = VarSizeInt::decode_from_slice(bytes).unwrap();
+                assert_eq!(consumed, expected_consumed);
+                assert_eq!(result.value(), expected_value);
+            }
+
+            assert!(matches!(
+                VarSizeInt::decode_from_slice(&[0xff, 0xff]),
+                Err(ConversionError::InsufficientBufferSize(_))
+            ));
+        }
+
         #[test]
         fn var_size_int_invalid() {
-            const INPUT: [&[u8]; 4] = [
+            const INPUT: [&[u8]; 5] = [
                 &[0xff],
                 &[0xff, 0xff],
                 &[0xff, 0xff, 0xff],
                 &[0xff, 0xff, 0xff, 0xff],
+                &[0xff, 0xff, 0xff, 0xff, 0xff],
             ];
 
             for bytes in INPUT {
@@ -1404,6 +1553,29 @@ mod test {
             }
         }
 
+        #[test]
+        fn var_size_int_checked_arithmetic() {
+            let max = VarSizeInt::try_from(VarSizeInt::MAX).unwrap();
+            let one = VarSizeInt::from(1u8);
+            let two = VarSizeInt::from(2u8);
+            let zero = VarSizeInt::default();
+
+            assert_eq!(one.checked_add(two), Some(VarSizeInt::from(3u8)));
+            assert_eq!(max.checked_add(one), None);
+            assert_eq!(two.checked_sub(one), Some(one));
+            assert_eq!(one.checked_sub(two), None);
+            assert_eq!(two.checked_mul(two), Some(VarSizeInt::from(4u8)));
+            assert_eq!(max.checked_mul(two), None);
+            assert_eq!(max.checked_mul(max), None); // Overflows u32
+            assert_eq!(two.checked_div(two), Some(one));
+            assert_eq!(two.checked_div(zero), None);
+
+            assert_eq!(max.saturating_add(max), max);
+            assert_eq!(one.saturating_add(one), two);
+            assert_eq!(one.saturating_sub(two), zero);
+            assert_eq!(two.saturating_sub(one), one);
+        }
+
         #[test]
         fn non_zero_from_0() {
             assert!(NonZero::<u8>::try_from(0).is_err());
@@ -1413,5 +1585,20 @@ mod test {
         fn non_zero_from_1() {
             assert!(NonZero::<u8>::try_from(1).is_ok());
         }
+
+        #[test]
+        fn qos_ordering() {
+            assert!(QoS::AtMostOnce < QoS::AtLeastOnce);
+            assert!(QoS::AtLeastOnce < QoS::ExactlyOnce);
+            assert_eq!(QoS::ExactlyOnce.min(QoS::AtLeastOnce), QoS::AtLeastOnce);
+        }
+
+        #[test]
+        fn qos_into_integer() {
+            assert_eq!(u8::from(QoS::AtMostOnce), 0);
+            assert_eq!(u16::from(QoS::AtLeastOnce), 1);
+            assert_eq!(u32::from(QoS::ExactlyOnce), 2);
+            assert!(u8::from(QoS::AtLeastOnce) <= u8::from(QoS::ExactlyOnce));
+        }
     }
 }
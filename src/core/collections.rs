@@ -1,4 +1,5 @@
 use crate::core::{base_types::UTF8StringPair, properties::UserProperty};
+use bytes::Bytes;
 use core::{fmt, str};
 
 /// Map collection for reading user properties as key-value pairs from packets.
@@ -51,6 +52,14 @@ impl UserProperties {
             .map(|pair| str::from_utf8(&pair.1).unwrap())
     }
 
+    /// Returns the first value under the given key.
+    pub fn get_first(&self, key: &str) -> Option<&str> {
+        self.map
+            .iter()
+            .find(|&pair| str::from_utf8(&pair.0).unwrap() == key)
+            .map(|pair| str::from_utf8(&pair.1).unwrap())
+    }
+
     /// Returns an iterator which iterates over the keys. Note that it can contain duplicates.
     pub fn keys(&self) -> impl Iterator<Item = &str> {
         self.map.iter().map(|pair| str::from_utf8(&pair.0).unwrap())
@@ -76,6 +85,33 @@ impl UserProperties {
     }
 }
 
+/// Builder of [UserProperties], e.g. for constructing the expected values in tests.
+#[derive(Default)]
+pub struct UserPropertiesBuilder {
+    map: Vec<UTF8StringPair>,
+}
+
+impl UserPropertiesBuilder {
+    /// Creates a new [UserPropertiesBuilder] instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the key-value pair. Multiple values may be added under the same key.
+    pub fn property(mut self, key: &str, val: &str) -> Self {
+        self.map.push(UTF8StringPair(
+            Bytes::copy_from_slice(key.as_bytes()),
+            Bytes::copy_from_slice(val.as_bytes()),
+        ));
+        self
+    }
+
+    /// Builds the [UserProperties].
+    pub fn build(self) -> UserProperties {
+        UserProperties::from(self.map)
+    }
+}
+
 impl fmt::Debug for UserProperties {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut dbg = f.debug_struct("UserProperties");
@@ -90,8 +126,6 @@ impl fmt::Debug for UserProperties {
 
 #[cfg(test)]
 mod test {
-    use bytes::Bytes;
-
     use super::*;
 
     #[test]
@@ -140,4 +174,21 @@ mod test {
             [("key0", "val0"), ("key1", "val1"), ("key1", "val2")]
         );
     }
+
+    #[test]
+    fn builder() {
+        let properties = UserPropertiesBuilder::new()
+            .property("key0", "val0")
+            .property("key1", "val1")
+            .property("key1", "val2")
+            .build();
+
+        assert_eq!(properties.len(), 3);
+        assert_eq!(properties.get_first("key1"), Some("val1"));
+        assert_eq!(properties.get_first("key2"), None);
+        assert_eq!(
+            properties.get("key1").collect::<Vec<&str>>(),
+            ["val1", "val2"]
+        );
+    }
 }
@@ -285,6 +285,59 @@ impl fmt::Display for MandatoryPropertyMissing {
 
 impl Error for MandatoryPropertyMissing {}
 
+/// Property is present in the packet without the property it depends on.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct MissingDependentProperty;
+
+impl fmt::Display for MissingDependentProperty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "missing dependent property")
+    }
+}
+
+impl Error for MissingDependentProperty {}
+
+/// Field required by other fields set in the packet is missing, e.g. will topic
+/// when will payload is set.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct MissingMandatoryField;
+
+impl fmt::Display for MissingMandatoryField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "missing mandatory field")
+    }
+}
+
+impl Error for MissingMandatoryField {}
+
+/// Packet which requires a non-empty payload has none, e.g. SUBSCRIBE without any topic filters.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct EmptyPayload;
+
+impl fmt::Display for EmptyPayload {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "empty payload")
+    }
+}
+
+impl Error for EmptyPayload {}
+
+/// Size of the incoming packet exceedes the maximum packet size declared by the client.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct PacketTooLarge;
+
+impl fmt::Display for PacketTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "packet too large")
+    }
+}
+
+impl Error for PacketTooLarge {}
+
 /// General error type for the packet codec.
 ///
 #[allow(missing_docs)]
@@ -298,6 +351,10 @@ pub enum CodecError {
     InvalidPropertyLength(InvalidPropertyLength),
     InsufficientBufferSize(InsufficientBufferSize),
     MandatoryPropertyMissing(MandatoryPropertyMissing),
+    PacketTooLarge(PacketTooLarge),
+    MissingDependentProperty(MissingDependentProperty),
+    MissingMandatoryField(MissingMandatoryField),
+    EmptyPayload(EmptyPayload),
 }
 
 impl fmt::Display for CodecError {
@@ -335,6 +392,26 @@ impl fmt::Display for CodecError {
                 "{{ \"type\": \"CodecError\", \"message\": \"{}\" }}",
                 err
             ),
+            Self::PacketTooLarge(err) => write!(
+                f,
+                "{{ \"type\": \"CodecError\", \"message\": \"{}\" }}",
+                err
+            ),
+            Self::MissingDependentProperty(err) => write!(
+                f,
+                "{{ \"type\": \"CodecError\", \"message\": \"{}\" }}",
+                err
+            ),
+            Self::MissingMandatoryField(err) => write!(
+                f,
+                "{{ \"type\": \"CodecError\", \"message\": \"{}\" }}",
+                err
+            ),
+            Self::EmptyPayload(err) => write!(
+                f,
+                "{{ \"type\": \"CodecError\", \"message\": \"{}\" }}",
+                err
+            ),
         }
     }
 }
@@ -389,6 +466,30 @@ impl From<MandatoryPropertyMissing> for CodecError {
     }
 }
 
+impl From<PacketTooLarge> for CodecError {
+    fn from(err: PacketTooLarge) -> Self {
+        Self::PacketTooLarge(err)
+    }
+}
+
+impl From<MissingDependentProperty> for CodecError {
+    fn from(err: MissingDependentProperty) -> Self {
+        Self::MissingDependentProperty(err)
+    }
+}
+
+impl From<MissingMandatoryField> for CodecError {
+    fn from(err: MissingMandatoryField) -> Self {
+        Self::MissingMandatoryField(err)
+    }
+}
+
+impl From<EmptyPayload> for CodecError {
+    fn from(err: EmptyPayload) -> Self {
+        Self::EmptyPayload(err)
+    }
+}
+
 impl From<UninitializedFieldError> for CodecError {
     fn from(_: UninitializedFieldError) -> CodecError {
         MandatoryPropertyMissing.into()
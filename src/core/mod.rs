@@ -5,4 +5,4 @@ pub(crate) mod properties;
 pub(crate) mod utils;
 
 pub use base_types::QoS;
-pub use collections::UserProperties;
+pub use collections::{UserProperties, UserPropertiesBuilder};
@@ -875,10 +875,7 @@ mod test {
 
             utf8_string_test(ContentTypeRef(input_str), &EXPECTED_BUF);
             utf8_string_test(ResponseTopicRef(input_str), &EXPECTED_BUF);
-            utf8_string_test(
-                AssignedClientIdentifierRef(input_str),
-                &EXPECTED_BUF,
-            );
+            utf8_string_test(AssignedClientIdentifierRef(input_str), &EXPECTED_BUF);
             utf8_string_test(AuthenticationMethodRef(input_str), &EXPECTED_BUF);
             utf8_string_test(ResponseInformationRef(input_str), &EXPECTED_BUF);
             utf8_string_test(ServerReferenceRef(input_str), &EXPECTED_BUF);
@@ -0,0 +1,57 @@
+use crate::{
+    codec::{AuthRx, ConnackRx, DisconnectRx, PublishRx, RxPacket, SubackRx, UnsubackRx},
+    core::{
+        base_types::VarSizeInt,
+        error::{CodecError, ConversionError},
+        utils::TryDecode,
+    },
+};
+use bytes::Bytes;
+
+/// Decodes the input as CONNACK packet.
+///
+pub fn decode_connack(data: &[u8]) -> Result<(), CodecError> {
+    ConnackRx::try_decode(Bytes::copy_from_slice(data)).map(drop)
+}
+
+/// Decodes the input as PUBLISH packet.
+///
+pub fn decode_publish(data: &[u8]) -> Result<(), CodecError> {
+    PublishRx::try_decode(Bytes::copy_from_slice(data)).map(drop)
+}
+
+/// Decodes the input as SUBACK packet.
+///
+pub fn decode_suback(data: &[u8]) -> Result<(), CodecError> {
+    SubackRx::try_decode(Bytes::copy_from_slice(data)).map(drop)
+}
+
+/// Decodes the input as UNSUBACK packet.
+///
+pub fn decode_unsuback(data: &[u8]) -> Result<(), CodecError> {
+    UnsubackRx::try_decode(Bytes::copy_from_slice(data)).map(drop)
+}
+
+/// Decodes the input as DISCONNECT packet.
+///
+pub fn decode_disconnect(data: &[u8]) -> Result<(), CodecError> {
+    DisconnectRx::try_decode(Bytes::copy_from_slice(data)).map(drop)
+}
+
+/// Decodes the input as AUTH packet.
+///
+pub fn decode_auth(data: &[u8]) -> Result<(), CodecError> {
+    AuthRx::try_decode(Bytes::copy_from_slice(data)).map(drop)
+}
+
+/// Decodes the input as any packet the client may receive, dispatching on the fixed header.
+///
+pub fn decode_packet(data: &[u8]) -> Result<(), CodecError> {
+    RxPacket::try_decode(Bytes::copy_from_slice(data)).map(drop)
+}
+
+/// Decodes the variable byte integer at the beginning of the input.
+///
+pub fn decode_var_size_int(data: &[u8]) -> Result<(), ConversionError> {
+    VarSizeInt::decode_from_slice(data).map(drop)
+}
@@ -0,0 +1,52 @@
+use async_std::net::TcpStream;
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite};
+
+/// Helpers for setting up the connection with [async-std](https://docs.rs/async-std) streams.
+///
+pub struct AsyncStdStream;
+
+impl AsyncStdStream {
+    /// Splits the connected TCP `stream` into the read and write halves, ready to be supplied
+    /// to the [set_up](crate::Context::set_up) method.
+    ///
+    pub fn from_tcp(stream: TcpStream) -> (impl AsyncRead + Unpin, impl AsyncWrite + Unpin) {
+        stream.split()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ConnectOpts, Context};
+    use async_std::{net::TcpListener, task};
+    use futures::{future, AsyncWriteExt};
+
+    #[test]
+    fn connect() {
+        task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+
+            let server = async move {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 64];
+                let _ = stream.read(&mut buf).await.unwrap(); // CONNECT
+                stream.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+                stream
+            };
+
+            let client = async move {
+                let (mut context, _handle) = Context::new();
+                context
+                    .set_up(AsyncStdStream::from_tcp(
+                        TcpStream::connect(addr).await.unwrap(),
+                    ))
+                    .connect(ConnectOpts::new().client_identifier("test"))
+                    .await
+            };
+
+            let (rsp, _) = future::join(client, server).await;
+            assert!(rsp.unwrap().is_left());
+        });
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{
+    client::{
+        error::MqttError, AuthOpts, ConnectOpts, DisconnectOpts, PublishData, PublishOpts,
+        SubscribeOpts, UnsubscribeOpts,
+    },
+    codec::{RxPacket, TxPacket},
+    core::{
+        base_types::VarSizeInt,
+        error::{CodecError, ConversionError, PacketTooLarge},
+        utils::{Encode, SizedPacket, TryDecode},
+    },
+};
+use bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Codec translating between the raw byte stream and MQTT packets, to be used with
+/// [Framed](tokio_util::codec::Framed) as an alternative to the [Context](crate::Context).
+///
+/// Incoming packets are decoded into [MqttPacket]. Outgoing packets are built from the options
+/// builders, e.g. [ConnectOpts] or [PublishOpts]. Note that packet identifiers are assigned by the
+/// [Context](crate::Context) only, hence QoS>0 publications and (un)subscriptions require them to
+/// be set explicitly (see the `unstable` feature).
+///
+/// # Example
+/// ```no_run
+/// use futures::{SinkExt, StreamExt};
+/// use poster::{ConnectOpts, MqttCodec, PublishOpts};
+/// use tokio_util::codec::Framed;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let stream = tokio::net::TcpStream::connect("localhost:1883").await?;
+/// let mut framed = Framed::new(stream, MqttCodec::new());
+///
+/// framed.send(ConnectOpts::new().client_identifier("framed")).await?;
+/// let connack = framed.next().await.unwrap()?;
+/// assert_eq!(connack.packet_type(), "CONNACK");
+///
+/// framed.send(PublishOpts::new().topic_name("topic").payload(b"hello")).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+#[derive(Debug, Default)]
+pub struct MqttCodec {
+    max_packet_size: Option<u32>,
+}
+
+impl MqttCodec {
+    /// Creates a new [MqttCodec] instance. The size of the incoming packets is not limited,
+    /// same as with the [Context](crate::Context).
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the size of the incoming packets, usually to the
+    /// [maximum_packet_size](ConnectOpts::maximum_packet_size) sent in CONNECT.
+    /// Larger packets are rejected with the [PacketTooLarge](crate::error::PacketTooLarge) error before buffering them.
+    ///
+    pub fn with_max_packet_size(mut self, val: u32) -> Self {
+        self.max_packet_size = Some(val);
+        self
+    }
+}
+
+/// Incoming packet decoded by the [MqttCodec].
+///
+pub struct MqttPacket {
+    packet: RxPacket,
+}
+
+impl MqttPacket {
+    /// Accesses the name of the packet type, e.g. `"PUBLISH"`, see [packet_type_name](crate::packet_type_name).
+    ///
+    pub fn packet_type(&self) -> &'static str {
+        self.packet.packet_type()
+    }
+
+    /// Transforms the packet into [PublishData], returning [None] if it is not the PUBLISH packet.
+    ///
+    pub fn into_publish(self) -> Option<PublishData> {
+        match self.packet {
+            RxPacket::Publish(packet) => Some(PublishData::from(packet)),
+            _ => None,
+        }
+    }
+}
+
+impl Decoder for MqttCodec {
+    type Item = MqttPacket;
+    type Error = MqttError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        // We need to be able to read at least fixed header and one byte of size to proceed.
+        if src.len() < 2 {
+            return Ok(None);
+        }
+
+        // Omit packet ID, try to read the remaining length.
+        let remaining_len = match VarSizeInt::try_from(&src[1..]) {
+            Ok(remaining_len) => remaining_len,
+            Err(ConversionError::InsufficientBufferSize(_)) => return Ok(None), // Need to read more data
+            Err(err) => return Err(CodecError::from(err).into()),
+        };
+
+        // Fixed header (1 byte), size of Variable Byte Integer
+        // encoding the remaining length and its value.
+        let packet_len = 1 + remaining_len.len() + remaining_len.value() as usize;
+        if self
+            .max_packet_size
+            .is_some_and(|max| packet_len > max as usize)
+        {
+            return Err(CodecError::from(PacketTooLarge).into());
+        }
+
+        if src.len() < packet_len {
+            src.reserve(packet_len - src.len());
+            return Ok(None);
+        }
+
+        let packet = RxPacket::try_decode(src.split_to(packet_len).freeze())?;
+        Ok(Some(MqttPacket { packet }))
+    }
+}
+
+fn encode_packet(packet: TxPacket<'_>, dst: &mut BytesMut) {
+    dst.reserve(packet.packet_len());
+    packet.encode(dst);
+}
+
+macro_rules! impl_encoder {
+    ($opts:ident, $variant:ident) => {
+        impl<'a> Encoder<$opts<'a>> for MqttCodec {
+            type Error = MqttError;
+
+            fn encode(&mut self, item: $opts<'a>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+                encode_packet(TxPacket::$variant(item.build()?), dst);
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_encoder!(ConnectOpts, Connect);
+impl_encoder!(AuthOpts, Auth);
+impl_encoder!(PublishOpts, Publish);
+impl_encoder!(SubscribeOpts, Subscribe);
+impl_encoder!(UnsubscribeOpts, Unsubscribe);
+impl_encoder!(DisconnectOpts, Disconnect);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_util::codec::Framed;
+
+    #[test]
+    fn decode_partial() {
+        let mut codec = MqttCodec::new();
+        let mut buf = BytesMut::from(&[0xd0][..]);
+
+        assert!(matches!(codec.decode(&mut buf), Ok(None)));
+
+        buf.extend_from_slice(&[0x00, 0xd0]);
+        let packet = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(packet.packet_type(), "PINGRESP");
+        assert!(packet.into_publish().is_none());
+        assert!(matches!(codec.decode(&mut buf), Ok(None)));
+        assert_eq!(buf.len(), 1);
+    }
+
+    #[test]
+    fn decode_packet_too_large() {
+        let mut codec = MqttCodec::new().with_max_packet_size(8);
+
+        // Only the fixed header of the 131 bytes long PUBLISH packet is received.
+        let mut buf = BytesMut::from(&[0x30, 0x80, 0x01][..]);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(MqttError::CodecError(CodecError::PacketTooLarge(_)))
+        ));
+        assert!(buf.capacity() < 131);
+
+        let mut buf = BytesMut::from(&[0xd0, 0x00][..]);
+        assert!(codec.decode(&mut buf).unwrap().is_some());
+    }
+
+    #[test]
+    fn encode() {
+        const EXPECTED: [u8; 4] = [0xe0, 2, 0, 0];
+
+        let mut codec = MqttCodec::new();
+        let mut buf = BytesMut::new();
+
+        assert!(codec.encode(DisconnectOpts::new(), &mut buf).is_ok());
+        assert_eq!(&buf[..], &EXPECTED[..]);
+
+        // QoS>0 publication without the packet identifier.
+        let opts = PublishOpts::new()
+            .topic_name("a")
+            .qos(crate::QoS::AtLeastOnce);
+        assert!(codec.encode(opts, &mut buf).is_err());
+    }
+
+    #[tokio::test]
+    async fn framed() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let mut framed = Framed::new(client, MqttCodec::new());
+
+        framed
+            .send(PublishOpts::new().topic_name("a").payload(b"ok"))
+            .await
+            .unwrap();
+
+        let mut buf = [0u8; 8];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, [0x30, 6, 0, 1, b'a', 0, b'o', b'k']);
+
+        server
+            .write_all(&[
+                0x20, 3, 0, 0, 0, 0x30, 8, 0, 1, b'a', 2, 0x0b, 1, b'o', b'k',
+            ])
+            .await
+            .unwrap();
+
+        let connack = framed.next().await.unwrap().unwrap();
+        assert_eq!(connack.packet_type(), "CONNACK");
+
+        let publish = framed
+            .next()
+            .await
+            .unwrap()
+            .unwrap()
+            .into_publish()
+            .unwrap();
+        assert_eq!(publish.topic_name(), "a");
+        assert_eq!(publish.payload(), b"ok");
+    }
+}
@@ -1,3 +1,20 @@
+#[cfg(feature = "async-std")]
+mod async_std_stream;
+#[cfg(feature = "tokio-codec")]
+mod codec;
+mod observer;
 mod packet_stream;
+#[cfg(feature = "ws")]
+pub(crate) mod ws;
 
-pub(crate) use packet_stream::{RxPacketStream, TxPacketStream};
+pub(crate) use packet_stream::{
+    RxPacketStream, SharedFramingCounters, SharedPacketObserver, TxPacketStream,
+};
+
+#[cfg(feature = "async-std")]
+pub use async_std_stream::AsyncStdStream;
+#[cfg(feature = "tokio-codec")]
+pub use codec::{MqttCodec, MqttPacket};
+#[cfg(feature = "tracing")]
+pub use observer::LoggingPacketObserver;
+pub use observer::PacketObserver;
@@ -0,0 +1,42 @@
+/// Observer of the packets exchanged with the broker, see
+/// [set_packet_observer](crate::Context::set_packet_observer).
+///
+/// Both methods receive the complete encoded packet, including the fixed header.
+/// They are called on the context task, so they should return quickly.
+///
+pub trait PacketObserver {
+    /// Called for each packet received from the broker, before it is processed.
+    ///
+    fn on_rx(&self, packet: &[u8]);
+
+    /// Called for each packet before it is written to the broker.
+    ///
+    fn on_tx(&self, packet: &[u8]);
+}
+
+/// [PacketObserver] reporting each packet with a trace event.
+///
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingPacketObserver;
+
+#[cfg(feature = "tracing")]
+impl PacketObserver for LoggingPacketObserver {
+    fn on_rx(&self, packet: &[u8]) {
+        tracing::trace!(
+            packet_type = packet.first().map(|fixed_hdr| crate::codec::packet_type_name(fixed_hdr >> 4)),
+            len = packet.len(),
+            bytes = ?packet,
+            "rx"
+        );
+    }
+
+    fn on_tx(&self, packet: &[u8]) {
+        tracing::trace!(
+            packet_type = packet.first().map(|fixed_hdr| crate::codec::packet_type_name(fixed_hdr >> 4)),
+            len = packet.len(),
+            bytes = ?packet,
+            "tx"
+        );
+    }
+}
@@ -1,8 +1,10 @@
+use super::observer::PacketObserver;
 use crate::{
-    codec::RxPacket,
+    client::error::{MqttError, ReadTimeout, SocketClosed},
+    codec::{v311, MqttVersion, RxPacket},
     core::{
         base_types::VarSizeInt,
-        error::{CodecError, ConversionError},
+        error::{CodecError, ConversionError, PacketTooLarge},
         utils::TryDecode,
     },
 };
@@ -11,9 +13,65 @@ use core::{
     ops::Range,
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
-use futures::{AsyncRead, AsyncWrite, AsyncWriteExt, Stream};
-use std::{io, mem};
+use futures::{io::BufWriter, AsyncRead, AsyncWrite, AsyncWriteExt, FutureExt, Stream};
+use futures_timer::Delay;
+use std::{
+    mem,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+pub(crate) type SharedPacketObserver = Arc<dyn PacketObserver + Send + Sync>;
+
+/// Counters of the received data that could not be turned into packets or delivered. Shared between
+/// the [RxPacketStream], the [Context](crate::Context) and the [ContextHandle](crate::ContextHandle)
+/// reporting them.
+///
+#[derive(Default)]
+pub(crate) struct FramingCounters {
+    framing_errors: AtomicU64,
+    bytes_discarded: AtomicU64,
+    unexpected_publishes: AtomicU64,
+}
+
+impl FramingCounters {
+    /// Number of times the stream has failed to frame or decode a packet, returning an error.
+    ///
+    pub(crate) fn framing_errors(&self) -> u64 {
+        self.framing_errors.load(Ordering::Relaxed)
+    }
+
+    /// Number of received bytes dropped from the stream buffer because of the errors.
+    ///
+    pub(crate) fn bytes_discarded(&self) -> u64 {
+        self.bytes_discarded.load(Ordering::Relaxed)
+    }
+
+    /// Number of PUBLISH packets that could not be routed to any subscription.
+    ///
+    pub(crate) fn unexpected_publishes(&self) -> u64 {
+        self.unexpected_publishes.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_unexpected_publish(&self) {
+        self.unexpected_publishes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record(&self, framing_error: bool, bytes_discarded: usize) {
+        if framing_error {
+            self.framing_errors.fetch_add(1, Ordering::Relaxed);
+        }
+
+        self.bytes_discarded
+            .fetch_add(bytes_discarded as u64, Ordering::Relaxed);
+    }
+}
+
+pub(crate) type SharedFramingCounters = Arc<FramingCounters>;
 
 enum PacketStreamState {
     Idle,
@@ -27,23 +85,97 @@ pub(crate) struct RxPacketStream<StreamT> {
     size: usize,
 
     packet: Range<usize>,
+    max_packet_size: Option<u32>,
+    observer: Option<SharedPacketObserver>,
+    protocol_version: MqttVersion,
+    counters: SharedFramingCounters,
+
+    read_timeout: Option<Duration>,
+    read_timer: Option<Delay>,
 
     state: PacketStreamState,
 }
 
-impl<StreamT> From<StreamT> for RxPacketStream<StreamT> {
-    fn from(stream: StreamT) -> Self {
+impl<StreamT> RxPacketStream<StreamT> {
+    pub(crate) const DEFAULT_BUF_SIZE: usize = 1024;
+
+    pub(crate) fn with_capacity(stream: StreamT, capacity: usize) -> Self {
         Self {
             stream,
-            buf: BytesMut::with_capacity(1024),
+            buf: BytesMut::with_capacity(capacity),
             size: 0,
             packet: 0..0,
+            max_packet_size: None,
+            observer: None,
+            protocol_version: MqttVersion::default(),
+            counters: SharedFramingCounters::default(),
+            read_timeout: None,
+            read_timer: None,
             state: PacketStreamState::Idle,
         }
     }
+
+    /// Limits the time of receiving a single packet, counted from its first bytes.
+    ///
+    pub(crate) fn with_read_timeout(mut self, val: Duration) -> Self {
+        self.read_timeout = Some(val);
+        self
+    }
+}
+
+impl<StreamT> From<StreamT> for RxPacketStream<StreamT> {
+    fn from(stream: StreamT) -> Self {
+        Self::with_capacity(stream, Self::DEFAULT_BUF_SIZE)
+    }
 }
 
 impl<StreamT> RxPacketStream<StreamT> {
+    pub(crate) fn set_max_packet_size(&mut self, val: Option<u32>) {
+        self.max_packet_size = val;
+    }
+
+    pub(crate) fn set_observer(&mut self, val: Option<SharedPacketObserver>) {
+        self.observer = val;
+    }
+
+    pub(crate) fn set_protocol_version(&mut self, val: MqttVersion) {
+        self.protocol_version = val;
+    }
+
+    pub(crate) fn set_counters(&mut self, val: SharedFramingCounters) {
+        self.counters = val;
+    }
+
+    pub(crate) fn map_stream<F, MappedT>(self, f: F) -> RxPacketStream<MappedT>
+    where
+        F: FnOnce(StreamT) -> MappedT,
+    {
+        RxPacketStream {
+            stream: f(self.stream),
+            buf: self.buf,
+            size: self.size,
+            packet: self.packet,
+            max_packet_size: self.max_packet_size,
+            observer: self.observer,
+            protocol_version: self.protocol_version,
+            counters: self.counters,
+            read_timeout: self.read_timeout,
+            read_timer: self.read_timer,
+            state: self.state,
+        }
+    }
+
+    // Polled only while the packet is partially received.
+    fn poll_read_timer(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match self.read_timeout {
+            Some(timeout) => self
+                .read_timer
+                .get_or_insert_with(|| Delay::new(timeout))
+                .poll_unpin(cx),
+            None => Poll::Pending,
+        }
+    }
+
     fn split_borrows_mut(
         &mut self,
     ) -> (
@@ -51,6 +183,8 @@ impl<StreamT> RxPacketStream<StreamT> {
         &mut BytesMut,
         &mut usize,
         &mut Range<usize>,
+        Option<u32>,
+        Option<&SharedPacketObserver>,
         &mut PacketStreamState,
     ) {
         (
@@ -58,6 +192,8 @@ impl<StreamT> RxPacketStream<StreamT> {
             &mut self.buf,
             &mut self.size,
             &mut self.packet,
+            self.max_packet_size,
+            self.observer.as_ref(),
             &mut self.state,
         )
     }
@@ -67,12 +203,13 @@ impl<StreamT> Stream for RxPacketStream<StreamT>
 where
     StreamT: AsyncRead + Unpin,
 {
-    type Item = Result<RxPacket, CodecError>;
+    type Item = Result<RxPacket, MqttError>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         const DEFAULT_CHUNK_SIZE: usize = 512;
 
-        let (mut stream, buf, size, packet, state) = self.split_borrows_mut();
+        let (mut stream, buf, size, packet, max_packet_size, observer, state) =
+            self.split_borrows_mut();
 
         match *state {
             PacketStreamState::Idle => {
@@ -84,16 +221,15 @@ where
 
                 buf.resize(*size + chunk_size, 0);
 
-                if let Poll::Ready(result) = Pin::new(&mut stream)
-                    .poll_read(cx, &mut buf[*size..*size + chunk_size])
-                    .map(|res| res.ok().filter(|&size| size != 0 /* EOF */))
+                if let Poll::Ready(result) =
+                    Pin::new(&mut stream).poll_read(cx, &mut buf[*size..*size + chunk_size])
                 {
-                    if result.is_none() {
-                        return Poll::Ready(None);
+                    match result {
+                        Ok(0) => return Poll::Ready(None), // EOF
+                        Ok(read) => *size += read,
+                        Err(err) => return Poll::Ready(Some(Err(SocketClosed::from(err).into()))),
                     }
 
-                    *size += result.unwrap();
-
                     // We need to be able to read at least fixed header and one byte of size to proceed.
                     if *size >= 2 {
                         *state = PacketStreamState::ReadPacketLen;
@@ -101,27 +237,59 @@ where
                     }
                 }
 
+                if *size != 0 && self.poll_read_timer(cx).is_ready() {
+                    // Packet cannot be processed, the stream is no longer usable.
+                    self.counters.record(false, self.size);
+                    let (_, buf, size, packet, _, _, state) = self.split_borrows_mut();
+                    buf.clear();
+                    *size = 0;
+                    *packet = 0..0;
+                    *state = PacketStreamState::Idle;
+                    return Poll::Ready(Some(Err(ReadTimeout.into())));
+                }
+
                 Poll::Pending
             }
             PacketStreamState::ReadPacketLen => {
-                // Omit packet ID, try to read the remaining length.
-                let maybe_remaining_len =
-                    VarSizeInt::try_from(&buf[1..]).map(Some).or_else(|err| {
+                // Omit packet ID, try to read the remaining length from the bytes received so far.
+                let maybe_remaining_len = VarSizeInt::decode_from_slice(&buf[1..*size])
+                    .map(Some)
+                    .or_else(|err| {
                         if let ConversionError::InsufficientBufferSize(_) = err {
                             return Ok(None); // Need to read more data
                         }
                         Err(err)
                     });
 
-                if maybe_remaining_len.is_err() {
-                    return Poll::Ready(None);
-                }
+                let maybe_remaining_len = match maybe_remaining_len {
+                    Ok(val) => val,
+                    Err(err) => {
+                        // Packet cannot be processed, the stream is no longer usable.
+                        let discarded = mem::take(size);
+                        buf.clear();
+                        *packet = 0..0;
+                        *state = PacketStreamState::Idle;
+                        self.counters.record(true, discarded);
+                        return Poll::Ready(Some(Err(CodecError::from(err).into())));
+                    }
+                };
 
-                if let Some(remaining_len) = maybe_remaining_len.unwrap() {
+                if let Some((remaining_len, consumed)) = maybe_remaining_len {
                     // Fixed header (1 byte), size of Variable Byte Integer
                     // encoding the remaining length and its value.
                     packet.start = 0;
-                    packet.end = 1 + remaining_len.len() + remaining_len.value() as usize;
+                    packet.end = 1 + consumed + remaining_len.value() as usize;
+
+                    if max_packet_size.is_some_and(|max| packet.end > max as usize) {
+                        // Packet cannot be processed, the stream is no longer usable.
+                        let discarded = mem::take(size);
+                        buf.clear();
+                        *packet = 0..0;
+                        *state = PacketStreamState::Idle;
+                        self.counters.record(true, discarded);
+                        return Poll::Ready(Some(Err(CodecError::from(PacketTooLarge).into())));
+                    }
+
                     *state = PacketStreamState::ReadPacketData;
                     return self.poll_next(cx);
                 }
@@ -142,29 +310,267 @@ where
                     *state = PacketStreamState::Idle;
                 }
 
-                Poll::Ready(Some(RxPacket::try_decode(
-                    buf.split_to(mem::replace(&mut packet.end, 0)).freeze(),
-                )))
+                let bytes = buf.split_to(mem::replace(&mut packet.end, 0)).freeze();
+                if let Some(observer) = observer {
+                    observer.on_rx(&bytes);
+                }
+
+                self.read_timer = None;
+
+                let packet_len = bytes.len();
+                let bytes = match self.protocol_version {
+                    MqttVersion::V311 => v311::upgrade(bytes),
+                    MqttVersion::V500 => Ok(bytes),
+                };
+
+                let result = bytes.and_then(RxPacket::try_decode);
+                if result.is_err() {
+                    self.counters.record(true, packet_len);
+                }
+
+                Poll::Ready(Some(result.map_err(MqttError::from)))
             }
         }
     }
 }
 
 pub(crate) struct TxPacketStream<TxStreamT> {
-    stream: TxStreamT,
+    stream: BufWriter<TxStreamT>,
+    capacity: usize,
+    observer: Option<SharedPacketObserver>,
+    protocol_version: MqttVersion,
+}
+
+impl<TxStreamT> TxPacketStream<TxStreamT>
+where
+    TxStreamT: AsyncWrite,
+{
+    pub(crate) fn with_capacity(stream: TxStreamT, capacity: usize) -> Self {
+        Self {
+            stream: BufWriter::with_capacity(capacity, stream),
+            capacity,
+            observer: None,
+            protocol_version: MqttVersion::default(),
+        }
+    }
 }
 
-impl<TxStreamT> From<TxStreamT> for TxPacketStream<TxStreamT> {
+impl<TxStreamT> From<TxStreamT> for TxPacketStream<TxStreamT>
+where
+    TxStreamT: AsyncWrite,
+{
     fn from(inner: TxStreamT) -> Self {
-        Self { stream: inner }
+        Self::with_capacity(inner, Self::DEFAULT_BUF_SIZE)
     }
 }
 
 impl<TxStreamT> TxPacketStream<TxStreamT> {
-    pub(crate) async fn write(&mut self, packet: &[u8]) -> Result<(), io::Error>
+    // Packets are written directly to the stream, without intermediate buffering.
+    pub(crate) const DEFAULT_BUF_SIZE: usize = 0;
+
+    // The context flushes the buffer before waiting for more work, so no data is lost when
+    // unwrapping the stream.
+    pub(crate) fn map_stream<F, MappedT>(self, f: F) -> TxPacketStream<MappedT>
+    where
+        F: FnOnce(TxStreamT) -> MappedT,
+        MappedT: AsyncWrite,
+    {
+        let mut mapped = TxPacketStream::with_capacity(f(self.stream.into_inner()), self.capacity);
+        mapped.observer = self.observer;
+        mapped.protocol_version = self.protocol_version;
+        mapped
+    }
+
+    pub(crate) fn set_observer(&mut self, val: Option<SharedPacketObserver>) {
+        self.observer = val;
+    }
+
+    pub(crate) fn set_protocol_version(&mut self, val: MqttVersion) {
+        self.protocol_version = val;
+    }
+
+    pub(crate) async fn write(&mut self, packet: &[u8]) -> Result<(), MqttError>
+    where
+        TxStreamT: AsyncWrite + Unpin,
+    {
+        let downgraded;
+        let packet = match self.protocol_version {
+            MqttVersion::V311 => {
+                downgraded = v311::downgrade(packet)?;
+                downgraded.as_ref()
+            }
+            MqttVersion::V500 => packet,
+        };
+
+        if let Some(observer) = self.observer.as_ref() {
+            observer.on_tx(packet);
+        }
+
+        self.stream.write_all(&packet[0..packet.len()]).await?;
+        Ok(())
+    }
+
+    // Packets are buffered until flushed, so that multiple packets written in a row
+    // are sent out together.
+    pub(crate) async fn flush(&mut self) -> Result<(), MqttError>
     where
         TxStreamT: AsyncWrite + Unpin,
     {
-        self.stream.write_all(&packet[0..packet.len()]).await
+        self.stream.flush().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::{executor::block_on, io::Cursor, StreamExt};
+
+    #[test]
+    fn max_packet_size_exceeded() {
+        // PINGRESP followed by the PUBLISH packet with remaining length of 9 bytes.
+        const INPUT: [u8; 13] = [
+            0xd0, 0x00, 0x30, 0x09, 0x00, 0x04, b't', b'e', b's', b't', 0x00, b'a', b'b',
+        ];
+
+        let mut stream = RxPacketStream::from(Cursor::new(INPUT));
+        stream.set_max_packet_size(Some(10));
+
+        assert!(matches!(
+            block_on(stream.next()),
+            Some(Ok(RxPacket::Pingresp(_)))
+        ));
+        assert!(matches!(
+            block_on(stream.next()),
+            Some(Err(MqttError::CodecError(CodecError::PacketTooLarge(_))))
+        ));
+    }
+
+    #[test]
+    fn read_error() {
+        struct Reset;
+
+        impl AsyncRead for Reset {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                _: &mut Context<'_>,
+                _: &mut [u8],
+            ) -> Poll<std::io::Result<usize>> {
+                Poll::Ready(Err(std::io::ErrorKind::ConnectionReset.into()))
+            }
+        }
+
+        let mut stream = RxPacketStream::from(Reset);
+        match block_on(stream.next()) {
+            Some(Err(MqttError::SocketClosed(err))) => {
+                assert_eq!(err.kind(), std::io::ErrorKind::ConnectionReset);
+            }
+            _ => panic!("expected SocketClosed"),
+        }
+
+        // End of the stream is not an error.
+        let mut stream = RxPacketStream::from(Cursor::new([]));
+        assert!(block_on(stream.next()).is_none());
+    }
+
+    #[test]
+    fn invalid_remaining_len() {
+        const INPUT: [u8; 6] = [0x30, 0xff, 0xff, 0xff, 0xff, 0x01];
+
+        let mut stream = RxPacketStream::from(Cursor::new(INPUT));
+        assert!(matches!(
+            block_on(stream.next()),
+            Some(Err(MqttError::CodecError(_)))
+        ));
+    }
+
+    #[test]
+    fn framing_counters() {
+        // PUBACK with invalid packet identifier, PINGRESP and the PUBLISH packet exceeding the limit.
+        const INPUT: [u8; 10] = [0x40, 0x02, 0x00, 0x00, 0xd0, 0x00, 0x30, 0x09, 0x00, 0x04];
+
+        let counters = SharedFramingCounters::default();
+        let mut stream = RxPacketStream::from(Cursor::new(INPUT));
+        stream.set_counters(counters.clone());
+        stream.set_max_packet_size(Some(8));
+
+        assert!(matches!(block_on(stream.next()), Some(Err(_))));
+        assert_eq!(counters.framing_errors(), 1);
+        assert_eq!(counters.bytes_discarded(), 4);
+
+        assert!(matches!(
+            block_on(stream.next()),
+            Some(Ok(RxPacket::Pingresp(_)))
+        ));
+        assert_eq!(counters.framing_errors(), 1);
+
+        assert!(matches!(
+            block_on(stream.next()),
+            Some(Err(MqttError::CodecError(CodecError::PacketTooLarge(_))))
+        ));
+        assert_eq!(counters.framing_errors(), 2);
+        assert_eq!(counters.bytes_discarded(), 8);
+    }
+
+    #[tokio::test]
+    async fn read_timeout() {
+        use tokio::io::AsyncWriteExt;
+        use tokio_util::compat::TokioAsyncReadCompatExt;
+
+        let (client, mut server) = tokio::io::duplex(64);
+        let mut stream =
+            RxPacketStream::from(client.compat()).with_read_timeout(Duration::from_millis(50));
+
+        server.write_all(&[0xd0, 0x00]).await.unwrap(); // PINGRESP
+        assert!(matches!(
+            stream.next().await,
+            Some(Ok(RxPacket::Pingresp(_)))
+        ));
+
+        // Idle stream is not limited by the read timeout.
+        let idle = futures::future::select(stream.next(), Delay::new(Duration::from_millis(100)));
+        assert!(matches!(idle.await, futures::future::Either::Right(_)));
+
+        server.write_all(&[0x30, 0x09, 0x00]).await.unwrap(); // Incomplete PUBLISH
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(MqttError::ReadTimeout(_)))
+        ));
+    }
+
+    #[test]
+    fn observer() {
+        use std::sync::Mutex;
+
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<Vec<u8>>>);
+
+        impl PacketObserver for Recorder {
+            fn on_rx(&self, packet: &[u8]) {
+                self.0.lock().unwrap().push(packet.to_vec());
+            }
+
+            fn on_tx(&self, packet: &[u8]) {
+                self.0.lock().unwrap().push(packet.to_vec());
+            }
+        }
+
+        const INPUT: [u8; 4] = [0xd0, 0x00, 0xd0, 0x00]; // Two PINGRESP packets
+
+        let recorder = Arc::new(Recorder::default());
+
+        let mut rx = RxPacketStream::from(Cursor::new(INPUT));
+        rx.set_observer(Some(recorder.clone()));
+        assert!(block_on(rx.next()).unwrap().is_ok());
+        assert!(block_on(rx.next()).unwrap().is_ok());
+
+        let mut tx = TxPacketStream::from(Cursor::new(Vec::new()));
+        tx.set_observer(Some(recorder.clone()));
+        block_on(tx.write(&[0xc0, 0x00])).unwrap(); // PINGREQ
+
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [vec![0xd0, 0x00], vec![0xd0, 0x00], vec![0xc0, 0x00]]
+        );
     }
 }
@@ -0,0 +1,78 @@
+use async_tungstenite::{
+    bytes::{ByteReader, ByteWriter},
+    tungstenite::{client::IntoClientRequest, http::HeaderValue},
+};
+use core::fmt;
+use futures::{AsyncRead, AsyncWrite};
+use std::error::Error;
+
+/// WebSocket subprotocol name registered for MQTT.
+///
+const MQTT_SUBPROTOCOL: &str = "mqtt";
+
+/// Error returned when the WebSocket connection could not be established.
+///
+#[derive(Debug)]
+pub struct WsConnectError {
+    message: String,
+}
+
+impl fmt::Display for WsConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{{ \"type\": \"WsConnectError\", \"message\": \"{}\" }}",
+            self.message
+        )
+    }
+}
+
+impl Error for WsConnectError {}
+
+impl From<async_tungstenite::tungstenite::Error> for WsConnectError {
+    fn from(err: async_tungstenite::tungstenite::Error) -> Self {
+        Self {
+            message: err.to_string(),
+        }
+    }
+}
+
+/// WebSocket transport for the MQTT connection.
+///
+pub struct WsStream;
+
+impl WsStream {
+    /// Performs the WebSocket upgrade handshake with the `mqtt` subprotocol over the already
+    /// established `stream` and returns the read and write halves, ready to be supplied
+    /// to the [set_up](crate::Context::set_up) method.
+    ///
+    /// # Arguments
+    /// * `url` - Broker URL with either `ws://` or `wss://` scheme.
+    /// * `stream` - Connected stream, must be [AsyncRead] + [AsyncWrite] + [Unpin].
+    ///   In case of `wss://` scheme, the stream must handle the encryption.
+    ///
+    pub async fn connect<StreamT>(
+        url: &str,
+        stream: StreamT,
+    ) -> Result<(impl AsyncRead + Unpin, impl AsyncWrite + Unpin), WsConnectError>
+    where
+        StreamT: AsyncRead + AsyncWrite + Unpin,
+    {
+        if !url.starts_with("ws://") && !url.starts_with("wss://") {
+            return Err(WsConnectError {
+                message: String::from("unsupported URL scheme"),
+            });
+        }
+
+        let mut request = url.into_client_request()?;
+        request.headers_mut().insert(
+            "Sec-WebSocket-Protocol",
+            HeaderValue::from_static(MQTT_SUBPROTOCOL),
+        );
+
+        let (ws, _) = async_tungstenite::client_async(request, stream).await?;
+        let (sender, receiver) = ws.split();
+
+        Ok((ByteReader::new(receiver), ByteWriter::new(sender)))
+    }
+}
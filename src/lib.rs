@@ -1,6 +1,8 @@
 #![forbid(unsafe_code, unreachable_pub, unused_must_use)]
 #![warn(missing_docs)]
 #![allow(dead_code)]
+#![cfg_attr(feature = "backtrace", feature(error_generic_member_access))]
+#![cfg_attr(feature = "nightly", feature(async_iterator))]
 
 //! Poster-rs is an asynchronous, runtime agnostic, zero-copy MQTT 5 library,
 //! designed having operation locality in mind.
@@ -105,7 +107,7 @@
 //! - await the invocation of [subscribe](crate::ContextHandle::subscribe) method
 //! - validate the result (optionally)
 //! - use [stream](crate::SubscribeRsp::stream) method in order to create a stream for
-//! the subscription.
+//!   the subscription.
 //!
 //! Note that under the hood, the library uses subscription identifiers to group subscriptions.
 //!
@@ -306,20 +308,104 @@
 //!
 //! The main library error type is [MqttError](crate::error::MqttError) enum found in [error] module.
 //!
+//! ## MQTT v3.1.1
+//!
+//! Legacy brokers are supported by selecting [V311](crate::MqttVersion::V311) with
+//! [protocol_version](crate::ConnectOpts::protocol_version). The MQTT 5 only features are then not
+//! sent over the wire, see the method documentation for details.
+//!
 //! ## TLS/SSL
 //!
 //! TLS/SSL libraries are available out there with AsyncRead, AsyncWrite TLS/SSL streams. These may be
 //! supplied to the [set_up](crate::Context::set_up) method. The library does not handle encription on its own.
 //!
+//! ## tokio codec
+//!
+//! The `tokio-codec` feature adds `MqttCodec`, implementing the `Encoder` and `Decoder` traits from `tokio_util::codec`,
+//! for users who handle the packets with `Framed` themselves instead of running the [Context].
+//!
+//! ## async-std
+//!
+//! With the `async-std` feature enabled, `AsyncStdStream::from_tcp` splits the async-std `TcpStream` into halves
+//! ready for the [set_up](crate::Context::set_up) method.
+//!
+//! ## WebSocket
+//!
+//! With the `ws` feature enabled, MQTT over WebSocket is available. The `ws::WsStream::connect` method performs
+//! the WebSocket handshake over the supplied stream and returns halves ready for the [set_up](crate::Context::set_up) method.
+//!
+//! ## Tracing
+//!
+//! With the `tracing` feature enabled, [connect](crate::Context::connect), [run](crate::Context::run) and the
+//! [ContextHandle](crate::ContextHandle) publish, subscribe and unsubscribe methods are instrumented with
+//! [tracing](https://docs.rs/tracing) spans. Each packet processed by the context is reported with a debug event.
+//! Capabilities reported by the broker in CONNACK are logged with an info event after a successful connection.
+//! Raw packets may be traced by registering `LoggingPacketObserver` with
+//! [set_packet_observer](crate::Context::set_packet_observer).
+//!
+//! ## Backtrace
+//!
+//! The `backtrace` feature implements `Error::provide` for [MqttError](crate::error::MqttError), forwarding
+//! requests (e.g. for [Backtrace](std::backtrace::Backtrace)) to the wrapped errors. It relies on the unstable
+//! `error_generic_member_access` API and therefore requires a nightly compiler.
+//!
+//! ## Async iterator
+//!
+//! The `nightly` feature implements the unstable `AsyncIterator` trait for the subscription streams, allowing
+//! `for await msg in stream { ... }` loops. Requires a nightly compiler and the `async_iterator` and
+//! `async_for_loop` features enabled in the user crate.
+//!
+//! ## Serde
+//!
+//! The `serde` feature implements `Serialize` for [MqttError](crate::error::MqttError), e.g. for structured
+//! error logging. Reason code enums and [QoS] implement `Serialize` and `Deserialize` as their
+//! snake case names, while the serialized error includes both the numeric `reason_code` and the `reason` name.
+//!
+//! ## Buffer pool
+//!
+//! With the `pool` feature enabled, buffers of the outgoing PUBLISH packets are returned to a bounded pool
+//! after being sent (or acknowledged, for QoS>0) and reused by subsequent publishes. The pool size is set with
+//! `ContextIoOptions::tx_pool_size`.
+//!
+//! ## Compression
+//!
+//! The `compress` feature adds the `compress` module with `CompressedPublishOpts`, compressing the payload
+//! with gzip or deflate before publishing, and `decompress` for the receiving side. The algorithm is
+//! advertised in the `content-encoding` user property. The `lz4` feature adds LZ4 to the supported algorithms.
+//!
+//! ## Unstable
+//!
+//! The `unstable` feature exposes `into_packet` on the options builders, returning the wire format of the
+//! corresponding packet, e.g. for testing or direct codec usage, and `ContextHandle::send_raw`,
+//! sending such packets without any validation. The API may change without a major version bump.
+//!
 
 mod client;
 mod codec;
 mod core;
 mod io;
 
+/// In-process MQTT broker stub for testing.
+///
+#[cfg(any(test, feature = "test-broker"))]
+pub mod testing;
+
+/// Packet decoding entry points for fuzz targets. Not part of the stable API.
+///
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing;
+
 pub use crate::client::*;
-pub use crate::codec::RetainHandling;
-pub use crate::core::{QoS, UserProperties};
+pub use crate::codec::{packet_type_name, MqttVersion, RetainHandling};
+pub use crate::core::{QoS, UserProperties, UserPropertiesBuilder};
+#[cfg(feature = "async-std")]
+pub use crate::io::AsyncStdStream;
+#[cfg(feature = "tracing")]
+pub use crate::io::LoggingPacketObserver;
+pub use crate::io::PacketObserver;
+#[cfg(feature = "tokio-codec")]
+pub use crate::io::{MqttCodec, MqttPacket};
 
 /// Reason codes for different operations.
 ///
@@ -337,6 +423,20 @@ pub mod error {
     pub use crate::core::error::*;
 }
 
+/// WebSocket transport.
+///
+#[cfg(feature = "ws")]
+pub mod ws {
+    pub use crate::io::ws::*;
+}
+
+/// Payload compression.
+///
+#[cfg(feature = "compress")]
+pub mod compress {
+    pub use crate::client::compress::*;
+}
+
 /// Reexports.
 ///
 pub mod prelude {
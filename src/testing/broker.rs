@@ -0,0 +1,499 @@
+use crate::{
+    client::error::MqttError,
+    core::{
+        base_types::VarSizeInt,
+        error::{
+            CodecError, ConversionError, InsufficientBufferSize, InvalidPacketHeader,
+            UnexpectedProperty,
+        },
+        utils::Encode,
+    },
+};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures::{
+    channel::mpsc,
+    future,
+    stream::{self, StreamExt},
+    AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt,
+};
+use std::{
+    io, str,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+const CONNECT: u8 = 1;
+const CONNACK: u8 = 2;
+const PUBLISH: u8 = 3;
+const PUBACK: u8 = 4;
+const SUBSCRIBE: u8 = 8;
+const SUBACK: u8 = 9;
+const UNSUBSCRIBE: u8 = 10;
+const UNSUBACK: u8 = 11;
+const PINGREQ: u8 = 12;
+const PINGRESP: u8 = 13;
+const DISCONNECT: u8 = 14;
+
+const SUBSCRIPTION_IDENTIFIER: u8 = 0x0b;
+const MAXIMUM_QOS: u8 = 0x24;
+const USER_PROPERTY: u8 = 0x26;
+
+const QOS_NOT_SUPPORTED: u8 = 0x9b;
+
+struct Subscriber {
+    connection: usize,
+    filter: String,
+    subscription_identifier: Option<u32>,
+    no_local: bool,
+    sender: mpsc::UnboundedSender<Bytes>,
+}
+
+#[derive(Default)]
+struct BrokerState {
+    next_connection: usize,
+    subscribers: Vec<Subscriber>,
+}
+
+enum Event {
+    Incoming(io::Result<(u8, Bytes)>),
+    Outgoing(Bytes),
+    Closed,
+}
+
+struct Reader(Bytes);
+
+impl Reader {
+    fn ensure(&self, len: usize) -> Result<(), CodecError> {
+        if self.0.remaining() < len {
+            return Err(InsufficientBufferSize.into());
+        }
+
+        Ok(())
+    }
+
+    fn u8(&mut self) -> Result<u8, CodecError> {
+        self.ensure(1)?;
+        Ok(self.0.get_u8())
+    }
+
+    fn u16(&mut self) -> Result<u16, CodecError> {
+        self.ensure(2)?;
+        Ok(self.0.get_u16())
+    }
+
+    fn var_size_int(&mut self) -> Result<u32, CodecError> {
+        let (val, consumed) = VarSizeInt::decode_from_slice(self.0.as_ref())?;
+        self.0.advance(consumed);
+        Ok(val.value())
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<Bytes, CodecError> {
+        self.ensure(len)?;
+        Ok(self.0.split_to(len))
+    }
+
+    fn string(&mut self) -> Result<String, CodecError> {
+        let len = self.u16()? as usize;
+        let bytes = self.bytes(len)?;
+        str::from_utf8(&bytes)
+            .map(String::from)
+            .map_err(|err| CodecError::from(ConversionError::from(err)))
+    }
+
+    fn properties(&mut self) -> Result<Reader, CodecError> {
+        let len = self.var_size_int()? as usize;
+        self.bytes(len).map(Reader)
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.0.has_remaining()
+    }
+
+    fn rest(self) -> Bytes {
+        self.0
+    }
+}
+
+/// Minimal in-process MQTT 5 broker, intended for testing the client code
+/// without the external broker process.
+///
+/// Supported features:
+/// - CONNECT, responded with CONNACK with the success reason and maximum QoS 1,
+/// - SUBSCRIBE and UNSUBSCRIBE, including wildcard topic filters and the no local option,
+/// - PUBLISH with QoS 0 and 1, routed to matching subscriptions with QoS 0,
+/// - PINGREQ,
+/// - DISCONNECT.
+///
+/// Each connection is served with the [serve](TestBroker::serve) method. The broker object
+/// is cloneable, all clones share the same subscriptions.
+///
+#[derive(Clone, Default)]
+pub struct TestBroker {
+    state: Arc<Mutex<BrokerState>>,
+}
+
+impl TestBroker {
+    /// Creates a new [TestBroker] instance.
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves a single client connection until the client disconnects or closes the stream.
+    /// Multiple connections may be served concurrently.
+    ///
+    /// # Arguments
+    /// * `rx` - Read half of the stream, must be [AsyncRead] + [Unpin].
+    /// * `tx` - Write half of the stream, must be [AsyncWrite] + [Unpin].
+    ///
+    pub async fn serve<RxStreamT, TxStreamT>(
+        &self,
+        (rx, mut tx): (RxStreamT, TxStreamT),
+    ) -> Result<(), MqttError>
+    where
+        RxStreamT: AsyncRead + Unpin,
+        TxStreamT: AsyncWrite + Unpin,
+    {
+        let (sender, receiver) = mpsc::unbounded();
+        let connection = {
+            let mut state = self.lock();
+            state.next_connection += 1;
+            state.next_connection
+        };
+
+        let incoming = stream::unfold(rx, |mut rx| async move {
+            match read_packet(&mut rx).await {
+                Ok(Some(packet)) => Some((Ok(packet), rx)),
+                Ok(None) => None,
+                Err(err) => Some((Err(err), rx)),
+            }
+        })
+        .map(Event::Incoming)
+        .chain(stream::once(future::ready(Event::Closed)));
+
+        let events = stream::select(incoming, receiver.map(Event::Outgoing));
+        futures::pin_mut!(events);
+
+        let result = async {
+            while let Some(event) = events.next().await {
+                match event {
+                    Event::Incoming(packet) => {
+                        let (fixed_hdr, body) = packet?;
+                        if !self
+                            .handle_packet(connection, &sender, &mut tx, fixed_hdr, body)
+                            .await?
+                        {
+                            break;
+                        }
+                    }
+                    Event::Outgoing(packet) => tx.write_all(&packet).await?,
+                    Event::Closed => break,
+                }
+            }
+
+            Ok(())
+        }
+        .await;
+
+        self.lock()
+            .subscribers
+            .retain(|subscriber| subscriber.connection != connection);
+
+        result
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BrokerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Returns `false` when the connection is to be closed.
+    async fn handle_packet<TxStreamT>(
+        &self,
+        connection: usize,
+        sender: &mpsc::UnboundedSender<Bytes>,
+        tx: &mut TxStreamT,
+        fixed_hdr: u8,
+        body: Bytes,
+    ) -> Result<bool, MqttError>
+    where
+        TxStreamT: AsyncWrite + Unpin,
+    {
+        let mut reader = Reader(body);
+
+        match fixed_hdr >> 4 {
+            CONNECT => {
+                // Flags, reason, properties with maximum QoS.
+                let body = [0x00, 0x00, 0x02, MAXIMUM_QOS, 0x01];
+                tx.write_all(&encode_packet(CONNACK << 4, &body)).await?;
+            }
+            PUBLISH => {
+                let qos = (fixed_hdr >> 1) & 0x03;
+                let topic = reader.string()?;
+                let packet_identifier = if qos > 0 { Some(reader.u16()?) } else { None };
+                reader.properties()?;
+                let payload = reader.rest();
+
+                if qos > 1 {
+                    tx.write_all(&encode_packet(DISCONNECT << 4, &[QOS_NOT_SUPPORTED]))
+                        .await?;
+                    return Ok(false);
+                }
+
+                self.route(connection, &topic, &payload);
+
+                if let Some(packet_identifier) = packet_identifier {
+                    tx.write_all(&encode_packet(
+                        PUBACK << 4,
+                        &packet_identifier.to_be_bytes(),
+                    ))
+                    .await?;
+                }
+            }
+            SUBSCRIBE => {
+                let packet_identifier = reader.u16()?;
+                let subscription_identifier = subscription_identifier(reader.properties()?)?;
+
+                let mut body = BytesMut::new();
+                body.put_u16(packet_identifier);
+                body.put_u8(0); // Property length
+
+                while !reader.is_empty() {
+                    let filter = reader.string()?;
+                    let options = reader.u8()?;
+
+                    let mut state = self.lock();
+                    state.subscribers.retain(|subscriber| {
+                        subscriber.connection != connection || subscriber.filter != filter
+                    });
+                    state.subscribers.push(Subscriber {
+                        connection,
+                        filter,
+                        subscription_identifier,
+                        no_local: options & 0x04 != 0,
+                        sender: sender.clone(),
+                    });
+
+                    body.put_u8((options & 0x03).min(1)); // Granted QoS
+                }
+
+                tx.write_all(&encode_packet(SUBACK << 4, &body)).await?;
+            }
+            UNSUBSCRIBE => {
+                let packet_identifier = reader.u16()?;
+                reader.properties()?;
+
+                let mut body = BytesMut::new();
+                body.put_u16(packet_identifier);
+                body.put_u8(0); // Property length
+
+                while !reader.is_empty() {
+                    let filter = reader.string()?;
+
+                    let mut state = self.lock();
+                    let count = state.subscribers.len();
+                    state.subscribers.retain(|subscriber| {
+                        subscriber.connection != connection || subscriber.filter != filter
+                    });
+
+                    if state.subscribers.len() != count {
+                        body.put_u8(0x00); // Success
+                    } else {
+                        body.put_u8(0x11); // No subscription existed
+                    }
+                }
+
+                tx.write_all(&encode_packet(UNSUBACK << 4, &body)).await?;
+            }
+            PINGREQ => {
+                tx.write_all(&encode_packet(PINGRESP << 4, &[])).await?;
+            }
+            DISCONNECT => {
+                return Ok(false);
+            }
+            _ => {
+                return Err(CodecError::from(InvalidPacketHeader).into());
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn route(&self, connection: usize, topic: &str, payload: &[u8]) {
+        let state = self.lock();
+
+        for subscriber in state.subscribers.iter().filter(|subscriber| {
+            !(subscriber.no_local && subscriber.connection == connection)
+                && topic_matches(&subscriber.filter, topic)
+        }) {
+            let mut properties = BytesMut::new();
+            if let Some(subscription_identifier) = subscriber.subscription_identifier {
+                properties.put_u8(SUBSCRIPTION_IDENTIFIER);
+                VarSizeInt::try_from(subscription_identifier)
+                    .unwrap()
+                    .encode(&mut properties);
+            }
+
+            let mut body = BytesMut::new();
+            body.put_u16(topic.len() as u16);
+            body.put_slice(topic.as_bytes());
+            VarSizeInt::try_from(properties.len())
+                .unwrap()
+                .encode(&mut body);
+            body.put_slice(&properties);
+            body.put_slice(payload);
+
+            // Subscriber may have already disconnected.
+            let _ = subscriber
+                .sender
+                .unbounded_send(encode_packet(PUBLISH << 4, &body));
+        }
+    }
+}
+
+fn subscription_identifier(mut properties: Reader) -> Result<Option<u32>, CodecError> {
+    let mut subscription_identifier = None;
+
+    while !properties.is_empty() {
+        match properties.u8()? {
+            SUBSCRIPTION_IDENTIFIER => {
+                subscription_identifier = Some(properties.var_size_int()?);
+            }
+            USER_PROPERTY => {
+                properties.string()?;
+                properties.string()?;
+            }
+            _ => return Err(UnexpectedProperty.into()),
+        }
+    }
+
+    Ok(subscription_identifier)
+}
+
+fn topic_matches(filter: &str, topic: &str) -> bool {
+    // Topics starting with '$' are not matched by wildcards on the first level.
+    if topic.starts_with('$') && (filter.starts_with('+') || filter.starts_with('#')) {
+        return false;
+    }
+
+    let mut filter_levels = filter.split('/');
+    let mut topic_levels = topic.split('/');
+
+    loop {
+        match (filter_levels.next(), topic_levels.next()) {
+            (Some("#"), _) => return true,
+            (Some("+"), Some(_)) => continue,
+            (Some(filter_level), Some(topic_level)) if filter_level == topic_level => continue,
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+fn encode_packet(fixed_hdr: u8, body: &[u8]) -> Bytes {
+    let remaining_len = VarSizeInt::try_from(body.len()).unwrap();
+
+    let mut buf = BytesMut::with_capacity(1 + remaining_len.len() + body.len());
+    buf.put_u8(fixed_hdr);
+    remaining_len.encode(&mut buf);
+    buf.put_slice(body);
+    buf.freeze()
+}
+
+async fn read_packet<RxStreamT>(rx: &mut RxStreamT) -> io::Result<Option<(u8, Bytes)>>
+where
+    RxStreamT: AsyncRead + Unpin,
+{
+    let mut fixed_hdr = [0u8; 1];
+    if rx.read(&mut fixed_hdr).await? == 0 {
+        return Ok(None); // EOF
+    }
+
+    let mut remaining_len = Vec::with_capacity(4);
+    loop {
+        let mut byte = [0u8; 1];
+        rx.read_exact(&mut byte).await?;
+        remaining_len.push(byte[0]);
+
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+
+    let remaining_len = VarSizeInt::try_from(remaining_len.as_slice())
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+
+    let mut body = vec![0u8; remaining_len.value() as usize];
+    rx.read_exact(&mut body).await?;
+
+    Ok(Some((fixed_hdr[0], Bytes::from(body))))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        ConnectOpts, Context, DisconnectOpts, PublishOpts, QoS, SubscribeOpts, SubscriptionOpts,
+    };
+    use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+    #[test]
+    fn topic_filters() {
+        assert!(topic_matches("a/b", "a/b"));
+        assert!(topic_matches("a/+", "a/b"));
+        assert!(topic_matches("a/#", "a/b/c"));
+        assert!(topic_matches("a/#", "a"));
+        assert!(topic_matches("#", "a/b"));
+        assert!(!topic_matches("a/+", "a/b/c"));
+        assert!(!topic_matches("a/b", "a/c"));
+        assert!(!topic_matches("#", "$SYS/a"));
+    }
+
+    #[tokio::test]
+    async fn publish_subscribe() {
+        let broker = TestBroker::new();
+        let (client, server) = tokio::io::duplex(4096);
+        let (srx, stx) = tokio::io::split(server);
+        let (crx, ctx) = tokio::io::split(client);
+
+        let (mut context, mut handle) = Context::new();
+        context.set_up((crx.compat(), ctx.compat_write()));
+
+        let client = async move {
+            context
+                .connect(ConnectOpts::new().client_identifier("test"))
+                .await
+                .unwrap();
+
+            let test = async {
+                let mut stream = handle
+                    .subscribe(SubscribeOpts::new().subscription("a/+", SubscriptionOpts::new()))
+                    .await
+                    .unwrap()
+                    .stream();
+
+                handle
+                    .publish(
+                        PublishOpts::new()
+                            .topic_name("a/b")
+                            .qos(QoS::AtLeastOnce)
+                            .payload(b"test"),
+                    )
+                    .await
+                    .unwrap();
+
+                let msg = stream.next().await.unwrap();
+                assert_eq!(msg.topic_name(), "a/b");
+                assert_eq!(msg.payload(), b"test");
+
+                handle.disconnect(DisconnectOpts::new()).await.unwrap();
+            };
+
+            match future::select(Box::pin(context.run()), Box::pin(test)).await {
+                future::Either::Left((result, _)) => panic!("context exited: {:?}", result.err()),
+                future::Either::Right(_) => {}
+            }
+        };
+
+        let (result, _) = futures::join!(broker.serve((srx.compat(), stx.compat_write())), client);
+        assert!(result.is_ok());
+    }
+}
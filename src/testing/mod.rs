@@ -0,0 +1,45 @@
+mod broker;
+
+pub use broker::TestBroker;
+
+#[cfg(test)]
+use crate::{client::error::MqttError, ConnectOpts, Context, ContextHandle};
+#[cfg(test)]
+use futures::{AsyncReadExt, AsyncWriteExt};
+#[cfg(test)]
+use tokio::{io::DuplexStream, task::JoinHandle};
+#[cfg(test)]
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+/// Connects a [Context] over an in-memory stream and spawns its [run](Context::run) loop.
+/// The CONNECT packet is answered with a bare CONNACK and the connection is established before
+/// returning. The server end of the stream is then either driven by the test directly or handed
+/// to the [TestBroker].
+///
+#[cfg(test)]
+pub(crate) async fn spawn_pair() -> (
+    ContextHandle,
+    Compat<DuplexStream>,
+    JoinHandle<Result<(), MqttError>>,
+) {
+    let (client, server) = tokio::io::duplex(4096);
+    let (crx, ctx) = tokio::io::split(client);
+    let mut server = server.compat();
+
+    let (mut context, handle) = Context::new();
+    context.set_up((crx.compat(), ctx.compat_write()));
+
+    let context = tokio::spawn(async move {
+        context
+            .connect(ConnectOpts::new().client_identifier("test"))
+            .await?;
+        context.run().await
+    });
+
+    let mut buf = [0u8; 64];
+    let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+    server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+    handle.wait_until_connected().await.unwrap();
+
+    (handle, server, context)
+}
@@ -0,0 +1,239 @@
+//! Integration tests against a local mosquitto broker. Every test spawns its own broker
+//! instance on a free port and is skipped when the `mosquitto` binary is not found on PATH.
+
+use futures::{future, StreamExt};
+use poster::{
+    error::MqttError, ConnectOpts, Context, ContextHandle, DisconnectOpts, PublishOpts, QoS,
+    SubscribeOpts, SubscriptionOpts,
+};
+use std::{
+    future::Future,
+    io,
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    process::{Child, Command, Stdio},
+    thread,
+    time::Duration,
+};
+use tokio::{net, task::JoinHandle};
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Broker {
+    process: Child,
+    port: u16,
+}
+
+impl Broker {
+    /// Spawns the broker, returning [None] if mosquitto is not installed.
+    ///
+    fn spawn() -> Option<Self> {
+        let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .and_then(|listener| listener.local_addr())
+            .map(|addr| addr.port())
+            .unwrap();
+
+        let process = match Command::new("mosquitto")
+            .args(["-p", &port.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(process) => process,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                eprintln!("mosquitto not found on PATH, skipping");
+                return None;
+            }
+            Err(err) => panic!("failed to spawn mosquitto: {}", err),
+        };
+
+        let broker = Self { process, port };
+
+        for _ in 0..50 {
+            if TcpStream::connect((Ipv4Addr::LOCALHOST, port)).is_ok() {
+                return Some(broker);
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+
+        panic!("mosquitto did not start listening on port {}", port);
+    }
+
+    fn kill(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+impl Drop for Broker {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+async fn timeout<F: Future>(fut: F) -> F::Output {
+    match future::select(Box::pin(fut), futures_timer::Delay::new(TIMEOUT)).await {
+        future::Either::Left((output, _)) => output,
+        future::Either::Right(_) => panic!("timed out"),
+    }
+}
+
+/// Connects the client and spawns the task running its context.
+///
+async fn connect(
+    broker: &Broker,
+    opts: ConnectOpts<'_>,
+) -> (ContextHandle, JoinHandle<Result<(), MqttError>>) {
+    let stream = net::TcpStream::connect((Ipv4Addr::LOCALHOST, broker.port))
+        .await
+        .unwrap();
+    let (rx, tx) = stream.into_split();
+
+    let (mut context, handle) = Context::new();
+    context
+        .set_up((rx.compat(), tx.compat_write()))
+        .connect(opts)
+        .await
+        .unwrap();
+
+    (handle, tokio::spawn(async move { context.run().await }))
+}
+
+#[tokio::test]
+async fn publish_subscribe_all_qos() {
+    let Some(broker) = Broker::spawn() else {
+        return;
+    };
+
+    let (mut subscriber, _) = connect(&broker, ConnectOpts::new().client_identifier("sub")).await;
+    let (mut publisher, _) = connect(&broker, ConnectOpts::new().client_identifier("pub")).await;
+
+    for qos in [QoS::AtMostOnce, QoS::AtLeastOnce, QoS::ExactlyOnce] {
+        let topic = format!("poster/qos/{}", u8::from(qos));
+        let mut stream = subscriber
+            .subscribe(SubscribeOpts::new().subscription(
+                &topic,
+                SubscriptionOpts::new().maximum_qos(QoS::ExactlyOnce),
+            ))
+            .await
+            .unwrap()
+            .stream();
+
+        for idx in 0..5u8 {
+            publisher
+                .publish(
+                    PublishOpts::new()
+                        .topic_name(&topic)
+                        .qos(qos)
+                        .payload(&[idx]),
+                )
+                .await
+                .unwrap();
+        }
+
+        for idx in 0..5u8 {
+            let msg = timeout(stream.next()).await.unwrap();
+            assert_eq!(msg.topic_name(), topic);
+            assert_eq!(msg.qos(), qos);
+            assert_eq!(msg.payload(), [idx]);
+        }
+    }
+}
+
+#[tokio::test]
+async fn retained_message() {
+    let Some(broker) = Broker::spawn() else {
+        return;
+    };
+
+    let (mut publisher, _) = connect(&broker, ConnectOpts::new().client_identifier("pub")).await;
+    publisher
+        .publish_retained("poster/retain", b"retained")
+        .await
+        .unwrap();
+
+    let (mut subscriber, _) = connect(&broker, ConnectOpts::new().client_identifier("sub")).await;
+    let mut stream = subscriber
+        .subscribe(SubscribeOpts::new().subscription("poster/retain", SubscriptionOpts::new()))
+        .await
+        .unwrap()
+        .stream();
+
+    let msg = timeout(stream.next()).await.unwrap();
+    assert!(msg.retain());
+    assert_eq!(msg.payload(), b"retained");
+
+    publisher.clear_retained("poster/retain").await.unwrap();
+
+    let (mut late, _) = connect(&broker, ConnectOpts::new().client_identifier("late")).await;
+    let msg = late
+        .subscribe_once("poster/retain", Duration::from_millis(500))
+        .await
+        .unwrap();
+    assert!(msg.is_none());
+}
+
+#[tokio::test]
+async fn graceful_disconnect() {
+    let Some(broker) = Broker::spawn() else {
+        return;
+    };
+
+    let (mut handle, task) = connect(&broker, ConnectOpts::new().client_identifier("test")).await;
+    handle.disconnect(DisconnectOpts::new()).await.unwrap();
+
+    // The broker closes the connection after receiving DISCONNECT.
+    match timeout(task).await.unwrap() {
+        Ok(()) | Err(MqttError::SocketClosed(_)) => {}
+        Err(err) => panic!("unexpected error: {}", err),
+    }
+
+    assert!(handle
+        .publish(PublishOpts::new().topic_name("poster/closed"))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn will_on_connection_loss() {
+    let Some(broker) = Broker::spawn() else {
+        return;
+    };
+
+    let (mut subscriber, _) = connect(&broker, ConnectOpts::new().client_identifier("sub")).await;
+    let mut stream = subscriber
+        .subscribe(SubscribeOpts::new().subscription("poster/will", SubscriptionOpts::new()))
+        .await
+        .unwrap()
+        .stream();
+
+    let (_handle, task) = connect(
+        &broker,
+        ConnectOpts::new()
+            .client_identifier("will")
+            .will_topic("poster/will")
+            .will_payload(b"gone"),
+    )
+    .await;
+
+    // Dropping the context closes the socket without sending DISCONNECT.
+    task.abort();
+
+    let msg = timeout(stream.next()).await.unwrap();
+    assert_eq!(msg.payload(), b"gone");
+}
+
+#[tokio::test]
+async fn broker_shutdown() {
+    let Some(mut broker) = Broker::spawn() else {
+        return;
+    };
+
+    let (_handle, task) = connect(&broker, ConnectOpts::new().client_identifier("test")).await;
+    broker.kill();
+
+    assert!(matches!(
+        timeout(task).await.unwrap(),
+        Err(MqttError::SocketClosed(_))
+    ));
+}
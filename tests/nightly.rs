@@ -0,0 +1,66 @@
+#![feature(async_iterator, async_for_loop)]
+
+use futures::future;
+use poster::{ConnectOpts, Context, SubscribeOpts, SubscriptionOpts};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+#[tokio::test]
+async fn subscription_for_await() {
+    let (client, mut server) = tokio::io::duplex(1024);
+    let (crx, ctx) = tokio::io::split(client);
+
+    let (mut context, mut handle) = Context::new();
+    context.set_up((crx.compat(), ctx.compat_write()));
+
+    let client = async move {
+        context
+            .connect(ConnectOpts::new().client_identifier("test"))
+            .await
+            .unwrap();
+        context.run().await
+    };
+
+    let test = async move {
+        let stream = handle
+            .subscribe(SubscribeOpts::new().subscription("a", SubscriptionOpts::new()))
+            .await
+            .unwrap()
+            .stream();
+
+        let mut payloads = Vec::new();
+        for await msg in stream {
+            payloads.push(msg.payload().to_vec());
+            if payloads.len() == 2 {
+                break;
+            }
+        }
+
+        assert_eq!(payloads, [b"ok".to_vec(), b"ko".to_vec()]);
+    };
+
+    let server = async move {
+        let mut buf = [0u8; 64];
+        let _ = server.read(&mut buf).await.unwrap(); // CONNECT
+        server.write_all(&[0x20, 3, 0, 0, 0]).await.unwrap(); // CONNACK
+
+        let _ = server.read(&mut buf).await.unwrap(); // SUBSCRIBE
+        server
+            .write_all(&[0x90, 4, buf[2], buf[3], 0, 0]) // SUBACK
+            .await
+            .unwrap();
+
+        for payload in [b"ok", b"ko"] {
+            server
+                .write_all(&[0x30, 8, 0, 1, b'a', 2, 0x0b, 1, payload[0], payload[1]]) // PUBLISH
+                .await
+                .unwrap();
+        }
+        future::pending::<()>().await; // Keep the connection open
+    };
+
+    match future::select(Box::pin(test), Box::pin(future::join(client, server))).await {
+        future::Either::Left(_) => {}
+        future::Either::Right(_) => unreachable!(),
+    }
+}